@@ -0,0 +1,15 @@
+ALTER TABLE rulesets ADD COLUMN override_filename TEXT;
+ALTER TABLE rulesets ADD COLUMN note TEXT;
+ALTER TABLE rulesets ADD COLUMN superseded BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS ruleset_audit_log
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    ruleset_id  INTEGER NOT NULL REFERENCES rulesets (id),
+    actor       TEXT NOT NULL,
+    action      TEXT NOT NULL,
+    details     TEXT NOT NULL,
+    created_at  TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS ruleset_audit_log_ruleset_id ON ruleset_audit_log (ruleset_id);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS submissions
+(
+    id              INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    ruleset_id      INTEGER NOT NULL REFERENCES rulesets (id),
+    player          TEXT NOT NULL,
+    time_seconds    INTEGER NOT NULL,
+    goal            TEXT NOT NULL,
+    vod_url         TEXT,
+    notes           TEXT,
+    -- one of 'pending', 'verified', 'rejected'
+    status          TEXT NOT NULL DEFAULT 'pending',
+    submitted_at    TEXT NOT NULL,
+    reviewed_by     TEXT,
+    reviewed_at     TEXT
+);
+
+CREATE INDEX IF NOT EXISTS submissions_ruleset_id ON submissions (ruleset_id, status, time_seconds);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS seasons
+(
+    id                      INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name                    TEXT NOT NULL,
+    -- inclusive range of periods, as days from CE like rulesets.id
+    start_day               INTEGER NOT NULL,
+    end_day                 INTEGER NOT NULL,
+    goal                    TEXT NOT NULL,
+    -- comma separated points for 1st, 2nd, ...
+    placement_points        TEXT NOT NULL,
+    time_points             INTEGER NOT NULL DEFAULT 0,
+    participation_points    INTEGER NOT NULL DEFAULT 0,
+    created_by              TEXT NOT NULL,
+    created_at              TEXT NOT NULL
+);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS submission_techniques
+(
+    submission_id   INTEGER NOT NULL REFERENCES submissions (id),
+    technique       TEXT NOT NULL,
+    PRIMARY KEY (submission_id, technique)
+);
+
+CREATE INDEX IF NOT EXISTS submission_techniques_technique ON submission_techniques (technique);
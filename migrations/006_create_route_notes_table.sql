@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS route_notes
+(
+    id              INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    ruleset_id      INTEGER NOT NULL REFERENCES rulesets (id),
+    author          TEXT NOT NULL,
+    -- markdown, rendered with raw HTML escaped
+    body            TEXT NOT NULL,
+    -- one of 'visible', 'hidden'
+    status          TEXT NOT NULL DEFAULT 'visible',
+    posted_at       TEXT NOT NULL,
+    moderated_by    TEXT,
+    moderated_at    TEXT
+);
+
+CREATE INDEX IF NOT EXISTS route_notes_ruleset_id ON route_notes (ruleset_id, status);
@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS webhooks
+(
+    id              INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    url             TEXT NOT NULL,
+    -- shared secret for the HMAC-SHA256 signature header
+    secret          TEXT NOT NULL,
+    active          BOOLEAN NOT NULL DEFAULT 1,
+    created_by      TEXT NOT NULL,
+    created_at      TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries
+(
+    id              INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    webhook_id      INTEGER NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+    event           TEXT NOT NULL,
+    ruleset_id      INTEGER NOT NULL,
+    payload         TEXT NOT NULL,
+    -- one of 'pending', 'delivered', 'failed'
+    status          TEXT NOT NULL DEFAULT 'pending',
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    last_status_code INTEGER,
+    last_error      TEXT,
+    created_at      TEXT NOT NULL,
+    updated_at      TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_webhook_id ON webhook_deliveries (webhook_id, id);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS custom_rulesets
+(
+    id              INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name            TEXT NOT NULL,
+    -- 'preset' rulesets are offered as comparisons everywhere; 'one_off' ones only have their own page
+    kind            TEXT NOT NULL DEFAULT 'one_off',
+    -- the format it was imported from: 'json', 'yaml', 'csv' or 'code'
+    source_format   TEXT NOT NULL,
+    -- the ruleset, serialized as JSON
+    ruleset         TEXT NOT NULL,
+    imported_by     TEXT NOT NULL,
+    imported_at     TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS custom_rulesets_preset_name ON custom_rulesets (name) WHERE kind = 'preset';
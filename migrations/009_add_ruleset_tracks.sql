@@ -0,0 +1,7 @@
+-- Several tracks can publish in the same period, so the period's day gets its own column. The
+-- built-in weekly track keeps using the day as its id; other tracks are numbered from 1000000000.
+ALTER TABLE rulesets ADD COLUMN track TEXT NOT NULL DEFAULT 'weekly';
+ALTER TABLE rulesets ADD COLUMN day INTEGER NOT NULL DEFAULT 0;
+UPDATE rulesets SET day = id;
+
+CREATE UNIQUE INDEX IF NOT EXISTS rulesets_track_day ON rulesets (track, day);
@@ -0,0 +1,6 @@
+-- Each game publishes its own tracks; everything saved before there were several games is for the
+-- default one (alttp). Custom rulesets carry their game in their JSON.
+ALTER TABLE rulesets ADD COLUMN game TEXT NOT NULL DEFAULT 'alttp';
+
+DROP INDEX IF EXISTS rulesets_track_day;
+CREATE UNIQUE INDEX IF NOT EXISTS rulesets_game_track_day ON rulesets (game, track, day);
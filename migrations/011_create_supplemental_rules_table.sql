@@ -0,0 +1,22 @@
+-- Rulings on techniques outside the randomized catalog. Each row is one version of a rule, in force
+-- for the periods starting from effective_from up to (not including) effective_until, both days
+-- from CE like rulesets.day. A game's rules start out as its supplemental.yaml.
+CREATE TABLE IF NOT EXISTS supplemental_rules
+(
+    id              INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    game            TEXT NOT NULL,
+    name            TEXT NOT NULL,
+    description     TEXT NOT NULL DEFAULT '',
+    -- 'ALLOWED' or 'DISALLOWED'
+    allowed         TEXT NOT NULL,
+    effective_from  INTEGER NOT NULL,
+    effective_until INTEGER,
+    created_by      TEXT NOT NULL,
+    created_at      TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS supplemental_rules_game ON supplemental_rules (game, effective_from);
+
+-- The supplemental rules in force when a ruleset was saved, as JSON. Rulesets saved before this
+-- look them up by their day instead.
+ALTER TABLE rulesets ADD COLUMN supplemental TEXT;
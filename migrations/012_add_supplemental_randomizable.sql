@@ -0,0 +1,3 @@
+-- A randomizable supplemental rule is rolled like a technique (its ruling is the default weeklies
+-- start from) rather than being the same every week. See crate::web::supplemental.
+ALTER TABLE supplemental_rules ADD COLUMN randomizable BOOLEAN NOT NULL DEFAULT 0;
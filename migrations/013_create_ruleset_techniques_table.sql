@@ -0,0 +1,15 @@
+-- The value of each technique in a saved ruleset, as in force (the override if there is one), so
+-- history can be searched by technique. Filled in when rulesets are saved or overridden; rulesets
+-- saved before this are added at startup.
+CREATE TABLE IF NOT EXISTS ruleset_techniques
+(
+    ruleset_id INTEGER NOT NULL REFERENCES rulesets (id),
+    technique  TEXT NOT NULL,
+    -- 'ALLOWED', 'DISALLOWED' or 'UNSPECIFIED'
+    allowed    TEXT NOT NULL,
+    PRIMARY KEY (ruleset_id, technique)
+);
+
+CREATE INDEX IF NOT EXISTS ruleset_techniques_value ON ruleset_techniques (technique, allowed, ruleset_id);
+
+CREATE INDEX IF NOT EXISTS rulesets_track_day ON rulesets (game, track, day);
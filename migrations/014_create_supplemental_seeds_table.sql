@@ -0,0 +1,10 @@
+-- The games whose supplemental.yaml has been copied into supplemental_rules, so a game whose rules
+-- organizers have all deleted isn't given them again. Games that already have rules count as done.
+CREATE TABLE IF NOT EXISTS supplemental_seeds
+(
+    game      TEXT PRIMARY KEY NOT NULL,
+    seeded_at TEXT NOT NULL
+);
+
+INSERT OR IGNORE INTO supplemental_seeds (game, seeded_at)
+SELECT game, MIN(created_at) FROM supplemental_rules GROUP BY game;
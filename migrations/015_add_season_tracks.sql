@@ -0,0 +1,14 @@
+-- A season scores one track of one game; seasons made before this were for the default game's
+-- built-in weekly.
+ALTER TABLE seasons ADD COLUMN game TEXT NOT NULL DEFAULT 'alttp';
+ALTER TABLE seasons ADD COLUMN track TEXT NOT NULL DEFAULT 'weekly';
+
+-- Goals are typed freely, so the same goal is now stored with the spelling it was first given,
+-- ignoring case and surrounding spaces. Bring the goals stored before that into line.
+UPDATE submissions SET goal = (
+    SELECT f.goal FROM submissions f WHERE LOWER(TRIM(f.goal)) = LOWER(TRIM(submissions.goal)) ORDER BY f.id LIMIT 1
+);
+UPDATE seasons SET goal = COALESCE(
+    (SELECT s.goal FROM submissions s WHERE LOWER(s.goal) = LOWER(TRIM(seasons.goal)) ORDER BY s.id LIMIT 1),
+    TRIM(goal)
+);
@@ -0,0 +1,25 @@
+-- Rulesets get their ids from AUTOINCREMENT, in the order they're saved, instead of the built-in
+-- track using the period's day and other tracks counting up from 1000000000. Weeks are looked up
+-- by (game, track, day); saved rulesets keep the ids they had. SQLite can't add AUTOINCREMENT to a
+-- column, so the table is rebuilt.
+CREATE TABLE rulesets_new
+(
+    id                INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name              TEXT,
+    filename          TEXT,
+    override_filename TEXT,
+    note              TEXT,
+    superseded        BOOLEAN NOT NULL DEFAULT 0,
+    track             TEXT NOT NULL DEFAULT 'weekly',
+    day               INTEGER NOT NULL DEFAULT 0,
+    game              TEXT NOT NULL DEFAULT 'alttp',
+    supplemental      TEXT
+);
+
+INSERT INTO rulesets_new (id, name, filename, override_filename, note, superseded, track, day, game, supplemental)
+SELECT id, name, filename, override_filename, note, superseded, track, day, game, supplemental FROM rulesets;
+
+DROP TABLE rulesets;
+ALTER TABLE rulesets_new RENAME TO rulesets;
+
+CREATE UNIQUE INDEX IF NOT EXISTS rulesets_game_track_day ON rulesets (game, track, day);
@@ -0,0 +1,13 @@
+-- Organizers' edits to seasons, like ruleset_audit_log. No foreign key: a deleted season keeps its
+-- entries.
+CREATE TABLE IF NOT EXISTS season_audit_log
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    season_id   INTEGER NOT NULL,
+    actor       TEXT NOT NULL,
+    action      TEXT NOT NULL,
+    details     TEXT NOT NULL,
+    created_at  TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS season_audit_log_season_id ON season_audit_log (season_id);
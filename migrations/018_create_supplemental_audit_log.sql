@@ -0,0 +1,13 @@
+-- Organizers' corrections and deletions of supplemental rule versions, like season_audit_log. New
+-- versions already say who added them.
+CREATE TABLE IF NOT EXISTS supplemental_audit_log
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    rule_id     INTEGER NOT NULL,
+    actor       TEXT NOT NULL,
+    action      TEXT NOT NULL,
+    details     TEXT NOT NULL,
+    created_at  TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS supplemental_audit_log_rule_id ON supplemental_audit_log (rule_id);
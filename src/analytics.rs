@@ -0,0 +1,153 @@
+//! Route analytics: how often each technique gets used when it's legal, and how its legality lines
+//! up with finish times. Fed from verified submissions and the rulesets they were run under.
+
+use crate::rules::IsAllowed;
+use crate::techniques::{Catalog, Ruleset};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One verified run, with the techniques the runner said they used.
+pub(crate) struct RunRecord {
+    pub(crate) ruleset_id: u32,
+    pub(crate) time_seconds: u32,
+    pub(crate) used: Vec<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct TechniqueStats {
+    pub(crate) technique: String,
+    /// Rulesets in which this technique was legal (anything but `DISALLOWED`).
+    pub(crate) weeks_legal: usize,
+    pub(crate) weeks: usize,
+    /// Runs done while the technique was legal, and how many of those declared using it.
+    pub(crate) runs_legal: usize,
+    pub(crate) uses: usize,
+    pub(crate) usage_rate: Option<f64>,
+    pub(crate) runs_banned: usize,
+    pub(crate) mean_time_legal: Option<f64>,
+    pub(crate) mean_time_banned: Option<f64>,
+    /// Pearson correlation between legality (1 legal, 0 banned) and finish time. Negative means
+    /// runs tend to be faster when the technique is legal.
+    pub(crate) correlation: Option<f64>,
+}
+
+fn mean(xs: &[f64]) -> Option<f64> {
+    if xs.is_empty() {
+        None
+    } else {
+        Some(xs.iter().sum::<f64>() / xs.len() as f64)
+    }
+}
+
+fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    let n = pairs.len() as f64;
+    let mx = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let my = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+    let cov: f64 = pairs.iter().map(|(x, y)| (x - mx) * (y - my)).sum();
+    let vx: f64 = pairs.iter().map(|(x, _)| (x - mx).powi(2)).sum();
+    let vy: f64 = pairs.iter().map(|(_, y)| (y - my).powi(2)).sum();
+    if vx == 0.0 || vy == 0.0 {
+        None
+    } else {
+        Some(cov / (vx.sqrt() * vy.sqrt()))
+    }
+}
+
+/// Per-technique stats for `catalog`'s techniques, in catalog order. Runs whose ruleset isn't in
+/// `rulesets` are skipped.
+pub(crate) fn technique_stats(catalog: &Catalog, rulesets: &HashMap<u32, Ruleset>, runs: &[RunRecord]) -> Vec<TechniqueStats> {
+    catalog
+        .techniques
+        .iter()
+        .map(|&technique| {
+            let legal = |r: &Ruleset| r.get(technique).is_some_and(|a| a != IsAllowed::DISALLOWED);
+            let weeks_legal = rulesets.values().filter(|r| legal(r)).count();
+
+            let mut legal_times = vec![];
+            let mut banned_times = vec![];
+            let mut uses = 0;
+            for run in runs {
+                let ruleset = match rulesets.get(&run.ruleset_id) {
+                    Some(r) => r,
+                    None => continue,
+                };
+                if legal(ruleset) {
+                    legal_times.push(run.time_seconds as f64);
+                    if run.used.iter().any(|u| u == technique) {
+                        uses += 1;
+                    }
+                } else {
+                    banned_times.push(run.time_seconds as f64);
+                }
+            }
+            let pairs: Vec<(f64, f64)> = legal_times
+                .iter()
+                .map(|t| (1.0, *t))
+                .chain(banned_times.iter().map(|t| (0.0, *t)))
+                .collect();
+
+            TechniqueStats {
+                technique: technique.to_string(),
+                weeks_legal,
+                weeks: rulesets.len(),
+                runs_legal: legal_times.len(),
+                uses,
+                usage_rate: if legal_times.is_empty() {
+                    None
+                } else {
+                    Some(uses as f64 / legal_times.len() as f64)
+                },
+                runs_banned: banned_times.len(),
+                mean_time_legal: mean(&legal_times),
+                mean_time_banned: mean(&banned_times),
+                correlation: correlation(&pairs),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{technique_stats, RunRecord};
+    use crate::rules::{IsAllowed, NMGRules};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_technique_stats() {
+        let mut clipping_week = NMGRules.clone();
+        clipping_week["OverworldClipping"] = IsAllowed::ALLOWED;
+        let mut rulesets = HashMap::new();
+        rulesets.insert(1, NMGRules.clone());
+        rulesets.insert(2, clipping_week);
+
+        let run = |ruleset_id, time_seconds, used: &[&str]| RunRecord {
+            ruleset_id,
+            time_seconds,
+            used: used.iter().map(|u| u.to_string()).collect(),
+        };
+        let runs = vec![
+            run(1, 5000, &[]),
+            run(1, 5200, &["Hover"]),
+            run(2, 4000, &["OverworldClipping"]),
+            run(2, 4400, &[]),
+            // not a ruleset we know about
+            run(3, 1, &["OverworldClipping"]),
+        ];
+        let stats = technique_stats(NMGRules.catalog(), &rulesets, &runs);
+        let clipping = stats.iter().find(|s| s.technique == "OverworldClipping").unwrap();
+        assert_eq!(1, clipping.weeks_legal);
+        assert_eq!(2, clipping.weeks);
+        assert_eq!(2, clipping.runs_legal);
+        assert_eq!(1, clipping.uses);
+        assert_eq!(Some(0.5), clipping.usage_rate);
+        assert_eq!(Some(4200.0), clipping.mean_time_legal);
+        assert_eq!(Some(5100.0), clipping.mean_time_banned);
+        assert!(clipping.correlation.unwrap() < -0.9);
+
+        let hover = stats.iter().find(|s| s.technique == "Hover").unwrap();
+        assert_eq!(4, hover.runs_legal);
+        assert_eq!(Some(0.25), hover.usage_rate);
+        // legal every week, so there's nothing to correlate against
+        assert_eq!(None, hover.correlation);
+    }
+}
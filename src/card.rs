@@ -0,0 +1,207 @@
+//! The "rules card": a ruleset drawn as an image, for link previews and for sharing in chat.
+//! The SVG is built by hand; the PNG is that SVG rasterized with resvg, so no browser is needed.
+
+use crate::rules::IsAllowed;
+use crate::techniques::Ruleset;
+use lazy_static::lazy_static;
+use resvg::{tiny_skia, usvg};
+use std::sync::Arc;
+
+/// OpenGraph's recommended preview size. Cards for catalogs too big for it are made taller.
+pub(crate) const CARD_WIDTH: u32 = 1200;
+pub(crate) const CARD_HEIGHT: u32 = 630;
+
+const MARGIN: u32 = 40;
+const HEADER_HEIGHT: u32 = 100;
+const COLUMNS: u32 = 3;
+const COLUMN_GAP: u32 = 20;
+const PILL_HEIGHT: u32 = 32;
+const PILL_GAP: u32 = 6;
+const GROUP_GAP: u32 = 14;
+
+const FONT_FAMILY: &str = "DejaVu Sans, Verdana, Arial, sans-serif";
+const TEXT_COLOR: &str = "#18181b";
+
+lazy_static! {
+    static ref FONTS: Arc<usvg::fontdb::Database> = {
+        let mut db = usvg::fontdb::Database::new();
+        db.load_system_fonts();
+        Arc::new(db)
+    };
+}
+
+/// Fill and mark for each status; the fills are the teal-300/red-300/zinc-300 used on the site.
+fn style(a: IsAllowed) -> (&'static str, &'static str) {
+    match a {
+        IsAllowed::ALLOWED => ("#5eead4", "✓"),
+        IsAllowed::DISALLOWED => ("#fca5a5", "✗"),
+        IsAllowed::UNSPECIFIED => ("#d4d4d8", "?"),
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn pill(out: &mut String, x: u32, y: u32, width: u32, technique: &str, a: IsAllowed) {
+    let (fill, mark) = style(a);
+    let baseline = y + PILL_HEIGHT / 2 + 6;
+    out.push_str(&format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"6\" fill=\"{}\"/>\n",
+        x, y, width, PILL_HEIGHT, fill
+    ));
+    out.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"17\">{}</text>\n",
+        x + 10,
+        baseline,
+        escape_xml(technique)
+    ));
+    out.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"18\" font-weight=\"bold\" text-anchor=\"end\">{}</text>\n",
+        x + width - 10,
+        baseline,
+        mark
+    ));
+}
+
+/// Where each of `groups` starts, as (column, y), flowing down the columns from `top` to `bottom`
+/// without splitting a group; `None` if they don't fit.
+fn layout(groups: &[&[&str]], top: u32, bottom: u32) -> Option<Vec<(u32, u32)>> {
+    let mut starts = vec![];
+    let mut column = 0;
+    let mut y = top;
+    for group in groups {
+        let height = group.len() as u32 * (PILL_HEIGHT + PILL_GAP);
+        if y != top && y + height > bottom {
+            column += 1;
+            y = top;
+        }
+        if column == COLUMNS || y + height > bottom {
+            return None;
+        }
+        starts.push((column, y));
+        y += height + GROUP_GAP;
+    }
+    Some(starts)
+}
+
+/// Draw `ruleset` as an SVG card headed `title`. Techniques keep their catalog grouping:
+/// groups flow down the columns and are never split across two of them.
+pub(crate) fn render_card_svg(title: &str, ruleset: &Ruleset) -> String {
+    card_svg(title, ruleset.catalog().groups, |t| ruleset.get(t).unwrap_or(IsAllowed::UNSPECIFIED))
+}
+
+/// `render_card_svg` for the techniques in `groups`, with `value` giving each one's status. The card
+/// is `CARD_HEIGHT` tall unless the groups need more room.
+fn card_svg(title: &str, groups: &[&[&str]], value: impl Fn(&str) -> IsAllowed) -> String {
+    let column_width = (CARD_WIDTH - 2 * MARGIN - (COLUMNS - 1) * COLUMN_GAP) / COLUMNS;
+    let top = MARGIN + HEADER_HEIGHT;
+    let mut height = CARD_HEIGHT;
+    let starts = loop {
+        match layout(groups, top, height - MARGIN) {
+            Some(starts) => break starts,
+            None => height += PILL_HEIGHT + PILL_GAP,
+        }
+    };
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+         font-family=\"{font}\" fill=\"{color}\">\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"#bae6fd\"/>\n\
+         <text x=\"{m}\" y=\"{m2}\" font-size=\"36\" font-weight=\"bold\">{title}</text>\n\
+         <text x=\"{m}\" y=\"{m3}\" font-size=\"20\">Ruleset Randomizer</text>\n",
+        w = CARD_WIDTH,
+        h = height,
+        font = FONT_FAMILY,
+        color = TEXT_COLOR,
+        m = MARGIN,
+        m2 = MARGIN + 36,
+        m3 = MARGIN + 72,
+        title = escape_xml(title),
+    );
+    // legend, right-aligned across from the subtitle
+    let mut x = CARD_WIDTH - MARGIN;
+    for (a, label) in IsAllowed::ALL.iter().zip(["Allowed", "Banned", "Unspecified"]).rev() {
+        let (fill, mark) = style(*a);
+        let width = 16 + 11 * label.len() as u32 + 24;
+        x -= width;
+        out.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"28\" rx=\"6\" fill=\"{}\"/>\n\
+             <text x=\"{}\" y=\"{}\" font-size=\"16\">{} {}</text>\n",
+            x,
+            MARGIN + 50,
+            width,
+            fill,
+            x + 8,
+            MARGIN + 70,
+            mark,
+            label
+        ));
+        x -= 10;
+    }
+
+    for (group, (column, mut y)) in groups.iter().zip(starts) {
+        let x = MARGIN + column * (column_width + COLUMN_GAP);
+        for technique in group.iter() {
+            pill(&mut out, x, y, column_width, technique, value(technique));
+            y += PILL_HEIGHT + PILL_GAP;
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Rasterize a card produced by `render_card_svg` to PNG.
+pub(crate) fn render_card_png(svg: &str) -> Result<Vec<u8>, String> {
+    let options = usvg::Options {
+        fontdb: FONTS.clone(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(svg, &options).map_err(|e| e.to_string())?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("Bad card size")?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{card_svg, render_card_png, render_card_svg, CARD_HEIGHT};
+    use crate::export::week_title;
+    use crate::rules::{IsAllowed, NMGRules};
+
+    #[test]
+    fn test_render_card() {
+        let svg = render_card_svg(&week_title("<October 18, 2026>"), &NMGRules);
+        assert!(svg.contains("Ruleset for the week of &lt;October 18, 2026&gt;"));
+        assert!(svg.contains(">SomariaTransitionCorruption</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(&format!("height=\"{}\"", CARD_HEIGHT)));
+
+        let png = render_card_png(&svg).unwrap();
+        assert_eq!(b"\x89PNG", &png[..4]);
+    }
+
+    #[test]
+    fn test_render_oversized_card() {
+        let names: Vec<String> = (0..40).map(|i| format!("Technique{}", i)).collect();
+        let big: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+        let svg = card_svg("Big", &[&big[..5], &big[5..], &big[..2]], |_| IsAllowed::ALLOWED);
+        let height: u32 = svg.split("height=\"").nth(1).unwrap().split('"').next().unwrap().parse().unwrap();
+        assert!(height > CARD_HEIGHT);
+        // every pill is on the card
+        let attr = |rect: &str, name: &str| -> u32 {
+            rect.split(&format!(" {}=\"", name)).nth(1).map_or(0, |v| v.split('"').next().unwrap().parse().unwrap())
+        };
+        for rect in svg.split("<rect").skip(1).map(|r| r.split("/>").next().unwrap()) {
+            assert!(attr(rect, "y") + attr(rect, "height") <= height, "{}", rect);
+        }
+        assert!(svg.contains(">Technique39</text>"));
+
+        let png = render_card_png(&svg).unwrap();
+        assert_eq!(b"\x89PNG", &png[..4]);
+    }
+}
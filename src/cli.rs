@@ -0,0 +1,249 @@
+//! Command line tools. With no arguments the binary runs the web server; otherwise the first
+//! argument picks one of these commands, which run against the same database and rulesets.
+
+use crate::export::{export_ruleset, ExportFormat};
+use crate::import::ImportFormat;
+use crate::rules::munge_user_input;
+use crate::share::decode_ruleset;
+use crate::techniques::Ruleset;
+use crate::templates::{flattened_yaml, resolve_template, TemplateDir};
+use crate::validate::{has_errors, validate_weights};
+use crate::web::{
+    discord_announcement, import_custom_ruleset, materialize_weekly, public_url, ruleset_share_code, ShareCode,
+    KIND_ONE_OFF, KIND_PRESET,
+};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use sqlx::SqlitePool;
+
+const USAGE: &str = "usage:
+    ruleset-randomizer
+        run the web server
+    ruleset-randomizer discord [<ruleset id>|weekly] [--embeds]
+        print the Discord announcement for a saved ruleset (this week's by default). Messages are
+        separated by a line containing only ---; with --embeds, print a JSON array of webhook bodies
+    ruleset-randomizer share [<ruleset id>|weekly]
+        print the share code and link for a saved ruleset (this week's by default)
+    ruleset-randomizer share encode <file>
+        print the share code for a ruleset file, as exported with ?format=yaml (JSON works too)
+    ruleset-randomizer share decode <code>
+        print the ruleset in a share code as YAML
+    ruleset-randomizer import <json|yaml|csv|code> <file|code> [--name <name>] [--game <game>] [--preset]
+        import a ruleset (from a file, or a share code given directly) as a one-off ruleset, or as a
+        preset with --preset, and print its link. YAML and CSV are for the default game unless
+        --game says otherwise
+    ruleset-randomizer template <file> [--roll <seed>] [--strict]
+        print a weights template with everything it extends or includes flattened into it. Those are
+        looked up as <name>.yaml next to the file. With --roll, print a ruleset rolled from it instead.
+        Weights for unknown techniques are warned about, or with --strict are an error
+    ruleset-randomizer validate <file> [--strict]
+        check a weights file and list any problems; with --strict, unknown techniques and fields are
+        errors, not warnings. Exits non-zero if there are errors";
+
+/// Run the command in `args` (not including the program name) and return the exit code.
+pub(crate) async fn run(args: &[String], pool: &SqlitePool) -> i32 {
+    match args[0].as_str() {
+        "discord" => discord(&args[1..], pool).await,
+        "share" => share(&args[1..], pool).await,
+        "import" => import(&args[1..], pool).await,
+        "template" => template(&args[1..]),
+        "validate" => validate(&args[1..]),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            0
+        }
+        other => {
+            eprintln!("Unknown command {}\n{}", other, USAGE);
+            2
+        }
+    }
+}
+
+/// A ruleset id argument; `weekly` (or nothing) means this week's, which is saved if it hasn't been.
+async fn parse_ruleset_id(arg: Option<&String>, pool: &SqlitePool) -> Result<u32, String> {
+    match arg.map(|a| a.as_str()) {
+        None | Some("weekly") => materialize_weekly(pool).await,
+        Some(a) => a.parse().map_err(|_| format!("Expected a ruleset id or weekly, got {}", a)),
+    }
+}
+
+async fn discord(args: &[String], pool: &SqlitePool) -> i32 {
+    let embeds = args.iter().any(|a| a == "--embeds");
+    let positional: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    let announcement = match parse_ruleset_id(positional.first().copied(), pool).await {
+        Ok(id) => discord_announcement(id, pool).await,
+        Err(e) => Err(e),
+    };
+    match announcement {
+        Ok(a) if embeds => {
+            println!("{}", serde_json::to_string_pretty(&a.embeds).unwrap());
+            0
+        }
+        Ok(a) => {
+            println!("{}", a.messages.join("\n---\n"));
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+async fn share(args: &[String], pool: &SqlitePool) -> i32 {
+    let result = match args.first().map(|a| a.as_str()) {
+        Some("decode") => match args.get(1) {
+            Some(code) => decode_ruleset(code, "Shared ruleset".to_string())
+                .map(|r| export_ruleset(ExportFormat::Yaml, &r.name, &r.name, &r, None))
+                .map_err(|e| e.to_string()),
+            None => Err(format!("share decode needs a code\n{}", USAGE)),
+        },
+        Some("encode") => match args.get(1) {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Couldn't read {}: {}", path, e))
+                .and_then(|s| serde_yaml::from_str::<Ruleset>(&s).map_err(|e| format!("Couldn't parse {}: {}", path, e)))
+                .map(|r| {
+                    let share = ShareCode::new(r);
+                    format!("{}\n{}", share.code, share.url)
+                }),
+            None => Err(format!("share encode needs a file\n{}", USAGE)),
+        },
+        _ => match parse_ruleset_id(args.first(), pool).await {
+            Ok(id) => ruleset_share_code(id, pool)
+                .await
+                .map(|share| format!("{}\n{}", share.code, share.url)),
+            Err(e) => Err(e),
+        },
+    };
+    match result {
+        Ok(out) => {
+            println!("{}", out.trim_end());
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+async fn import(args: &[String], pool: &SqlitePool) -> i32 {
+    let kind = if args.iter().any(|a| a == "--preset") { KIND_PRESET } else { KIND_ONE_OFF };
+    let option = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let (name, game) = (option("--name"), option("--game"));
+    let positional: Vec<&String> = args
+        .iter()
+        .enumerate()
+        .filter(|(i, a)| !a.starts_with("--") && (*i == 0 || (args[i - 1] != "--name" && args[i - 1] != "--game")))
+        .map(|(_, a)| a)
+        .collect();
+    let (format, source) = match positional.as_slice() {
+        [format, source] => (format, source),
+        _ => {
+            eprintln!("import needs a format and a file\n{}", USAGE);
+            return 2;
+        }
+    };
+    let result = match format.parse::<ImportFormat>() {
+        Ok(ImportFormat::ShareCode) => import_custom_ruleset(ImportFormat::ShareCode, source, &game, &name, kind, "cli", pool).await,
+        Ok(format) => match std::fs::read_to_string(source) {
+            Ok(data) => import_custom_ruleset(format, &data, &game, &name, kind, "cli", pool).await,
+            Err(e) => Err(format!("Couldn't read {}: {}", source, e)),
+        },
+        Err(e) => Err(e.to_string()),
+    };
+    match result {
+        Ok(id) => {
+            println!("{}/custom/{}", public_url(), id);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn template(args: &[String]) -> i32 {
+    let strict = args.iter().any(|a| a == "--strict");
+    let args: Vec<String> = args.iter().filter(|a| *a != "--strict").cloned().collect();
+    let (path, seed) = match &args[..] {
+        [p] => (std::path::Path::new(p), None),
+        [p, flag, seed] if flag == "--roll" => match seed.parse::<u64>() {
+            Ok(seed) => (std::path::Path::new(p), Some(seed)),
+            Err(_) => {
+                eprintln!("Expected a number to roll with, got {}", seed);
+                return 2;
+            }
+        },
+        _ => {
+            eprintln!("template needs a file\n{}", USAGE);
+            return 2;
+        }
+    };
+    let yaml = match std::fs::read_to_string(path) {
+        Ok(y) => y,
+        Err(e) => {
+            eprintln!("Couldn't read {}: {}", path.display(), e);
+            return 1;
+        }
+    };
+    let dir = TemplateDir(path.parent().map(|p| p.to_path_buf()).unwrap_or_default());
+    let output = resolve_template(&yaml, &dir).and_then(|weights| {
+        let flat = flattened_yaml(&weights);
+        let (munged, diagnostics) = munge_user_input(weights, strict)?;
+        for d in &diagnostics {
+            eprintln!("{}: {}", path.display(), d);
+        }
+        Ok(match seed {
+            Some(seed) => {
+                let ruleset = munged.roll_with_rng(&mut SmallRng::seed_from_u64(seed));
+                export_ruleset(ExportFormat::Yaml, &ruleset.name, &ruleset.name, &ruleset, None)
+            }
+            None => flat,
+        })
+    });
+    match output {
+        Ok(output) => {
+            print!("{}", output);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn validate(args: &[String]) -> i32 {
+    let (path, strict) = match args {
+        [p] => (p, false),
+        [p, flag] | [flag, p] if flag == "--strict" => (p, true),
+        _ => {
+            eprintln!("validate needs a file\n{}", USAGE);
+            return 2;
+        }
+    };
+    let yaml = match std::fs::read_to_string(path) {
+        Ok(y) => y,
+        Err(e) => {
+            eprintln!("Couldn't read {}: {}", path, e);
+            return 1;
+        }
+    };
+    let diagnostics = validate_weights(&yaml, strict);
+    for d in &diagnostics {
+        println!("{}:{}", path, d);
+    }
+    if has_errors(&diagnostics) {
+        1
+    } else {
+        0
+    }
+}
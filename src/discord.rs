@@ -0,0 +1,272 @@
+//! Discord announcements for a ruleset, as plain markdown messages or as embed payloads. Both are
+//! split to fit Discord's limits, so a bot or webhook can post each piece verbatim, in order.
+
+use crate::rules::{IsAllowed, RuleChange};
+use crate::games::{default_game, find_game, Game};
+use crate::webhooks::RulesetPayload;
+use serde_json::{json, Value};
+
+/// Longest plain message Discord accepts.
+pub(crate) const MESSAGE_LIMIT: usize = 2000;
+const EMBED_TITLE_LIMIT: usize = 256;
+const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+const EMBED_FIELD_VALUE_LIMIT: usize = 1024;
+const EMBED_FIELDS_LIMIT: usize = 25;
+/// Total characters across title, description and fields of one embed.
+const EMBED_TOTAL_LIMIT: usize = 6000;
+
+const EMBED_COLOR: u32 = 0x8b5cf6;
+
+fn emoji(a: IsAllowed) -> &'static str {
+    match a {
+        IsAllowed::ALLOWED => "✅",
+        IsAllowed::DISALLOWED => "❌",
+        IsAllowed::UNSPECIFIED => "❔",
+    }
+}
+
+fn heading(a: IsAllowed) -> &'static str {
+    match a {
+        IsAllowed::ALLOWED => "Allowed",
+        IsAllowed::DISALLOWED => "Banned",
+        IsAllowed::UNSPECIFIED => "Unspecified",
+    }
+}
+
+fn word(a: IsAllowed) -> &'static str {
+    match a {
+        IsAllowed::ALLOWED => "allowed",
+        IsAllowed::DISALLOWED => "banned",
+        IsAllowed::UNSPECIFIED => "unspecified",
+    }
+}
+
+fn title(payload: &RulesetPayload) -> String {
+    format!("Ruleset for the week of {}", payload.name)
+}
+
+fn game(payload: &RulesetPayload) -> &'static Game {
+    find_game(&payload.game).unwrap_or_else(default_game)
+}
+
+/// Techniques with status `a`, in catalog order.
+fn techniques_with(payload: &RulesetPayload, a: IsAllowed) -> Vec<&'static str> {
+    game(payload)
+        .catalog
+        .techniques
+        .iter()
+        .filter(|t| payload.ruleset.get(**t).map(|s| s.as_str()) == Some(a.as_str()))
+        .copied()
+        .collect()
+}
+
+fn change_lines(changes: &[RuleChange]) -> Vec<String> {
+    changes
+        .iter()
+        .map(|c| format!("{} {}: {} (was {})", emoji(c.after), c.technique, word(c.after), word(c.before)))
+        .collect()
+}
+
+/// Each titled section of the announcement, as (heading, lines).
+fn sections(payload: &RulesetPayload) -> Vec<(String, Vec<String>)> {
+    let mut sections = vec![];
+    for a in IsAllowed::ALL {
+        let techniques = techniques_with(payload, a);
+        if !techniques.is_empty() {
+            sections.push((format!("{} {}", emoji(a), heading(a)), vec![techniques.join(", ")]));
+        }
+    }
+    if let Some(previous) = &payload.diff_vs_previous {
+        let lines = if previous.changes.is_empty() {
+            vec!["No changes.".to_string()]
+        } else {
+            change_lines(&previous.changes)
+        };
+        sections.push((format!("🔁 Changes since {}", previous.name), lines));
+    }
+    if !payload.diff_vs_nmg.is_empty() {
+        let baseline = &game(payload).baseline().name;
+        sections.push((format!("🆚 Differences from {}", baseline), change_lines(&payload.diff_vs_nmg)));
+    }
+    sections
+}
+
+/// Cut `s` to at most `limit` characters, marking the cut with an ellipsis.
+fn truncate(s: &str, limit: usize) -> String {
+    if s.chars().count() <= limit {
+        s.to_string()
+    } else {
+        let mut t: String = s.chars().take(limit - 1).collect();
+        t.push('…');
+        t
+    }
+}
+
+/// Break `text` into chunks of at most `limit` characters, preferring to break between lines,
+/// then between words, and only splitting a word if it's longer than `limit` by itself.
+pub(crate) fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut push = |current: &mut String, piece: &str, sep: char| {
+        let needed = if current.is_empty() { 0 } else { 1 } + piece.chars().count();
+        if current.chars().count() + needed > limit && !current.is_empty() {
+            chunks.push(std::mem::take(current));
+        }
+        if !current.is_empty() {
+            current.push(sep);
+        }
+        current.push_str(piece);
+    };
+    for line in text.lines() {
+        if line.chars().count() <= limit {
+            push(&mut current, line, '\n');
+            continue;
+        }
+        // an overlong line: fall back to words, and to raw characters for overlong words
+        let mut first = true;
+        for word in line.split(' ') {
+            let sep = if first { '\n' } else { ' ' };
+            first = false;
+            let chars: Vec<char> = word.chars().collect();
+            if chars.is_empty() {
+                continue;
+            }
+            for piece in chars.chunks(limit) {
+                push(&mut current, &piece.iter().collect::<String>(), sep);
+            }
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// The announcement as Discord markdown, split into messages of at most `MESSAGE_LIMIT` characters.
+pub(crate) fn render_markdown(payload: &RulesetPayload) -> Vec<String> {
+    let mut lines = vec![format!("**{}**", title(payload)), format!("<{}>", payload.permalink)];
+    if let Some(note) = &payload.note {
+        lines.extend(note.lines().map(|l| format!("> {}", l)));
+    }
+    for (heading, body) in sections(payload) {
+        lines.push(String::new());
+        lines.push(format!("**{}**", heading));
+        lines.extend(body);
+    }
+    split_message(&lines.join("\n"), MESSAGE_LIMIT)
+}
+
+fn embed_size(embed: &Value) -> usize {
+    let len = |v: &Value| v.as_str().map_or(0, |s| s.chars().count());
+    len(&embed["title"])
+        + len(&embed["description"])
+        + embed["fields"]
+            .as_array()
+            .map_or(0, |fs| fs.iter().map(|f| len(&f["name"]) + len(&f["value"])).sum())
+}
+
+/// The announcement as Discord webhook bodies (`{"embeds": [...]}`), one embed each. Long sections
+/// are spread over several fields, and fields over several messages, to stay within embed limits.
+pub(crate) fn render_embeds(payload: &RulesetPayload) -> Vec<Value> {
+    let title = truncate(&title(payload), EMBED_TITLE_LIMIT);
+    let description = truncate(payload.note.as_deref().unwrap_or(""), EMBED_DESCRIPTION_LIMIT);
+
+    let mut fields: Vec<Value> = vec![];
+    for (heading, body) in sections(payload) {
+        let name = truncate(&heading, EMBED_TITLE_LIMIT);
+        for (i, value) in split_message(&body.join("\n"), EMBED_FIELD_VALUE_LIMIT).into_iter().enumerate() {
+            let name = if i == 0 { name.clone() } else { format!("{} (cont.)", name) };
+            fields.push(json!({"name": name, "value": value, "inline": false}));
+        }
+    }
+
+    let new_embed = |first: bool| {
+        let mut embed = json!({"title": title, "url": payload.permalink, "color": EMBED_COLOR, "fields": []});
+        if first && !description.is_empty() {
+            embed["description"] = json!(description);
+        }
+        embed
+    };
+    let mut embeds = vec![new_embed(true)];
+    for field in fields {
+        let embed = embeds.last_mut().unwrap();
+        let count = embed["fields"].as_array().unwrap().len();
+        let field_size = field["name"].as_str().unwrap().chars().count() + field["value"].as_str().unwrap().chars().count();
+        if count > 0 && (count == EMBED_FIELDS_LIMIT || embed_size(embed) + field_size > EMBED_TOTAL_LIMIT) {
+            embeds.push(new_embed(false));
+        }
+        embeds.last_mut().unwrap()["fields"].as_array_mut().unwrap().push(field);
+    }
+    embeds.into_iter().map(|e| json!({ "embeds": [e] })).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render_embeds, render_markdown, split_message, MESSAGE_LIMIT};
+    use crate::rules::{IsAllowed, NMGRules};
+    use crate::webhooks::{RulesetPayload, EVENT_PUBLISHED};
+
+    fn payload(note: Option<String>) -> RulesetPayload {
+        let mut ruleset = NMGRules.clone();
+        ruleset["OverworldClipping"] = IsAllowed::ALLOWED;
+        let mut previous = NMGRules.clone();
+        previous["Hover"] = IsAllowed::DISALLOWED;
+        RulesetPayload::new(
+            EVENT_PUBLISHED,
+            739907,
+            "October 18, 2026".to_string(),
+            "https://example.com/history/739907".to_string(),
+            note,
+            &ruleset,
+        )
+        .with_previous(739900, "October 11, 2026".to_string(), &previous, &ruleset)
+    }
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(vec!["a\nb", "c"], split_message("a\nb\nc", 3));
+        assert_eq!(vec!["one", "two", "thre", "e"], split_message("one two three", 4));
+        assert_eq!(Vec::<String>::new(), split_message("", 10));
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let messages = render_markdown(&payload(Some("Good luck".to_string())));
+        assert_eq!(1, messages.len());
+        let m = &messages[0];
+        assert!(m.starts_with("**Ruleset for the week of October 18, 2026**\n<https://example.com/history/739907>\n> Good luck\n"));
+        assert!(m.contains("**❌ Banned**\nSaveAndQuit, DoorStateExtension, DiverDown, HookShopping"));
+        assert!(m.contains("**🔁 Changes since October 11, 2026**\n✅ Hover: allowed (was banned)\n✅ OverworldClipping: allowed (was banned)"));
+        assert!(m.contains("**🆚 Differences from NMG**\n✅ OverworldClipping: allowed (was banned)"));
+    }
+
+    #[test]
+    fn test_long_note_is_split() {
+        let note = "Read this carefully. ".repeat(300);
+        let messages = render_markdown(&payload(Some(note)));
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|m| m.chars().count() <= MESSAGE_LIMIT));
+
+        let embeds = render_embeds(&payload(Some("x".repeat(5000))));
+        let description = embeds[0]["embeds"][0]["description"].as_str().unwrap();
+        assert_eq!(4096, description.chars().count());
+    }
+
+    #[test]
+    fn test_render_embeds() {
+        let embeds = render_embeds(&payload(None));
+        assert_eq!(1, embeds.len());
+        let embed = &embeds[0]["embeds"][0];
+        assert_eq!("https://example.com/history/739907", embed["url"]);
+        let names: Vec<&str> = embed["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            vec!["✅ Allowed", "❌ Banned", "❔ Unspecified", "🔁 Changes since October 11, 2026", "🆚 Differences from NMG"],
+            names
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Rulesets as documents people can paste elsewhere: markdown tables, CSV for spreadsheets, YAML
+//! that loads straight back into a `Ruleset`, and a compact plaintext rules card.
+
+use crate::rules::{IsAllowed, UserInputError};
+use crate::techniques::Ruleset;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum ExportFormat {
+    Markdown,
+    Csv,
+    Yaml,
+    Plaintext,
+}
+
+impl ExportFormat {
+    /// The name used in `?format=`, which doubles as a file extension.
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Yaml => "yaml",
+            ExportFormat::Plaintext => "txt",
+        }
+    }
+
+    /// The media type served for this format, as (top, sub).
+    pub(crate) fn media_type(&self) -> (&'static str, &'static str) {
+        match self {
+            ExportFormat::Markdown => ("text", "markdown"),
+            ExportFormat::Csv => ("text", "csv"),
+            ExportFormat::Yaml => ("application", "yaml"),
+            ExportFormat::Plaintext => ("text", "plain"),
+        }
+    }
+
+    /// The format for an `Accept`ed media type, if we export to it.
+    pub(crate) fn from_media_type(top: &str, sub: &str) -> Option<Self> {
+        match (top.to_lowercase().as_str(), sub.to_lowercase().as_str()) {
+            ("text", "markdown") | ("text", "x-markdown") => Some(ExportFormat::Markdown),
+            ("text", "csv") => Some(ExportFormat::Csv),
+            ("application", "yaml") | ("application", "x-yaml") | ("text", "yaml") | ("text", "x-yaml") => {
+                Some(ExportFormat::Yaml)
+            }
+            ("text", "plain") => Some(ExportFormat::Plaintext),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = UserInputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "csv" => Ok(ExportFormat::Csv),
+            "yaml" | "yml" => Ok(ExportFormat::Yaml),
+            "txt" | "text" | "plaintext" => Ok(ExportFormat::Plaintext),
+            _ => Err(UserInputError {
+                err: format!("Expected one of md, csv, yaml, or txt, got {}", s),
+            }),
+        }
+    }
+}
+
+fn label(a: IsAllowed) -> &'static str {
+    match a {
+        IsAllowed::ALLOWED => "Allowed",
+        IsAllowed::DISALLOWED => "Banned",
+        IsAllowed::UNSPECIFIED => "Unspecified",
+    }
+}
+
+fn techniques(ruleset: &Ruleset) -> impl Iterator<Item = (&'static str, IsAllowed)> + '_ {
+    ruleset.techniques()
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// The title of a weekly ruleset published as `name`.
+pub(crate) fn week_title(name: &str) -> String {
+    format!("Ruleset for the week of {}", name)
+}
+
+fn render_markdown(title: &str, ruleset: &Ruleset, note: Option<&str>) -> String {
+    let mut out = format!("# {}\n\n", title);
+    if let Some(note) = note {
+        out.push_str(&note.lines().map(|l| format!("> {}\n", l)).collect::<String>());
+        out.push('\n');
+    }
+    out.push_str("| Technique | Status |\n| --- | --- |\n");
+    for (technique, allowed) in techniques(ruleset) {
+        out.push_str(&format!("| {} | {} |\n", technique, label(allowed)));
+    }
+    out
+}
+
+fn render_csv(ruleset: &Ruleset) -> String {
+    let mut out = "technique,status\r\n".to_string();
+    for (technique, allowed) in techniques(ruleset) {
+        out.push_str(&format!("{},{}\r\n", csv_field(technique), allowed.as_str()));
+    }
+    out
+}
+
+/// One line per status, techniques in catalog order, so the whole card fits in a chat message.
+fn render_plaintext(title: &str, ruleset: &Ruleset, note: Option<&str>) -> String {
+    let mut out = format!("{}\n", title);
+    if let Some(note) = note {
+        out.push_str(&format!("Note: {}\n", note.lines().collect::<Vec<_>>().join(" ")));
+    }
+    for a in IsAllowed::ALL {
+        let names: Vec<&str> = techniques(ruleset).filter(|(_, s)| *s == a).map(|(t, _)| t).collect();
+        if !names.is_empty() {
+            out.push_str(&format!("{}: {}\n", label(a), names.join(", ")));
+        }
+    }
+    out
+}
+
+/// Render `ruleset`, published as `name`, as `format`, headed with `title` where the format has a
+/// heading. The YAML export carries `name` as the ruleset's name and leaves out the note, so that it
+/// deserializes back into the same `Ruleset`.
+pub(crate) fn export_ruleset(format: ExportFormat, title: &str, name: &str, ruleset: &Ruleset, note: Option<&str>) -> String {
+    match format {
+        ExportFormat::Markdown => render_markdown(title, ruleset, note),
+        ExportFormat::Csv => render_csv(ruleset),
+        ExportFormat::Yaml => {
+            let mut named = ruleset.clone();
+            named.name = name.to_string();
+            serde_yaml::to_string(&named).unwrap()
+        }
+        ExportFormat::Plaintext => render_plaintext(title, ruleset, note),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{csv_field, export_ruleset, week_title, ExportFormat};
+    use crate::rules::{IsAllowed, NMGRules};
+    use crate::techniques::Ruleset;
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let mut ruleset = NMGRules.clone();
+        ruleset["Hover"] = IsAllowed::UNSPECIFIED;
+        let yaml = export_ruleset(ExportFormat::Yaml, "Title", "October 18, 2026", &ruleset, Some("ignored"));
+        let parsed: Ruleset = serde_yaml::from_str(&yaml).unwrap();
+        ruleset.name = "October 18, 2026".to_string();
+        assert_eq!(ruleset, parsed);
+    }
+
+    #[test]
+    fn test_formats() {
+        let ruleset = NMGRules.clone();
+        let md = export_ruleset(ExportFormat::Markdown, &week_title("Week"), "Week", &ruleset, Some("Have fun"));
+        assert!(md.starts_with("# Ruleset for the week of Week\n\n> Have fun\n\n| Technique | Status |\n| --- | --- |\n| SaveAndQuit | Banned |\n"));
+        let csv = export_ruleset(ExportFormat::Csv, "Week", "Week", &ruleset, None);
+        assert!(csv.starts_with("technique,status\r\nSaveAndQuit,DISALLOWED\r\nBombJump,ALLOWED\r\n"));
+        let txt = export_ruleset(ExportFormat::Plaintext, "Shared ruleset", "Shared ruleset", &ruleset, None);
+        assert_eq!(4, txt.lines().count());
+        assert!(txt.starts_with("Shared ruleset\n"));
+        assert!(txt.contains("\nBanned: SaveAndQuit, DoorStateExtension, DiverDown, HookShopping,"));
+        assert_eq!("\"a, \"\"b\"\"\"", csv_field("a, \"b\""));
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(ExportFormat::Yaml, "yml".parse().unwrap());
+        assert_eq!(Some(ExportFormat::Csv), ExportFormat::from_media_type("text", "CSV"));
+        assert_eq!(None, ExportFormat::from_media_type("text", "html"));
+        assert!("docx".parse::<ExportFormat>().is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! The games this site hosts. Each has a data directory under `GAMES_PATH` (`games/` by default)
+//! named after it:
+//!
+//! - `techniques`: its technique catalog (see `crate::techniques`; games are listed in
+//!   `games/games`, the default first)
+//! - `game.yaml`: its name, the preset weeklies are compared against, and the catalog version
+//!   share codes are written with
+//! - `presets.yaml`
+//! - `supplemental.yaml`: the supplemental rules it starts out with (see `crate::web::supplemental`)
+//! - `tracks.yaml` and the weights templates its tracks roll from (see `crate::tracks`)
+//!
+//! All but `supplemental.yaml` is read at startup, along with the named chances in `games/levels`. Catalogs are
+//! loaded after the database is up, since randomizable supplemental rules are added to them.
+
+use crate::rules::{IsAllowed, UserInputError};
+use crate::techniques::{catalogs, default_catalog, Catalog, Ruleset};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env::var;
+use std::path::PathBuf;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GameFile {
+    name: String,
+    baseline: String,
+    catalog_version: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetEntry {
+    id: String,
+    name: String,
+    rules: HashMap<String, IsAllowed>,
+}
+
+/// A ruling on something outside the randomized catalog. `supplemental.yaml` has the ones a game
+/// starts out with; after that they're edited in the database (see `crate::web::supplemental`).
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SupplementalRule {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) description: String,
+    pub(crate) allowed: IsAllowed,
+    /// Rolled like a technique, with `allowed` as its default, rather than the same every week.
+    #[serde(default)]
+    pub(crate) randomizable: bool,
+}
+
+pub(crate) struct Preset {
+    pub(crate) id: String,
+    pub(crate) ruleset: Ruleset,
+}
+
+pub(crate) struct Game {
+    pub(crate) slug: &'static str,
+    pub(crate) name: String,
+    pub(crate) catalog: &'static Catalog,
+    pub(crate) presets: Vec<Preset>,
+    baseline: usize,
+    pub(crate) catalog_version: u8,
+}
+
+pub(crate) fn games_path() -> PathBuf {
+    PathBuf::from(&var("GAMES_PATH").unwrap_or("games".to_string()))
+}
+
+impl Game {
+    pub(crate) fn is_default(&self) -> bool {
+        self.slug == default_catalog().game
+    }
+
+    /// Where this game's data lives.
+    pub(crate) fn dir(&self) -> PathBuf {
+        games_path().join(self.slug)
+    }
+
+    /// The preset this game's weeklies are compared against.
+    pub(crate) fn baseline(&self) -> &Ruleset {
+        &self.presets[self.baseline].ruleset
+    }
+
+    /// `path` (starting with `/`) under this game's urls: unprefixed for the default game.
+    pub(crate) fn url(&self, path: &str) -> String {
+        if self.is_default() {
+            path.to_string()
+        } else {
+            format!("/{}{}", self.slug, path)
+        }
+    }
+
+    /// The game `ruleset` is for.
+    pub(crate) fn of(ruleset: &Ruleset) -> &'static Game {
+        find_game(&ruleset.game).unwrap_or_else(default_game)
+    }
+}
+
+fn read_yaml<T: for<'de> Deserialize<'de>>(dir: &std::path::Path, file: &str) -> Result<T, UserInputError> {
+    let path = dir.join(file);
+    let yaml = std::fs::read_to_string(&path).map_err(|e| UserInputError {
+        err: format!("Couldn't read {}: {}", path.display(), e),
+    })?;
+    serde_yaml::from_str(&yaml).map_err(|e| UserInputError {
+        err: format!("Error parsing {}: {}", path.display(), e),
+    })
+}
+
+/// The supplemental rules `game` starts out with, from its `supplemental.yaml`.
+pub(crate) fn read_supplemental(game: &str) -> Result<Vec<SupplementalRule>, UserInputError> {
+    read_yaml(&games_path().join(game), "supplemental.yaml")
+}
+
+fn load_game(catalog: &'static Catalog) -> Result<Game, UserInputError> {
+    let dir = games_path().join(catalog.game);
+    let file: GameFile = read_yaml(&dir, "game.yaml")?;
+    let entries: Vec<PresetEntry> = read_yaml(&dir, "presets.yaml")?;
+    let mut presets = vec![];
+    for entry in entries {
+        let mut ruleset = Ruleset::unspecified(catalog.game, entry.name);
+        for (technique, allowed) in entry.rules {
+            if !ruleset.set(&technique, allowed) {
+                return Err(UserInputError {
+                    err: format!("Preset {} sets {}, which isn't a {} technique", entry.id, technique, catalog.game),
+                });
+            }
+        }
+        presets.push(Preset { id: entry.id, ruleset });
+    }
+    let baseline = presets.iter().position(|p| p.id == file.baseline).ok_or_else(|| UserInputError {
+        err: format!("Baseline {} isn't one of {}'s presets", file.baseline, catalog.game),
+    })?;
+    Ok(Game {
+        slug: catalog.game,
+        name: file.name,
+        catalog,
+        presets,
+        baseline,
+        catalog_version: file.catalog_version,
+    })
+}
+
+fn load_games() -> Result<Vec<Game>, UserInputError> {
+    let games = catalogs().iter().map(load_game).collect::<Result<Vec<_>, _>>()?;
+    let mut ids: Vec<&str> = games.iter().flat_map(|g| g.presets.iter().map(|p| p.id.as_str())).collect();
+    ids.sort_unstable();
+    if let Some(w) = ids.windows(2).find(|w| w[0] == w[1]) {
+        return Err(UserInputError {
+            err: format!("Preset {} is defined more than once", w[0]),
+        });
+    }
+    Ok(games)
+}
+
+lazy_static! {
+    static ref GAMES: Vec<Game> = load_games().unwrap_or_else(|e| panic!("Error loading games: {}", e));
+}
+
+/// Every game, the default first.
+pub(crate) fn games() -> &'static [Game] {
+    &GAMES
+}
+
+pub(crate) fn find_game(slug: &str) -> Option<&'static Game> {
+    GAMES.iter().find(|g| g.slug == slug)
+}
+
+pub(crate) fn default_game() -> &'static Game {
+    &GAMES[0]
+}
+
+/// The preset called `id`, from whichever game has it.
+pub(crate) fn find_preset(id: &str) -> Option<&'static Ruleset> {
+    GAMES.iter().flat_map(|g| g.presets.iter()).find(|p| p.id == id).map(|p| &p.ruleset)
+}
+
+/// Every game's preset ids.
+pub(crate) fn preset_ids() -> Vec<&'static str> {
+    GAMES.iter().flat_map(|g| g.presets.iter().map(|p| p.id.as_str())).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{default_game, find_game, find_preset, games};
+    use crate::rules::IsAllowed;
+    use crate::techniques::default_catalog;
+
+    #[test]
+    fn test_games() {
+        assert_eq!(default_catalog().game, default_game().slug);
+        for game in games() {
+            assert!(std::ptr::eq(game, find_game(game.slug).unwrap()));
+            assert_eq!(game.slug, game.baseline().game);
+            for preset in &game.presets {
+                assert_eq!(game.slug, preset.ruleset.game);
+            }
+        }
+        let sm = find_game("sm").unwrap();
+        assert_eq!("/sm/weekly", sm.url("/weekly"));
+        assert_eq!("/weekly", default_game().url("/weekly"));
+        assert_eq!(Some(IsAllowed::ALLOWED), find_preset("SMAnyPercent").unwrap().get("BlueSuit"));
+        // techniques from other games aren't part of a ruleset
+        assert_eq!(None, find_preset("NMGRules").unwrap().get("BlueSuit"));
+    }
+}
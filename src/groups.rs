@@ -0,0 +1,246 @@
+//! Technique groups as weighting units. The catalog's sections (`[Overworld]` and so on in a game's
+//! `techniques` file) can be weighted as a whole in a template's `groups`:
+//!
+//! ```yaml
+//! groups:
+//!   Overworld: together 10%      # one roll: the whole group is allowed, or none of it
+//!   Underworld: exactly 1        # exactly one member allowed, picked at random
+//!   Movement: each common        # every member rolled on its own
+//! ```
+//!
+//! Chances are written like technique weights (see `crate::weights`). A template can't weight a group and one of
+//! its members too, so "exactly K" always means exactly K; templates that `extends` one that does
+//! just replace it (see `crate::templates`).
+
+use crate::rules::{IsAllowed, TemplateState, UserInputError};
+use crate::weights::{Chance, ChanceError};
+use crate::techniques::{Catalog, Ruleset};
+use rand::seq::index::sample;
+use rand::Rng;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum GroupMode {
+    /// One roll for the whole group: all allowed, or all disallowed.
+    Together(Chance),
+    /// Exactly this many members allowed, picked uniformly; the rest disallowed.
+    Exactly(usize),
+    /// Each member rolled on its own at the same chance.
+    Each(Chance),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct GroupRule {
+    pub(crate) group: &'static str,
+    members: &'static [&'static str],
+    pub(crate) mode: GroupMode,
+}
+
+fn chance(group: &str, input: &str) -> Result<Chance, UserInputError> {
+    input.parse().map_err(|e| UserInputError {
+        err: match e {
+            ChanceError::NotAChance => format!("Error parsing user input for group {}: expected a chance, got {}", group, input),
+            ChanceError::Invalid(e) => format!("Error parsing user input for group {}: {}", group, e),
+        },
+    })
+}
+
+impl GroupRule {
+    /// Parse `together <chance>`, `exactly <k>` or `each <chance>` for the group `group` in `catalog`.
+    pub(crate) fn from_user_input(catalog: &'static Catalog, group: &str, input: &str) -> Result<Self, UserInputError> {
+        let (name, members) = catalog
+            .group_names
+            .iter()
+            .zip(catalog.groups.iter())
+            .find(|(n, _)| **n == group)
+            .ok_or_else(|| UserInputError {
+                err: format!("Unknown group {}, expected one of {}", group, catalog.group_names.join(", ")),
+            })?;
+        let mode = match input.trim().split_once(char::is_whitespace) {
+            Some((mode, arg)) if mode.eq_ignore_ascii_case("together") => GroupMode::Together(chance(group, arg.trim())?),
+            Some((mode, arg)) if mode.eq_ignore_ascii_case("each") => GroupMode::Each(chance(group, arg.trim())?),
+            Some((mode, arg)) if mode.eq_ignore_ascii_case("exactly") => match arg.trim().parse::<usize>() {
+                Ok(k) if k <= members.len() => GroupMode::Exactly(k),
+                _ => {
+                    return Err(UserInputError {
+                        err: format!(
+                            "Error parsing user input for group {}: expected a count from 0 to {}, got {}",
+                            group,
+                            members.len(),
+                            arg.trim()
+                        ),
+                    })
+                }
+            },
+            _ => {
+                return Err(UserInputError {
+                    err: format!(
+                        "Error parsing user input for group {}: expected \"together <chance>\", \"exactly <count>\", or \"each <chance>\", got {}",
+                        group, input
+                    ),
+                })
+            }
+        };
+        Ok(Self { group: name, members, mode })
+    }
+
+    pub(crate) fn members(&self) -> &'static [&'static str] {
+        self.members
+    }
+
+    /// The chance each member comes out `ALLOWED`.
+    pub(crate) fn allowed_chance(&self) -> f64 {
+        match self.mode {
+            GroupMode::Together(c) | GroupMode::Each(c) => c.probability(),
+            GroupMode::Exactly(k) => k as f64 / self.members.len() as f64,
+        }
+    }
+
+    /// How this would be written in a weights file.
+    pub(crate) fn to_user_input(self) -> String {
+        match self.mode {
+            GroupMode::Together(c) => format!("together {}", c),
+            GroupMode::Exactly(k) => format!("exactly {}", k),
+            GroupMode::Each(c) => format!("each {}", c),
+        }
+    }
+
+    /// Overwrite the group's members in `ruleset` with a roll.
+    pub(crate) fn apply_with_rng<R: Rng>(&self, ruleset: &mut Ruleset, rng: &mut R) {
+        let roll = |rng: &mut R, c: Chance| {
+            if c.roll(rng) {
+                IsAllowed::ALLOWED
+            } else {
+                IsAllowed::DISALLOWED
+            }
+        };
+        let members = self.members();
+        match self.mode {
+            GroupMode::Together(c) => {
+                let allowed = roll(rng, c);
+                for t in members {
+                    ruleset.set(t, allowed);
+                }
+            }
+            GroupMode::Exactly(k) => {
+                for t in members {
+                    ruleset.set(t, IsAllowed::DISALLOWED);
+                }
+                for i in sample(rng, members.len(), k).iter() {
+                    ruleset.set(members[i], IsAllowed::ALLOWED);
+                }
+            }
+            GroupMode::Each(c) => {
+                for t in members {
+                    let allowed = roll(rng, c);
+                    ruleset.set(t, allowed);
+                }
+            }
+        }
+    }
+}
+
+/// Parse a template's `groups`, in catalog order so rolls are reproducible. `techniques` are the
+/// template's parsed technique weights, which mustn't overlap the groups.
+pub(crate) fn parse_group_weights(
+    catalog: &'static Catalog,
+    mut input: HashMap<String, String>,
+    techniques: &HashMap<String, TemplateState>,
+) -> Result<Vec<GroupRule>, UserInputError> {
+    let mut rules = vec![];
+    for name in catalog.group_names {
+        if let Some(value) = input.remove(*name) {
+            let rule = GroupRule::from_user_input(catalog, name, &value)?;
+            if let Some(t) = rule.members().iter().find(|t| techniques.contains_key(**t)) {
+                return Err(UserInputError {
+                    err: format!("{} is weighted both on its own and as part of group {}", t, name),
+                });
+            }
+            rules.push(rule);
+        }
+    }
+    if let Some(unknown) = input.keys().next() {
+        return Err(UserInputError {
+            err: format!("Unknown group {}, expected one of {}", unknown, catalog.group_names.join(", ")),
+        });
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_group_weights, GroupMode, GroupRule};
+    use crate::rules::{IsAllowed, NMGRules, TemplateState};
+    use crate::techniques::{catalogs, default_catalog, Catalog};
+    use crate::weights::Chance;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    fn alttp() -> &'static Catalog {
+        default_catalog()
+    }
+
+    #[test]
+    fn test_parse() {
+        for c in catalogs() {
+            assert_eq!(c.groups.len(), c.group_names.len());
+        }
+        assert_eq!(
+            GroupMode::Together(Chance::per_thousand(300)),
+            GroupRule::from_user_input(alttp(), "Overworld", "together 30%").unwrap().mode
+        );
+        assert_eq!(GroupMode::Exactly(2), GroupRule::from_user_input(alttp(), "Overworld", "Exactly 2").unwrap().mode);
+        let each = GroupRule::from_user_input(alttp(), "Overworld", "each 1 in 2").unwrap();
+        assert_eq!(GroupMode::Each(Chance::per_thousand(500)), each.mode);
+        assert_eq!("each 50%", each.to_user_input());
+        assert!(GroupRule::from_user_input(alttp(), "Overworld", "exactly 6").is_err());
+        assert!(GroupRule::from_user_input(alttp(), "Overworld", "sometimes 5").is_err());
+        assert!(GroupRule::from_user_input(alttp(), "Overworld", "together false").is_err());
+        assert!(GroupRule::from_user_input(alttp(), "Overworld", "together 30").is_err());
+        assert!(GroupRule::from_user_input(alttp(), "Nope", "each 5")
+            .unwrap_err()
+            .to_string()
+            .starts_with("Unknown group Nope"));
+
+        let groups = |g: &[(&str, &str)]| g.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let mut techniques = HashMap::new();
+        assert_eq!(
+            vec!["Movement", "Overworld"],
+            parse_group_weights(alttp(), groups(&[("Overworld", "each 5%"), ("Movement", "exactly 1")]), &techniques)
+                .unwrap()
+                .iter()
+                .map(|r| r.group)
+                .collect::<Vec<_>>()
+        );
+        techniques.insert("OverworldYBA".to_string(), TemplateState::STATIC(IsAllowed::ALLOWED));
+        assert_eq!(
+            "OverworldYBA is weighted both on its own and as part of group Overworld",
+            parse_group_weights(alttp(), groups(&[("Overworld", "each 5%")]), &techniques).unwrap_err().to_string()
+        );
+        assert!(parse_group_weights(alttp(), groups(&[("Overworld ", "each 5%")]), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let count = |r: &crate::techniques::Ruleset, members: &[&str]| {
+            members.iter().filter(|t| r.get(t) == Some(IsAllowed::ALLOWED)).count()
+        };
+        for _ in 0..20 {
+            let mut r = NMGRules.clone();
+            let rule = GroupRule::from_user_input(alttp(), "Underworld", "exactly 2").unwrap();
+            rule.apply_with_rng(&mut r, &mut rng);
+            assert_eq!(2, count(&r, rule.members()));
+
+            let rule = GroupRule::from_user_input(alttp(), "Overworld", "together 50%").unwrap();
+            rule.apply_with_rng(&mut r, &mut rng);
+            assert!([0, 5].contains(&count(&r, rule.members())));
+            // other groups are left alone
+            assert_eq!(NMGRules["Hover"], r["Hover"]);
+        }
+        let mut r = NMGRules.clone();
+        GroupRule::from_user_input(alttp(), "Movement", "each 0%").unwrap().apply_with_rng(&mut r, &mut rng);
+        assert_eq!(IsAllowed::DISALLOWED, r["SuperSpeed"]);
+    }
+}
@@ -0,0 +1,97 @@
+//! Just enough iCalendar (RFC 5545) to publish a list of events.
+
+use chrono::{DateTime, Utc};
+
+pub(crate) struct IcsEvent {
+    pub(crate) uid: String,
+    pub(crate) start: DateTime<Utc>,
+    pub(crate) end: DateTime<Utc>,
+    pub(crate) summary: String,
+    pub(crate) description: Option<String>,
+    pub(crate) url: Option<String>,
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line so no line is longer than 75 octets, continuing with a leading space.
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out
+}
+
+fn timestamp(t: &DateTime<Utc>) -> String {
+    t.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+pub(crate) fn render_calendar(name: &str, events: &[IcsEvent], now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Ruleset Randomizer//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+    for e in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", escape_text(&e.uid)));
+        lines.push(format!("DTSTAMP:{}", timestamp(&now)));
+        lines.push(format!("DTSTART:{}", timestamp(&e.start)));
+        lines.push(format!("DTEND:{}", timestamp(&e.end)));
+        lines.push(format!("SUMMARY:{}", escape_text(&e.summary)));
+        if let Some(d) = &e.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(d)));
+        }
+        if let Some(u) = &e.url {
+            lines.push(format!("URL:{}", u));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|l| fold(l) + "\r\n").collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape_text, fold, render_calendar, IcsEvent};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_escape_and_fold() {
+        assert_eq!("a\\, b\\; c\\\\d\\ne", escape_text("a, b; c\\d\ne"));
+        let folded = fold(&"x".repeat(160));
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(vec![75, 75, 12], lines.iter().map(|l| l.len()).collect::<Vec<_>>());
+        assert!(lines[1].starts_with(' '));
+    }
+
+    #[test]
+    fn test_render_calendar() {
+        let event = IcsEvent {
+            uid: "739907@example.com".to_string(),
+            start: Utc.ymd(2026, 10, 18).and_hms(0, 0, 0),
+            end: Utc.ymd(2026, 10, 25).and_hms(0, 0, 0),
+            summary: "Weekly ruleset: October 18, 2026".to_string(),
+            description: None,
+            url: Some("https://example.com/history/739907".to_string()),
+        };
+        let ics = render_calendar("Weekly", &[event], Utc.ymd(2026, 10, 18).and_hms(12, 0, 0));
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.contains("DTSTART:20261018T000000Z\r\nDTEND:20261025T000000Z\r\nSUMMARY:Weekly ruleset: October 18\\, 2026\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Turning rulesets from elsewhere back into a `Ruleset`: JSON (as `/comparisons` emits), YAML (as
+//! exported with `?format=yaml`), CSV (`technique,status` rows, as exported with `?format=csv`) or
+//! a share code. Every technique is checked against the game's catalog; techniques that aren't
+//! mentioned come back `UNSPECIFIED`, like rulesets saved before they existed. JSON and YAML may say
+//! which game they're for (as exports do), and share codes always do; otherwise a ruleset is for
+//! the game it's imported as.
+
+use crate::games::{find_game, Game};
+use crate::rules::{IsAllowed, UserInputError};
+use crate::share::decode_ruleset;
+use crate::techniques::Ruleset;
+use crate::validate::did_you_mean;
+use std::collections::HashSet;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum ImportFormat {
+    Json,
+    Yaml,
+    Csv,
+    ShareCode,
+}
+
+impl ImportFormat {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ImportFormat::Json => "json",
+            ImportFormat::Yaml => "yaml",
+            ImportFormat::Csv => "csv",
+            ImportFormat::ShareCode => "code",
+        }
+    }
+}
+
+impl std::str::FromStr for ImportFormat {
+    type Err = UserInputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ImportFormat::Json),
+            "yaml" | "yml" => Ok(ImportFormat::Yaml),
+            "csv" => Ok(ImportFormat::Csv),
+            "code" | "share" => Ok(ImportFormat::ShareCode),
+            _ => Err(UserInputError {
+                err: format!("Expected one of json, yaml, csv, or code, got {}", s),
+            }),
+        }
+    }
+}
+
+fn error(err: String) -> UserInputError {
+    UserInputError { err }
+}
+
+/// Collects (technique, status) pairs into a ruleset, rejecting unknown techniques, bad statuses
+/// and techniques given twice.
+struct Builder {
+    ruleset: Ruleset,
+    seen: HashSet<String>,
+}
+
+impl Builder {
+    fn new(game: &Game, name: String) -> Self {
+        Self {
+            ruleset: Ruleset::unspecified(game.slug, name),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// `location` says where the pair came from, for error messages.
+    fn set(&mut self, technique: &str, status: &str, location: &str) -> Result<(), UserInputError> {
+        let techniques = self.ruleset.catalog().techniques;
+        if !techniques.contains(&technique) {
+            let hint = match did_you_mean(technique, techniques) {
+                Some(t) if t.eq_ignore_ascii_case(technique) => {
+                    format!(" (techniques are case sensitive: did you mean {}?)", t)
+                }
+                Some(t) => format!(" (did you mean {}?)", t),
+                None => String::new(),
+            };
+            return Err(error(format!("{}: unknown technique {}{}", location, technique, hint)));
+        }
+        if !self.seen.insert(technique.to_string()) {
+            return Err(error(format!("{}: {} is listed more than once", location, technique)));
+        }
+        let allowed: IsAllowed = status.trim().to_uppercase().parse().map_err(|e: UserInputError| {
+            if location == technique {
+                error(format!("{}: {}", technique, e))
+            } else {
+                error(format!("{}: {}: {}", location, technique, e))
+            }
+        })?;
+        self.ruleset.set(technique, allowed);
+        Ok(())
+    }
+}
+
+fn named_game(slug: &str) -> Result<&'static Game, UserInputError> {
+    find_game(slug).ok_or_else(|| error(format!("game: unknown game {}", slug)))
+}
+
+fn import_json(input: &str, game: &Game, name: String) -> Result<Ruleset, UserInputError> {
+    let value: serde_json::Value =
+        serde_json::from_str(input).map_err(|e| error(format!("Invalid JSON: {}", e)))?;
+    let object = value
+        .as_object()
+        .ok_or_else(|| error("Expected a JSON object of technique names to statuses".to_string()))?;
+    let name = object.get("name").and_then(|n| n.as_str()).map(str::to_string).unwrap_or(name);
+    let game = match object.get("game") {
+        Some(g) => named_game(g.as_str().ok_or_else(|| error(format!("game: expected a string, got {}", g)))?)?,
+        None => game,
+    };
+    let mut builder = Builder::new(game, name);
+    for (key, value) in object {
+        if key == "name" || key == "game" {
+            continue;
+        }
+        let status = value
+            .as_str()
+            .ok_or_else(|| error(format!("{}: expected a string status, got {}", key, value)))?;
+        builder.set(key, status, key)?;
+    }
+    Ok(builder.ruleset)
+}
+
+fn import_yaml(input: &str, game: &Game, name: String) -> Result<Ruleset, UserInputError> {
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(input).map_err(|e| error(format!("Invalid YAML: {}", e)))?;
+    let mapping = value
+        .as_mapping()
+        .ok_or_else(|| error("Expected a YAML mapping of technique names to statuses".to_string()))?;
+    let game = match mapping.get(&serde_yaml::Value::from("game")) {
+        Some(g) => named_game(g.as_str().ok_or_else(|| error(format!("game: expected a string, got {:?}", g)))?)?,
+        None => game,
+    };
+    let mut builder = Builder::new(game, name);
+    for (key, value) in mapping {
+        let key = key
+            .as_str()
+            .ok_or_else(|| error(format!("Expected technique names as keys, got {:?}", key)))?;
+        let status = value
+            .as_str()
+            .ok_or_else(|| error(format!("{}: expected a string status, got {:?}", key, value)))?;
+        if key == "name" {
+            builder.ruleset.name = status.to_string();
+            continue;
+        }
+        if key == "game" {
+            continue;
+        }
+        builder.set(key, status, key)?;
+    }
+    Ok(builder.ruleset)
+}
+
+/// Split one CSV line into fields, handling quotes the way spreadsheets write them.
+fn csv_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    Ok(fields.into_iter().map(|f| f.trim().to_string()).collect())
+}
+
+fn import_csv(input: &str, game: &Game, name: String) -> Result<Ruleset, UserInputError> {
+    let mut builder = Builder::new(game, name);
+    for (i, line) in input.lines().enumerate() {
+        let location = format!("line {}", i + 1);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = csv_fields(line).map_err(|e| error(format!("{}: {}", location, e)))?;
+        if i == 0 && fields[0].eq_ignore_ascii_case("technique") {
+            continue;
+        }
+        match fields.as_slice() {
+            [technique, status] => builder.set(technique, status, &location)?,
+            _ => {
+                return Err(error(format!(
+                    "{}: expected 2 columns (technique,status), got {}",
+                    location,
+                    fields.len()
+                )))
+            }
+        }
+    }
+    Ok(builder.ruleset)
+}
+
+/// Parse `input` as `format`, for `game` unless the input says otherwise. `name` is used unless the
+/// input names the ruleset itself.
+pub(crate) fn import_ruleset(
+    format: ImportFormat,
+    input: &str,
+    game: &Game,
+    name: String,
+) -> Result<Ruleset, UserInputError> {
+    match format {
+        ImportFormat::Json => import_json(input, game, name),
+        ImportFormat::Yaml => import_yaml(input, game, name),
+        ImportFormat::Csv => import_csv(input, game, name),
+        ImportFormat::ShareCode => decode_ruleset(input, name),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{import_ruleset, ImportFormat};
+    use crate::export::{export_ruleset, ExportFormat};
+    use crate::games::{default_game, find_game, find_preset};
+    use crate::rules::{IsAllowed, NMGRules};
+    use crate::share::encode_ruleset;
+
+    #[test]
+    fn test_round_trips() {
+        let ruleset = NMGRules.clone();
+        let json = serde_json::to_string(&ruleset).unwrap();
+        assert_eq!(ruleset, import_ruleset(ImportFormat::Json, &json, default_game(), "x".to_string()).unwrap());
+        let yaml = export_ruleset(ExportFormat::Yaml, &ruleset.name, &ruleset.name, &ruleset, None);
+        assert_eq!(ruleset, import_ruleset(ImportFormat::Yaml, &yaml, default_game(), "x".to_string()).unwrap());
+        let csv = export_ruleset(ExportFormat::Csv, &ruleset.name, &ruleset.name, &ruleset, None);
+        let imported = import_ruleset(ImportFormat::Csv, &csv, default_game(), ruleset.name.clone()).unwrap();
+        assert_eq!(ruleset, imported);
+        let code = encode_ruleset(&ruleset);
+        assert_eq!(ruleset, import_ruleset(ImportFormat::ShareCode, &code, default_game(), ruleset.name.clone()).unwrap());
+    }
+
+    #[test]
+    fn test_csv_details() {
+        let r = import_ruleset(ImportFormat::Csv, "\"Hover\", allowed\n\nBombJump,Disallowed\n", default_game(), "x".to_string()).unwrap();
+        assert_eq!(IsAllowed::ALLOWED, r["Hover"]);
+        assert_eq!(IsAllowed::DISALLOWED, r["BombJump"]);
+        assert_eq!(IsAllowed::UNSPECIFIED, r["HeraPot"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |format, input: &str| import_ruleset(format, input, default_game(), "x".to_string()).unwrap_err().to_string();
+        assert_eq!(
+            "line 2: unknown technique hover (techniques are case sensitive: did you mean Hover?)",
+            err(ImportFormat::Csv, "technique,status\nhover,ALLOWED")
+        );
+        assert_eq!("line 2: Hover is listed more than once", err(ImportFormat::Csv, "Hover,ALLOWED\nHover,DISALLOWED"));
+        assert_eq!(
+            "line 1: expected 2 columns (technique,status), got 3",
+            err(ImportFormat::Csv, "Hover,ALLOWED,yes")
+        );
+        assert_eq!(
+            "Hover: Expected ALLOWED, DISALLOWED, or UNSPECIFIED, got MAYBE",
+            err(ImportFormat::Json, r#"{"Hover": "maybe"}"#)
+        );
+        assert_eq!("Hover: expected a string status, got 3", err(ImportFormat::Json, r#"{"Hover": 3}"#));
+        assert!(err(ImportFormat::Yaml, "- Hover").starts_with("Expected a YAML mapping"));
+        assert_eq!("line 1: unknown technique BlueSuit", err(ImportFormat::Csv, "BlueSuit,ALLOWED"));
+    }
+
+    #[test]
+    fn test_games() {
+        let sm = find_game("sm").unwrap();
+        let r = import_ruleset(ImportFormat::Csv, "BlueSuit,ALLOWED", sm, "x".to_string()).unwrap();
+        assert_eq!("sm", r.game);
+        assert_eq!(Some(IsAllowed::ALLOWED), r.get("BlueSuit"));
+        // JSON says which game it's for
+        let any = find_preset("SMAnyPercent").unwrap();
+        let json = serde_json::to_string(any).unwrap();
+        assert_eq!(any, &import_ruleset(ImportFormat::Json, &json, default_game(), "x".to_string()).unwrap());
+    }
+}
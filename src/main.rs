@@ -1,43 +1,50 @@
+mod analytics;
+mod card;
+mod cli;
+mod discord;
+mod export;
+mod games;
+mod groups;
+mod ics;
+mod import;
+mod markdown;
 mod rules;
+mod share;
+mod similarity;
+mod standings;
+mod stats;
 mod techniques;
+mod templates;
+mod tracks;
+mod validate;
 mod web;
+mod webhooks;
+mod weights;
 
-use crate::rules::{InputWeights, IsAllowed, MGRules, NMGRules, NoEGRules, RMGRules, TemplateState, get_weekly_ruleset};
-use crate::techniques::{Ruleset, RulesetTemplate, TECHNIQUE_NAMES};
+use crate::games::{default_game, find_game, games};
+use crate::rules::{InputWeights, IsAllowed, TemplateState};
+use crate::techniques::Ruleset;
 use chrono::{Date, DateTime, Datelike, Month, Offset, TimeZone, Weekday};
 use rand::rngs::SmallRng;
 use rand::SeedableRng;
-use rocket::form::{Form, FromForm};
-use rocket::fs::{NamedFile, TempFile};
 use rocket::request::{FromRequest, Outcome};
 use rocket::response::Redirect;
 use rocket::serde::json::Json;
-use rocket::{get, post};
-use rocket::{Build, Request, Rocket};
+use rocket::get;
+use rocket::{Build, Request, Rocket, State};
 use rocket_dyn_templates::Template;
 use serde::Serialize;
 use serde_yaml;
 use std::collections::HashMap;
-use std::fs::read_to_string;
 use std::path::{Path, PathBuf};
 use sqlx::SqlitePool;
-use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::migrate::{MigrateError, Migrator};
 
 
-#[derive(Serialize)]
-struct EmptyContext {}
-
-const EMPTY_CONTEXT: EmptyContext = EmptyContext {};
-
 // TODO: combine these static-ish-pages routes into one
 
 
-#[get("/upload")]
-async fn upload_form() -> Template {
-    Template::render("submit_weights", EMPTY_CONTEXT)
-}
-
 #[get("/")]
 async fn root() -> Redirect {
     // can't use the `weekly` function b/c it's not in scope and i guess i dont really want to make it in scope?
@@ -49,31 +56,16 @@ struct RulesetContext<'a> {
     ruleset: &'a Ruleset,
 }
 
-#[derive(FromForm)]
-struct Upload<'f> {
-    upload: TempFile<'f>,
-}
-
-#[post("/upload", data = "<form>")]
-async fn upload(mut form: Form<Upload<'_>>) {
-    println!("{:?}", form.upload);
-    let c = match &form.upload {
-        TempFile::File { path, .. } => read_to_string(path).unwrap(),
-        TempFile::Buffered { content } => content.to_string(),
+#[get("/comparisons?<game>")]
+async fn comparisons(game: Option<&str>, pool: &State<SqlitePool>) -> Option<Json<Vec<Ruleset>>> {
+    let game = match game {
+        Some(slug) => find_game(slug)?,
+        None => default_game(),
     };
-    // form.upload.persist_to("uploads/blah").await;
-    println!("Uploaded file contents: {}", c);
-}
-
-#[get("/comparisons")]
-async fn comparisons() -> Json<Vec<Ruleset>> {
-    Json(vec![
-        NMGRules.clone(),
-        RMGRules.clone(),
-        NoEGRules.clone(),
-        MGRules.clone(),
-        get_weekly_ruleset(),
-    ])
+    let mut rulesets: Vec<Ruleset> = game.presets.iter().map(|p| p.ruleset.clone()).collect();
+    rulesets.push(web::current_weekly_ruleset(game, pool).await);
+    rulesets.extend(web::get_presets(pool).await.into_iter().filter(|r| r.game == game.slug));
+    Some(Json(rulesets))
 }
 
 #[get("/world")]
@@ -87,8 +79,6 @@ fn build_rocket() -> Rocket<Build> {
             "/",
             rocket::routes![
                 hello,
-                // upload_form,
-                // upload,
                 root,
                 comparisons,
             ],
@@ -98,30 +88,53 @@ fn build_rocket() -> Rocket<Build> {
     r
 }
 
-async fn get_pool() -> Result<SqlitePool, sqlx::Error> {
+fn connect_options() -> SqliteConnectOptions {
     let sqlite_db_path = std::env::var("DATABASE_PATH").unwrap_or("db/test.db3".to_string());
     let p = Path::new(&sqlite_db_path);
     std::fs::create_dir_all(p.parent().unwrap()).unwrap();
-    // use a SqliteConnectOptions instead of a hardcoded queryparam?
-    let path_with_params = format!("sqlite://{}?mode=rwc", sqlite_db_path);
+    SqliteConnectOptions::new().filename(p).create_if_missing(true)
+}
+
+async fn get_pool() -> Result<SqlitePool, sqlx::Error> {
     SqlitePoolOptions::new()
         .max_connections(12)
-        .connect(&path_with_params)
+        .connect_with(connect_options())
         .await
 }
 
-async fn run_migrations(pool: &SqlitePool) -> Result<(), MigrateError> {
+/// Run the migrations on a connection of their own, without foreign key checks: migrations that
+/// rebuild a table drop it while other tables still reference it.
+async fn run_migrations() -> Result<(), MigrateError> {
     let migrator = Migrator::new(Path::new("migrations")).await?;
-    migrator.run(pool).await
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(connect_options().foreign_keys(false))
+        .await?;
+    migrator.run(&pool).await
 }
 
 #[rocket::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        let pool = get_pool().await.unwrap();
+        if let Err(e) = run_migrations().await {
+            eprintln!("Migration error: {:?}", e);
+            std::process::exit(1);
+        }
+        if let Err(e) = web::init_supplemental_rules(&pool).await {
+            eprintln!("Error loading supplemental rules: {}", e);
+            std::process::exit(1);
+        }
+        std::process::exit(cli::run(&args, &pool).await);
+    }
+
     println!("Hello, world!");
     let mut t = InputWeights {
         name: "hi".to_string(),
         defaults: "NMGRules".to_string(),
         weights: Default::default(),
+        groups: Default::default(),
     };
 
     t.weights
@@ -129,13 +142,31 @@ async fn main() {
     println!("{}", serde_yaml::to_string(&t).unwrap());
 
     let pool = get_pool().await.unwrap();
-    match run_migrations(&pool).await {
+    match run_migrations().await {
         Ok(_) => {},
         Err(e) => {
             println!("Migration error: {:?}", e);
             panic!();
         }
     }
+    if let Err(e) = web::init_supplemental_rules(&pool).await {
+        println!("Error loading supplemental rules: {}", e);
+        panic!();
+    }
+    if let Err(e) = web::index_saved_rulesets(&pool).await {
+        println!("Error indexing saved rulesets, history search will miss some: {}", e);
+    }
+    match webhooks::resume_deliveries(webhooks::DEFAULT_RETRY_POLICY, &pool).await {
+        Ok(0) => {}
+        Ok(n) => println!("Resuming {} webhook deliveries", n),
+        Err(e) => println!("Error resuming webhook deliveries: {:?}", e),
+    }
+    // fail now rather than on the first request if a game's data is broken
+    println!(
+        "Hosting {} game(s) with {} track(s)",
+        games().len(),
+        games().iter().map(|g| tracks::tracks(g).len()).sum::<usize>()
+    );
     let rocket = build_rocket()
         .manage(pool);
     let ignited = rocket.ignite().await.unwrap();
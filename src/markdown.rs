@@ -0,0 +1,71 @@
+//! Markdown rendering for user-written text. Raw HTML is never passed through: it's shown as
+//! escaped text instead, and links or images pointing anywhere but http(s)/mailto are dropped.
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+fn safe_url(url: &str) -> bool {
+    let lower = url.trim().to_lowercase();
+    match lower.find(':') {
+        // relative links, as long as the colon is in a path/query rather than a scheme
+        Some(i) if lower[..i].contains(['/', '?', '#']) => true,
+        Some(_) => lower.starts_with("http:") || lower.starts_with("https:") || lower.starts_with("mailto:"),
+        None => true,
+    }
+}
+
+fn defuse_tag(tag: Tag) -> Tag {
+    match tag {
+        Tag::Link(kind, url, title) if !safe_url(&url) => Tag::Link(kind, CowStr::from(""), title),
+        Tag::Image(kind, url, title) if !safe_url(&url) => Tag::Image(kind, CowStr::from(""), title),
+        tag => tag,
+    }
+}
+
+/// Render user-written markdown to HTML that's safe to put on the page unescaped.
+pub(crate) fn render_markdown(input: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    let events = Parser::new_ext(input, options).map(|event| match event {
+        Event::Html(raw) => Event::Text(raw),
+        Event::Start(tag) => Event::Start(defuse_tag(tag)),
+        Event::End(tag) => Event::End(defuse_tag(tag)),
+        event => event,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::render_markdown;
+
+    #[test]
+    fn test_render_markdown() {
+        assert_eq!(
+            "<p><strong>Hover</strong> to <a href=\"https://example.com\">desert</a></p>\n",
+            render_markdown("**Hover** to [desert](https://example.com)")
+        );
+        assert_eq!(
+            "&lt;script&gt;alert(1)&lt;/script&gt;",
+            render_markdown("<script>alert(1)</script>")
+        );
+        assert_eq!(
+            "<p>a &lt;img src=x onerror=alert(1)&gt; b</p>\n",
+            render_markdown("a <img src=x onerror=alert(1)> b")
+        );
+        assert_eq!(
+            "<p><a href=\"\">click</a></p>\n",
+            render_markdown("[click](javascript:alert(1))")
+        );
+        assert_eq!(
+            "<p><a href=\"\">click</a></p>\n",
+            render_markdown("[click]( JavaScript:alert(1))")
+        );
+        assert_eq!(
+            "<p><a href=\"/history/1?a=b:c\">week</a></p>\n",
+            render_markdown("[week](/history/1?a=b:c)")
+        );
+    }
+}
@@ -1,141 +1,39 @@
-use crate::techniques::{Ruleset, RulesetTemplate, TECHNIQUE_NAMES};
+// for the ALttP presets below, which keep their names from when they were written out here:
+// lazy_static doesn't pass an `allow` on to the statics it makes
+#![allow(non_upper_case_globals)]
+
+use crate::groups::{parse_group_weights, GroupRule};
+use crate::games::find_preset;
+use crate::techniques::{Catalog, Ruleset, RulesetTemplate};
+use crate::validate::{has_errors, unknown_techniques, Diagnostic};
+use crate::weights::{Chance, ChanceError};
 use custom_error::custom_error;
 use lazy_static::lazy_static;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use rand::rngs::SmallRng;
 use chrono::{TimeZone, Date, Datelike};
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 // use rand::{SeedableRng, RngCore, Rng};
 
 lazy_static! {
-    static ref PERCENT_WEIGHT_PATTERN: Regex = Regex::new("(\\d+)%?").unwrap();
+    // ALttP's presets, from games/alttp/presets.yaml
 
     #[allow(non_upper_case_globals)]
-    pub(crate) static ref NMGRules: Ruleset = Ruleset {
-        name: "NMG".to_owned(),
-        SaveAndQuit: IsAllowed::DISALLOWED,
-        FakeFlippers: IsAllowed::ALLOWED,
-        BombJump: IsAllowed::ALLOWED,
-        SilverlessGanon: IsAllowed::ALLOWED,
-        ItemDash: IsAllowed::ALLOWED,
-        OverworldClipping: IsAllowed::DISALLOWED,
-        OverworldYBA: IsAllowed::DISALLOWED,
-        SuperSpeed: IsAllowed::ALLOWED,
-        OverworldEG: IsAllowed::DISALLOWED,
-        Misslotting: IsAllowed::DISALLOWED,
-        HeraPot: IsAllowed::UNSPECIFIED,
-        HookShopping: IsAllowed::DISALLOWED,
-        LayerDisparity: IsAllowed::DISALLOWED,
-        OverworldSwimmyG: IsAllowed::DISALLOWED,
-        UnderworldClipping: IsAllowed::DISALLOWED,
-        AncillaOverload: IsAllowed::ALLOWED,
-        DiverDown: IsAllowed::DISALLOWED,
-        DoorJukes: IsAllowed::DISALLOWED,
-        DoorStateExtension: IsAllowed::DISALLOWED,
-        HammerJump: IsAllowed::ALLOWED,
-        Hover: IsAllowed::ALLOWED,
-        OverworldBunnyRevival: IsAllowed::UNSPECIFIED,
-        OverworldMirrorGlitches: IsAllowed::DISALLOWED,
-        SomariaTransitionCorruption: IsAllowed::DISALLOWED,
-        UnderworldDeathHole: IsAllowed::DISALLOWED,
-        UnderworldYBA: IsAllowed::DISALLOWED,
-    };
+    pub(crate) static ref NMGRules: Ruleset = alttp_preset("NMGRules");
 
     #[allow(non_upper_case_globals)]
-    pub(crate) static ref RMGRules: Ruleset = Ruleset {
-        name: "RMG".to_owned(),
-        SaveAndQuit: IsAllowed::ALLOWED,
-        FakeFlippers: IsAllowed::ALLOWED,
-        BombJump: IsAllowed::ALLOWED,
-        SilverlessGanon: IsAllowed::ALLOWED,
-        ItemDash: IsAllowed::ALLOWED,
-        OverworldClipping: IsAllowed::ALLOWED,
-        OverworldYBA: IsAllowed::ALLOWED,
-        SuperSpeed: IsAllowed::ALLOWED,
-        OverworldEG: IsAllowed::ALLOWED,
-        Misslotting: IsAllowed::DISALLOWED,
-        HeraPot: IsAllowed::ALLOWED,
-        HookShopping: IsAllowed::UNSPECIFIED,
-        LayerDisparity: IsAllowed::DISALLOWED,
-        OverworldSwimmyG: IsAllowed::UNSPECIFIED,
-        UnderworldClipping: IsAllowed::ALLOWED,
-        AncillaOverload: IsAllowed::ALLOWED,
-        DiverDown: IsAllowed::ALLOWED,
-        DoorJukes: IsAllowed::DISALLOWED,
-        DoorStateExtension: IsAllowed::ALLOWED,
-        HammerJump: IsAllowed::ALLOWED,
-        Hover: IsAllowed::ALLOWED,
-        OverworldBunnyRevival: IsAllowed::ALLOWED,
-        OverworldMirrorGlitches: IsAllowed::ALLOWED,
-        SomariaTransitionCorruption: IsAllowed::DISALLOWED,
-        UnderworldDeathHole: IsAllowed::DISALLOWED,
-        UnderworldYBA: IsAllowed::DISALLOWED,
-    };
+    pub(crate) static ref RMGRules: Ruleset = alttp_preset("RMGRules");
 
     #[allow(non_upper_case_globals)]
-    pub(crate) static ref NoEGRules: Ruleset = Ruleset {
-        name: "No EG".to_owned(),
-        SaveAndQuit: IsAllowed::ALLOWED,
-        FakeFlippers: IsAllowed::ALLOWED,
-        BombJump: IsAllowed::ALLOWED,
-        SilverlessGanon: IsAllowed::ALLOWED,
-        ItemDash: IsAllowed::ALLOWED,
-        OverworldClipping: IsAllowed::ALLOWED,
-        OverworldYBA: IsAllowed::ALLOWED,
-        SuperSpeed: IsAllowed::ALLOWED,
-        OverworldEG: IsAllowed::ALLOWED,
-        Misslotting: IsAllowed::ALLOWED,
-        HeraPot: IsAllowed::ALLOWED,
-        HookShopping: IsAllowed::ALLOWED,
-        LayerDisparity: IsAllowed::DISALLOWED,
-        OverworldSwimmyG: IsAllowed::UNSPECIFIED,
-        UnderworldClipping: IsAllowed::ALLOWED,
-        AncillaOverload: IsAllowed::ALLOWED,
-        DiverDown: IsAllowed::ALLOWED,
-        DoorJukes: IsAllowed::ALLOWED,
-        DoorStateExtension: IsAllowed::ALLOWED,
-        HammerJump: IsAllowed::ALLOWED,
-        Hover: IsAllowed::ALLOWED,
-        OverworldBunnyRevival: IsAllowed::ALLOWED,
-        OverworldMirrorGlitches: IsAllowed::ALLOWED,
-        SomariaTransitionCorruption: IsAllowed::ALLOWED,
-        UnderworldDeathHole: IsAllowed::ALLOWED,
-        UnderworldYBA: IsAllowed::ALLOWED,
-    };
-
+    pub(crate) static ref NoEGRules: Ruleset = alttp_preset("NoEGRules");
 
     #[allow(non_upper_case_globals)]
-    pub(crate) static ref MGRules: Ruleset = Ruleset {
-        name: "MG".to_owned(),
-        SaveAndQuit: IsAllowed::ALLOWED,
-        FakeFlippers: IsAllowed::ALLOWED,
-        BombJump: IsAllowed::ALLOWED,
-        SilverlessGanon: IsAllowed::ALLOWED,
-        ItemDash: IsAllowed::ALLOWED,
-        OverworldClipping: IsAllowed::ALLOWED,
-        OverworldYBA: IsAllowed::ALLOWED,
-        SuperSpeed: IsAllowed::ALLOWED,
-        OverworldEG: IsAllowed::ALLOWED,
-        Misslotting: IsAllowed::ALLOWED,
-        HeraPot: IsAllowed::ALLOWED,
-        HookShopping: IsAllowed::ALLOWED,
-        LayerDisparity: IsAllowed::ALLOWED,
-        OverworldSwimmyG: IsAllowed::ALLOWED,
-        UnderworldClipping: IsAllowed::ALLOWED,
-        AncillaOverload: IsAllowed::ALLOWED,
-        DiverDown: IsAllowed::ALLOWED,
-        DoorJukes: IsAllowed::ALLOWED,
-        DoorStateExtension: IsAllowed::ALLOWED,
-        HammerJump: IsAllowed::ALLOWED,
-        Hover: IsAllowed::ALLOWED,
-        OverworldBunnyRevival: IsAllowed::ALLOWED,
-        OverworldMirrorGlitches: IsAllowed::ALLOWED,
-        SomariaTransitionCorruption: IsAllowed::ALLOWED,
-        UnderworldDeathHole: IsAllowed::ALLOWED,
-        UnderworldYBA: IsAllowed::ALLOWED,
-    };
+    pub(crate) static ref MGRules: Ruleset = alttp_preset("MGRules");
+}
+
+fn alttp_preset(id: &str) -> Ruleset {
+    find_preset(id).unwrap_or_else(|| panic!("games/alttp/presets.yaml needs a {} preset", id)).clone()
 }
 
 
@@ -146,15 +44,42 @@ pub(crate) enum IsAllowed {
     UNSPECIFIED,
 }
 
+impl IsAllowed {
+    pub(crate) const ALL: [IsAllowed; 3] = [IsAllowed::ALLOWED, IsAllowed::DISALLOWED, IsAllowed::UNSPECIFIED];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            IsAllowed::ALLOWED => "ALLOWED",
+            IsAllowed::DISALLOWED => "DISALLOWED",
+            IsAllowed::UNSPECIFIED => "UNSPECIFIED",
+        }
+    }
+}
+
+impl std::str::FromStr for IsAllowed {
+    type Err = UserInputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ALLOWED" => Ok(IsAllowed::ALLOWED),
+            "DISALLOWED" => Ok(IsAllowed::DISALLOWED),
+            "UNSPECIFIED" => Ok(IsAllowed::UNSPECIFIED),
+            _ => Err(UserInputError {
+                err: format!("Expected ALLOWED, DISALLOWED, or UNSPECIFIED, got {}", s),
+            }),
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub(crate) enum TemplateState {
     STATIC(IsAllowed),
-    CHANCE_PER_THOUSAND(u16),
+    CHANCE(Chance),
     USE_DEFAULT,
 }
 
 custom_error! {
-    TemplateStateParseError { err: String } = "{err}"
+    pub(crate) TemplateStateParseError { err: String } = "{err}"
 
 }
 
@@ -162,35 +87,41 @@ impl TemplateState {
     const ERR: &'static str = r#"Expected "true", "false", or a number."#;
 
     fn _maybe_from_user_input(user_input: String) -> Option<Self> {
-        if user_input.to_lowercase() == "true" {
-            Some(Self::STATIC(IsAllowed::ALLOWED))
-        } else if user_input.to_lowercase() == "false" {
-            Some(Self::STATIC(IsAllowed::DISALLOWED))
-        } else if let Some(m) = PERCENT_WEIGHT_PATTERN.captures(&*user_input) {
-            match m[1].parse::<u16>() {
-                Ok(w) => {
-                    if (0..=1000).contains(&w) {
-                        Some(Self::CHANCE_PER_THOUSAND(w))
-                    } else {
-                        None
-                    }
-                }
-                Err(e) => {
-                    println!("Error parsing {} as a u16: {}", user_input, e);
-                    None
-                }
-            }
-        } else {
-            None
+        Self::from_user_input(user_input).ok()
+    }
+
+    /// Short description for display, e.g. `20%` or `always DISALLOWED`.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::STATIC(a) => format!("always {}", a.as_str()),
+            Self::CHANCE(c) => c.to_string(),
+            Self::USE_DEFAULT => "default".to_string(),
         }
     }
 
-    fn from_user_input(user_input: String) -> Result<Self, TemplateStateParseError> {
-        match Self::_maybe_from_user_input(user_input) {
-            Some(s) => Ok(s),
-            None => Err(TemplateStateParseError {
+    /// How this would be written in a weights file, for the states that can be.
+    pub(crate) fn to_user_input(self) -> Option<String> {
+        match self {
+            Self::STATIC(IsAllowed::ALLOWED) => Some("true".to_string()),
+            Self::STATIC(IsAllowed::DISALLOWED) => Some("false".to_string()),
+            Self::CHANCE(c) => Some(c.to_string()),
+            Self::STATIC(IsAllowed::UNSPECIFIED) | Self::USE_DEFAULT => None,
+        }
+    }
+
+    /// `true`, `false`, or a chance in any of the forms in `crate::weights`.
+    pub(crate) fn from_user_input(user_input: String) -> Result<Self, TemplateStateParseError> {
+        if user_input.trim().eq_ignore_ascii_case("true") {
+            return Ok(Self::STATIC(IsAllowed::ALLOWED));
+        } else if user_input.trim().eq_ignore_ascii_case("false") {
+            return Ok(Self::STATIC(IsAllowed::DISALLOWED));
+        }
+        match user_input.parse::<Chance>() {
+            Ok(c) => Ok(Self::CHANCE(c)),
+            Err(ChanceError::NotAChance) => Err(TemplateStateParseError {
                 err: Self::ERR.to_string(),
             }),
+            Err(ChanceError::Invalid(err)) => Err(TemplateStateParseError { err }),
         }
     }
 }
@@ -201,6 +132,9 @@ pub(crate) struct InputWeights {
     pub(crate) name: String,
     pub(crate) defaults: String,
     pub(crate) weights: HashMap<String, String>,
+    /// Weights for whole technique groups; see `crate::groups`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) groups: HashMap<String, String>,
 }
 
 #[derive(Debug)]
@@ -208,6 +142,32 @@ pub(crate) struct MungedInputWeights {
     pub(crate) name: String,
     pub(crate) defaults: &'static Ruleset,
     pub(crate) weights: HashMap<String, TemplateState>,
+    pub(crate) groups: Vec<GroupRule>,
+}
+
+impl MungedInputWeights {
+    /// Roll the technique weights over the defaults, then each group.
+    pub(crate) fn roll_with_rng<R: Rng>(&self, rng: &mut R) -> Ruleset {
+        let mut r = RulesetTemplate::from_template_states(&self.weights).apply_with_rng(self.defaults, rng);
+        for group in &self.groups {
+            group.apply_with_rng(&mut r, rng);
+        }
+        r.name = self.name.clone();
+        r
+    }
+
+    /// The chance each technique comes out `ALLOWED`, in catalog order.
+    pub(crate) fn allowed_chances(&self) -> Vec<(&'static str, f64)> {
+        let mut chances = RulesetTemplate::from_template_states(&self.weights).allowed_chances(self.defaults);
+        for group in &self.groups {
+            for (technique, chance) in chances.iter_mut() {
+                if group.members().contains(technique) {
+                    *chance = group.allowed_chance();
+                }
+            }
+        }
+        chances
+    }
 }
 
 custom_error! {
@@ -223,10 +183,11 @@ parsed TemplateStates and a list of unexpected keys (if any).
  */
 fn parse_weights(
     mut input_weights: HashMap<String, String>,
+    catalog: &Catalog,
 ) -> Result<(HashMap<String, TemplateState>, Option<Vec<String>>), UserInputError> {
     let mut parsed: HashMap<String, TemplateState> = Default::default();
-    for k in TECHNIQUE_NAMES {
-        if let Some(ts_input) = input_weights.remove(k) {
+    for k in catalog.techniques {
+        if let Some(ts_input) = input_weights.remove(*k) {
             match TemplateState::from_user_input(ts_input) {
                 Ok(ts) => {
                     parsed.insert(k.to_string(), ts);
@@ -256,74 +217,110 @@ fn parse_weights(
     Ok((parsed, keys))
 }
 
-fn find_default(defaults_name: String) -> Result<&'static Ruleset, UserInputError> {
-    match defaults_name.as_str() {
-        "NMGRules" => Ok(&NMGRules),
-        _ => Err(UserInputError {
-            err: format!("Unknown value for `defaults` field: {}", defaults_name),
-        }),
-    }
+/// A preset from any game; the weights are then for that game's techniques.
+pub(crate) fn find_default(defaults_name: String) -> Result<&'static Ruleset, UserInputError> {
+    find_preset(&defaults_name).ok_or_else(|| UserInputError {
+        err: format!("Unknown value for `defaults` field: {}", defaults_name),
+    })
 }
 
-fn munge_user_input(user_input: InputWeights) -> Result<MungedInputWeights, UserInputError> {
-    let (parsed, unknown_keys) = parse_weights(user_input.weights)?;
-    if let Some(ks) = unknown_keys {
-        println!("Unknown user input keys: {}", ks.join(", "));
-    }
+/// Parse `user_input`'s weights for its defaults' game. Weights for techniques the game doesn't
+/// have are returned as warnings, or fail it if `strict`.
+pub(crate) fn munge_user_input(
+    user_input: InputWeights,
+    strict: bool,
+) -> Result<(MungedInputWeights, Vec<Diagnostic>), UserInputError> {
     let defaults = find_default(user_input.defaults)?;
-    Ok(MungedInputWeights {
+    let (parsed, unknown_keys) = parse_weights(user_input.weights, defaults.catalog())?;
+    let diagnostics = unknown_techniques(&unknown_keys.unwrap_or_default(), defaults.catalog(), strict);
+    if has_errors(&diagnostics) {
+        return Err(UserInputError {
+            err: diagnostics.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("\n"),
+        });
+    }
+    let groups = parse_group_weights(defaults.catalog(), user_input.groups, &parsed)?;
+    let munged = MungedInputWeights {
         name: user_input.name,
         defaults,
         weights: parsed,
-    })
+        groups,
+    };
+    Ok((munged, diagnostics))
 }
 
-pub(crate) fn parse_user_input(yaml: String) -> Result<MungedInputWeights, UserInputError> {
-    match serde_yaml::from_str::<InputWeights>(&yaml) {
-        Ok(iw) => {
-            munge_user_input(iw)
-        }
-        Err(e) => Err(UserInputError {
-            err: format!("Invalid input yaml: {}", e),
-        }),
-    }
+/// A technique whose status differs between two rulesets.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub(crate) struct RuleChange {
+    pub(crate) technique: String,
+    pub(crate) before: IsAllowed,
+    pub(crate) after: IsAllowed,
+}
 
+/// Every technique whose status changes going from `before` to `after`, in `after`'s catalog order.
+pub(crate) fn diff_rulesets(before: &Ruleset, after: &Ruleset) -> Vec<RuleChange> {
+    after
+        .catalog()
+        .techniques
+        .iter()
+        .filter_map(|t| {
+            let (b, a) = (before.get(t)?, after.get(t)?);
+            if a != b {
+                Some(RuleChange {
+                    technique: t.to_string(),
+                    before: b,
+                    after: a,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
 }
 
+/// The seed the weekly for the period starting on `sunday` is rolled with, absent any admin re-roll.
+pub(crate) fn weekly_seed<TZ: TimeZone>(sunday: &Date<TZ>) -> u64 {
+    1 + sunday.num_days_from_ce() as u64
+}
 
-pub(crate) fn get_weekly_ruleset() -> Ruleset {
-    let rt = RulesetTemplate {
-        SaveAndQuit: TemplateState::CHANCE_PER_THOUSAND(200),
-        FakeFlippers: TemplateState::CHANCE_PER_THOUSAND(980),
-        BombJump: TemplateState::CHANCE_PER_THOUSAND(980),
-        SilverlessGanon: TemplateState::CHANCE_PER_THOUSAND(990),
-        ItemDash: TemplateState::CHANCE_PER_THOUSAND(950),
-        AncillaOverload: TemplateState::CHANCE_PER_THOUSAND(950),
-        Hover: TemplateState::CHANCE_PER_THOUSAND(850),
-        HammerJump: TemplateState::CHANCE_PER_THOUSAND(980),
-        DoorStateExtension: TemplateState::CHANCE_PER_THOUSAND(330),
-        DiverDown: TemplateState::CHANCE_PER_THOUSAND(330),
-        OverworldBunnyRevival: TemplateState::CHANCE_PER_THOUSAND(800),
-        HeraPot: TemplateState::CHANCE_PER_THOUSAND(200),
-        OverworldClipping: TemplateState::CHANCE_PER_THOUSAND(100),
-        OverworldMirrorGlitches: TemplateState::CHANCE_PER_THOUSAND(100),
-        OverworldYBA: TemplateState::CHANCE_PER_THOUSAND(100),
-        SuperSpeed: TemplateState::CHANCE_PER_THOUSAND(950),
-        OverworldEG: TemplateState::CHANCE_PER_THOUSAND(50),
-        Misslotting: TemplateState::CHANCE_PER_THOUSAND(50),
-        HookShopping: TemplateState::CHANCE_PER_THOUSAND(100),
-        OverworldSwimmyG: TemplateState::CHANCE_PER_THOUSAND(100),
-        UnderworldClipping: TemplateState::CHANCE_PER_THOUSAND(50),
-        UnderworldYBA: TemplateState::CHANCE_PER_THOUSAND(30),
-        UnderworldDeathHole: TemplateState::CHANCE_PER_THOUSAND(30),
-        SomariaTransitionCorruption: TemplateState::CHANCE_PER_THOUSAND(30),
-        DoorJukes: TemplateState::CHANCE_PER_THOUSAND(20),
-        LayerDisparity: TemplateState::STATIC(IsAllowed::DISALLOWED),
-    };
-    let now = chrono::offset::Utc::now();
-    let last_sunday = most_recent_sunday(now.date());
+/// The weights every weekly is rolled from, on top of `NMGRules`.
+pub(crate) fn weekly_template() -> RulesetTemplate {
+    let weights: HashMap<String, TemplateState> = [
+        ("SaveAndQuit", TemplateState::CHANCE(Chance::per_thousand(200))),
+        ("FakeFlippers", TemplateState::CHANCE(Chance::per_thousand(980))),
+        ("BombJump", TemplateState::CHANCE(Chance::per_thousand(980))),
+        ("SilverlessGanon", TemplateState::CHANCE(Chance::per_thousand(990))),
+        ("ItemDash", TemplateState::CHANCE(Chance::per_thousand(950))),
+        ("AncillaOverload", TemplateState::CHANCE(Chance::per_thousand(950))),
+        ("Hover", TemplateState::CHANCE(Chance::per_thousand(850))),
+        ("HammerJump", TemplateState::CHANCE(Chance::per_thousand(980))),
+        ("DoorStateExtension", TemplateState::CHANCE(Chance::per_thousand(330))),
+        ("DiverDown", TemplateState::CHANCE(Chance::per_thousand(330))),
+        ("OverworldBunnyRevival", TemplateState::CHANCE(Chance::per_thousand(800))),
+        ("HeraPot", TemplateState::CHANCE(Chance::per_thousand(200))),
+        ("OverworldClipping", TemplateState::CHANCE(Chance::per_thousand(100))),
+        ("OverworldMirrorGlitches", TemplateState::CHANCE(Chance::per_thousand(100))),
+        ("OverworldYBA", TemplateState::CHANCE(Chance::per_thousand(100))),
+        ("SuperSpeed", TemplateState::CHANCE(Chance::per_thousand(950))),
+        ("OverworldEG", TemplateState::CHANCE(Chance::per_thousand(50))),
+        ("Misslotting", TemplateState::CHANCE(Chance::per_thousand(50))),
+        ("HookShopping", TemplateState::CHANCE(Chance::per_thousand(100))),
+        ("OverworldSwimmyG", TemplateState::CHANCE(Chance::per_thousand(100))),
+        ("UnderworldClipping", TemplateState::CHANCE(Chance::per_thousand(50))),
+        ("UnderworldYBA", TemplateState::CHANCE(Chance::per_thousand(30))),
+        ("UnderworldDeathHole", TemplateState::CHANCE(Chance::per_thousand(30))),
+        ("SomariaTransitionCorruption", TemplateState::CHANCE(Chance::per_thousand(30))),
+        ("DoorJukes", TemplateState::CHANCE(Chance::per_thousand(20))),
+        ("LayerDisparity", TemplateState::STATIC(IsAllowed::DISALLOWED)),
+    ]
+    .iter()
+    .map(|(technique, state)| (technique.to_string(), *state))
+    .collect();
+    RulesetTemplate::from_template_states(&weights)
+}
 
-    let mut rng = SmallRng::seed_from_u64(1 + last_sunday.num_days_from_ce() as u64);
+pub(crate) fn roll_weekly_ruleset(seed: u64) -> Ruleset {
+    let rt = weekly_template();
+    let mut rng = SmallRng::seed_from_u64(seed);
     let mut r = rt.apply_with_rng(&NMGRules, &mut rng);
     r.name = "Weekly".to_string();
     r
@@ -337,12 +334,13 @@ pub(crate) fn most_recent_sunday<TZ: TimeZone>(mut d: Date<TZ>) -> Date<TZ> {
     d
 }
 
-
+#[cfg(test)]
 mod test {
     use super::TemplateState;
-    use crate::rules::{find_default, IsAllowed, NMGRules, UserInputError, parse_weights, InputWeights, munge_user_input, MungedInputWeights, parse_user_input};
+    use crate::rules::{find_default, IsAllowed, NMGRules, UserInputError, parse_weights, InputWeights, munge_user_input};
     use std::collections::HashMap;
-    use crate::techniques::{RulesetTemplate, Ruleset};
+    use crate::techniques::RulesetTemplate;
+    use crate::weights::Chance;
     use rand::rngs::SmallRng;
     use rand::SeedableRng;
 
@@ -391,12 +389,13 @@ mod test {
             TemplateState::_maybe_from_user_input("fALse".to_string())
         );
 
+        // a bare 69 could be 69% or 69 per thousand
         assert_eq!(
-            Some(TemplateState::CHANCE_PER_THOUSAND(69)),
+            None,
             TemplateState::_maybe_from_user_input("69".to_lowercase())
         );
         assert_eq!(
-            Some(TemplateState::CHANCE_PER_THOUSAND(69)),
+            Some(TemplateState::CHANCE(Chance::per_thousand(690))),
             TemplateState::_maybe_from_user_input("69%".to_lowercase())
         );
     }
@@ -416,7 +415,7 @@ mod test {
         ui.insert("FakeFlippers".to_string(), "blahhhh".to_string());
         assert_eq!(
         UserInputError { err: r#"Error parsing user input for FakeFlippers: Expected "true", "false", or a number."#.to_string() },
-        parse_weights(ui).unwrap_err()
+        parse_weights(ui, NMGRules.catalog()).unwrap_err()
         )
     }
 
@@ -424,10 +423,10 @@ mod test {
     fn test_parse_weights_clean() {
         let mut ui: HashMap<String, String> = Default::default();
         ui.insert("FakeFlippers".to_string(), "69%".to_string());
-        let (mut parsed, extras) = parse_weights(ui).unwrap();
+        let (mut parsed, extras) = parse_weights(ui, NMGRules.catalog()).unwrap();
         assert!(extras.is_none());
         assert_eq!(
-            TemplateState::CHANCE_PER_THOUSAND(69),
+            TemplateState::CHANCE(Chance::per_thousand(690)),
             parsed.remove("FakeFlippers").unwrap()
         );
     }
@@ -437,46 +436,38 @@ mod test {
         let mut ui: HashMap<String, String> = Default::default();
         ui.insert("FakeFlippers".to_string(), "69%".to_string());
         ui.insert("unused".to_string(), "who cares".to_string());
-        let (mut parsed, extras) = parse_weights(ui).unwrap();
+        let (mut parsed, extras) = parse_weights(ui, NMGRules.catalog()).unwrap();
         assert_eq!(vec!["unused".to_string()], extras.unwrap());
         assert_eq!(
-            TemplateState::CHANCE_PER_THOUSAND(69),
+            TemplateState::CHANCE(Chance::per_thousand(690)),
             parsed.remove("FakeFlippers").unwrap()
         );
     }
 
     #[test]
     fn test_munge() {
-        let mut iw = InputWeights {
+        let input = |weights: &[(&str, &str)]| InputWeights {
             name: "a_name".to_string(),
             defaults: "NMGRules".to_string(),
-            weights: Default::default()
+            weights: weights.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            groups: Default::default(),
         };
 
-        iw.weights.insert("FakeFlippers".to_string(), "true".to_string());
-        let munged = munge_user_input(iw).unwrap();
+        let (munged, diagnostics) = munge_user_input(input(&[("FakeFlippers", "true")]), false).unwrap();
         assert_eq!(
             "a_name".to_string(),
             munged.name,
         );
-        // meh
-    }
-
-    #[test]
-    fn test_user_inputs_from_yaml() {
-        let some_yaml = r#"
-name: hello
-defaults: NMGRules
-weights:
-    OverworldClipping: 40%
-    unused: unused
-"#.to_string();
-        let parsed = parse_user_input(some_yaml);
-        assert!(parsed.is_ok(), "Failed to parse yaml: {}", parsed.unwrap_err());
-        let p = parsed.unwrap();
-        assert_eq!("hello", p.name);
-        assert_eq!(TemplateState::CHANCE_PER_THOUSAND(40), *p.weights.get("OverworldClipping").unwrap());
+        assert!(diagnostics.is_empty());
 
+        let typo = [("FakeFlippers", "true"), ("OverworldClip", "40%")];
+        let (_, diagnostics) = munge_user_input(input(&typo), false).unwrap();
+        assert_eq!(
+            vec!["warning: weights.OverworldClip: unknown technique OverworldClip (did you mean `OverworldClipping`?)"],
+            diagnostics.iter().map(|d| d.to_string()).collect::<Vec<_>>()
+        );
+        let strict = munge_user_input(input(&typo), true).unwrap_err();
+        assert!(strict.err.starts_with("error: weights.OverworldClip: unknown technique OverworldClip"));
     }
 
     #[test]
@@ -484,54 +475,52 @@ weights:
         let mut weights: HashMap<String, TemplateState> = Default::default();
         weights.insert("OverworldClipping".to_string(), TemplateState::STATIC(IsAllowed::ALLOWED));
         let rt = RulesetTemplate::from_template_states(&weights);
-        assert_eq!(TemplateState::STATIC(IsAllowed::ALLOWED), rt.OverworldClipping);
-        assert_eq!(TemplateState::USE_DEFAULT, rt.FakeFlippers);
+        assert_eq!(TemplateState::STATIC(IsAllowed::ALLOWED), rt.get("OverworldClipping"));
+        assert_eq!(TemplateState::USE_DEFAULT, rt.get("FakeFlippers"));
     }
 
     // N.B. These will probably break when the order of techniques changes; this is mostly just
     // to prove to myself that seeding RNG works and that I can spell it right, etc.
     #[test]
     fn test_apply_rule_with_rng() {
-        let rt = RulesetTemplate {
-            FakeFlippers: TemplateState::CHANCE_PER_THOUSAND(50),
-            OverworldClipping: TemplateState::USE_DEFAULT,
-        };
+        let mut weights: HashMap<String, TemplateState> = Default::default();
+        weights.insert("FakeFlippers".to_string(), TemplateState::CHANCE(Chance::per_thousand(50)));
+        let rt = RulesetTemplate::from_template_states(&weights);
 
         let mut rng = SmallRng::seed_from_u64(1);
-        assert_eq!(IsAllowed::DISALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE_PER_THOUSAND(40), &mut rng));
+        assert_eq!(IsAllowed::DISALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE(Chance::per_thousand(40)), &mut rng));
 
 
         let mut rng2 = SmallRng::seed_from_u64(2);
-        assert_eq!(IsAllowed::DISALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE_PER_THOUSAND(40), &mut rng2));
+        assert_eq!(IsAllowed::DISALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE(Chance::per_thousand(40)), &mut rng2));
 
         let mut rng3 = SmallRng::seed_from_u64(3);
-        assert_eq!(IsAllowed::DISALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE_PER_THOUSAND(40), &mut rng3));
+        assert_eq!(IsAllowed::DISALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE(Chance::per_thousand(40)), &mut rng3));
 
 
         let mut rng4 = SmallRng::seed_from_u64(4);
-        assert_eq!(IsAllowed::DISALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE_PER_THOUSAND(40), &mut rng4));
+        assert_eq!(IsAllowed::DISALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE(Chance::per_thousand(40)), &mut rng4));
 
         let mut rng5 = SmallRng::seed_from_u64(500);
-        assert_eq!(IsAllowed::ALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE_PER_THOUSAND(40), &mut rng5));
+        assert_eq!(IsAllowed::ALLOWED, rt.apply_rule_with_rng(&IsAllowed::DISALLOWED, &TemplateState::CHANCE(Chance::per_thousand(40)), &mut rng5));
     }
 
 
     #[test]
     fn test_apply_with_rng() {
-        let rt = RulesetTemplate {
-            FakeFlippers: TemplateState::CHANCE_PER_THOUSAND(50),
-            OverworldClipping: TemplateState::USE_DEFAULT,
-        };
+        let mut weights: HashMap<String, TemplateState> = Default::default();
+        weights.insert("FakeFlippers".to_string(), TemplateState::CHANCE(Chance::per_thousand(50)));
+        let rt = RulesetTemplate::from_template_states(&weights);
 
-        let defaults = Ruleset {
-            FakeFlippers: IsAllowed::ALLOWED,
-            OverworldClipping: IsAllowed::DISALLOWED,
-        };
+        let mut defaults = NMGRules.clone();
+        defaults["FakeFlippers"] = IsAllowed::ALLOWED;
+        defaults["OverworldClipping"] = IsAllowed::DISALLOWED;
 
         let mut rng = SmallRng::seed_from_u64(1);
-        assert_eq!(IsAllowed::DISALLOWED, rt.apply_with_rng(&defaults, &mut rng).FakeFlippers);
-        let mut rng2 = SmallRng::seed_from_u64(500);
-        assert_eq!(IsAllowed::ALLOWED, rt.apply_with_rng(&defaults, &mut rng).FakeFlippers);
+        assert_eq!(IsAllowed::DISALLOWED, rt.apply_with_rng(&defaults, &mut rng)["FakeFlippers"]);
+        // one of the few seeds that rolls the 5% chance
+        let mut rng2 = SmallRng::seed_from_u64(9);
+        assert_eq!(IsAllowed::ALLOWED, rt.apply_with_rng(&defaults, &mut rng2)["FakeFlippers"]);
     }
 }
 
@@ -0,0 +1,234 @@
+//! Share codes: a whole ruleset as a short string that can be pasted anywhere and opened without
+//! the database. A code is base64url (no padding) of one byte of code format, one byte of catalog
+//! version, then two bits per technique in catalog order. Format 1 is for the default game, as
+//! codes were before there were several games; format 2 puts the game's slug (a length byte, then
+//! the slug) between the catalog version and the techniques. Randomizable supplemental rules aren't
+//! covered by the catalog version, since organizers change them from the admin pages: when a game
+//! has any, they follow the techniques as a byte with how many there are, two bytes of a checksum
+//! of their names, then two bits each. A code without them still decodes, with each rule at its
+//! ruling; one made with a different set of them doesn't.
+
+use crate::games::{default_game, find_game, Game};
+use crate::rules::{IsAllowed, UserInputError};
+use crate::techniques::{Catalog, Ruleset};
+use sha2::{Digest, Sha256};
+
+const DEFAULT_GAME_FORMAT: u8 = 1;
+const GAME_FORMAT: u8 = 2;
+
+fn to_bits(a: IsAllowed) -> u8 {
+    match a {
+        IsAllowed::UNSPECIFIED => 0,
+        IsAllowed::ALLOWED => 1,
+        IsAllowed::DISALLOWED => 2,
+    }
+}
+
+fn from_bits(b: u8) -> Option<IsAllowed> {
+    match b {
+        0 => Some(IsAllowed::UNSPECIFIED),
+        1 => Some(IsAllowed::ALLOWED),
+        2 => Some(IsAllowed::DISALLOWED),
+        _ => None,
+    }
+}
+
+/// Bytes needed for `techniques` techniques.
+fn packed_len(techniques: usize) -> usize {
+    (techniques * 2).div_ceil(8)
+}
+
+fn pack(values: &[IsAllowed]) -> Vec<u8> {
+    let mut bytes = vec![0; packed_len(values.len())];
+    for (i, allowed) in values.iter().enumerate() {
+        bytes[i / 4] |= to_bits(*allowed) << (6 - 2 * (i % 4));
+    }
+    bytes
+}
+
+fn unpack(techniques: &[&str], bits: &[u8]) -> Result<Vec<IsAllowed>, UserInputError> {
+    techniques
+        .iter()
+        .enumerate()
+        .map(|(i, technique)| {
+            from_bits((bits[i / 4] >> (6 - 2 * (i % 4))) & 0b11).ok_or_else(|| UserInputError {
+                err: format!("Share code has a bad value for {}", technique),
+            })
+        })
+        .collect()
+}
+
+/// Identifies `catalog`'s randomizable supplemental rules and their order.
+fn supplemental_checksum(catalog: &Catalog) -> [u8; 2] {
+    let digest = Sha256::digest(catalog.supplemental().join("\n").as_bytes());
+    [digest[0], digest[1]]
+}
+
+/// The techniques of `catalog` with `values`, packed as in a share code after its header.
+fn pack_techniques(catalog: &Catalog, values: &[IsAllowed]) -> Vec<u8> {
+    let (techniques, supplemental) = values.split_at(catalog.techniques.len() - catalog.supplemental().len());
+    let mut bytes = pack(techniques);
+    if !supplemental.is_empty() {
+        bytes.push(supplemental.len() as u8);
+        bytes.extend(supplemental_checksum(catalog));
+        bytes.extend(pack(supplemental));
+    }
+    bytes
+}
+
+/// The values of the techniques of `catalog` packed in `bits`, in catalog order. Supplemental rules
+/// are left out when the code doesn't have them.
+fn unpack_techniques(catalog: &Catalog, bits: &[u8]) -> Result<Vec<IsAllowed>, UserInputError> {
+    let wrong_length = || UserInputError {
+        err: "Share code has the wrong length".to_string(),
+    };
+    let (techniques, supplemental) =
+        catalog.techniques.split_at(catalog.techniques.len() - catalog.supplemental().len());
+    let (technique_bits, rest) = bits.split_at_checked(packed_len(techniques.len())).ok_or_else(wrong_length)?;
+    let mut values = unpack(techniques, technique_bits)?;
+    if !rest.is_empty() {
+        let (header, supplemental_bits) = rest.split_at_checked(3).ok_or_else(wrong_length)?;
+        if header[0] as usize != supplemental.len() || header[1..] != supplemental_checksum(catalog) {
+            return Err(UserInputError {
+                err: "Share code was made with different randomizable supplemental rules than this site has now"
+                    .to_string(),
+            });
+        }
+        if supplemental_bits.len() != packed_len(supplemental.len()) {
+            return Err(wrong_length());
+        }
+        values.extend(unpack(supplemental, supplemental_bits)?);
+    }
+    Ok(values)
+}
+
+pub(crate) fn encode_ruleset(ruleset: &Ruleset) -> String {
+    let game = Game::of(ruleset);
+    let mut bytes = if game.is_default() {
+        vec![DEFAULT_GAME_FORMAT, game.catalog_version]
+    } else {
+        let mut b = vec![GAME_FORMAT, game.catalog_version, game.slug.len() as u8];
+        b.extend(game.slug.as_bytes());
+        b
+    };
+    let values: Vec<IsAllowed> = ruleset.techniques().map(|(_, allowed)| allowed).collect();
+    bytes.extend(pack_techniques(game.catalog, &values));
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decode a share code into a ruleset named `name`.
+pub(crate) fn decode_ruleset(code: &str, name: String) -> Result<Ruleset, UserInputError> {
+    let err = |err: String| UserInputError { err };
+    let not_a_code = || err(format!("{} is not a share code", code));
+    let bytes = base64::decode_config(code.trim(), base64::URL_SAFE_NO_PAD).map_err(|_| not_a_code())?;
+    let (game, version, bits) = match bytes.as_slice() {
+        [DEFAULT_GAME_FORMAT, version, bits @ ..] => (default_game(), *version, bits),
+        [GAME_FORMAT, version, len, rest @ ..] if rest.len() >= *len as usize => {
+            let (slug, bits) = rest.split_at(*len as usize);
+            let slug = String::from_utf8_lossy(slug);
+            let game = find_game(&slug).ok_or_else(|| err(format!("Share code is for {}, which this site doesn't host", slug)))?;
+            (game, *version, bits)
+        }
+        _ => return Err(not_a_code()),
+    };
+    if version != game.catalog_version {
+        return Err(err(format!(
+            "Share code is for technique catalog version {}, but this site uses version {}",
+            version, game.catalog_version
+        )));
+    }
+    let mut ruleset = Ruleset::unspecified(game.slug, name);
+    for (technique, allowed) in game.catalog.techniques.iter().zip(unpack_techniques(game.catalog, bits)?) {
+        ruleset.set(technique, allowed);
+    }
+    Ok(ruleset)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_ruleset, encode_ruleset, pack_techniques, unpack_techniques};
+    use crate::games::{default_game, find_game, find_preset};
+    use crate::rules::{IsAllowed, NMGRules};
+    use crate::techniques::parse_catalog;
+
+    #[test]
+    fn test_round_trip() {
+        let mut ruleset = NMGRules.clone();
+        ruleset["Hover"] = IsAllowed::UNSPECIFIED;
+        ruleset["LayerDisparity"] = IsAllowed::ALLOWED;
+        let code = encode_ruleset(&ruleset);
+        assert_eq!(12, code.len());
+        assert_eq!(ruleset, decode_ruleset(&code, ruleset.name.clone()).unwrap());
+
+        // other games' codes carry the game
+        let mut ruleset = find_preset("SMAnyPercent").unwrap().clone();
+        ruleset.set("GTCode", IsAllowed::UNSPECIFIED);
+        let code = encode_ruleset(&ruleset);
+        assert_eq!(ruleset, decode_ruleset(&code, ruleset.name.clone()).unwrap());
+    }
+
+    #[test]
+    fn test_bad_codes() {
+        assert!(decode_ruleset("not a code!", String::new()).is_err());
+        // a different catalog version
+        let mut code = base64::decode_config(encode_ruleset(&NMGRules), base64::URL_SAFE_NO_PAD).unwrap();
+        code[1] += 1;
+        let err = decode_ruleset(&base64::encode_config(&code, base64::URL_SAFE_NO_PAD), String::new()).unwrap_err();
+        assert!(err.to_string().contains("catalog version"));
+        // 0b11 isn't a status
+        code[1] -= 1;
+        code[2] = 0xff;
+        assert!(decode_ruleset(&base64::encode_config(&code, base64::URL_SAFE_NO_PAD), String::new()).is_err());
+    }
+
+    #[test]
+    fn test_supplemental() {
+        use IsAllowed::*;
+        let catalog = |rules: &[(&'static str, IsAllowed)]| {
+            parse_catalog("test", "[Movement]\nHover\nSuperSpeed\nClip\nFakeFlippers\n")
+                .unwrap()
+                .add_supplemental(rules)
+                .unwrap()
+        };
+        let bunny = catalog(&[("Super Bunny", DISALLOWED)]);
+        let values = [ALLOWED, DISALLOWED, UNSPECIFIED, ALLOWED];
+
+        // made before the rule was randomizable, though the rule would fit in the same bytes
+        let old = pack_techniques(&catalog(&[]), &values);
+        assert_eq!(values.to_vec(), unpack_techniques(&bunny, &old).unwrap());
+
+        let with_bunny = [&values[..], &[ALLOWED]].concat();
+        let code = pack_techniques(&bunny, &with_bunny);
+        assert_eq!(with_bunny, unpack_techniques(&bunny, &code).unwrap());
+        assert!(unpack_techniques(&bunny, &code[..code.len() - 1]).is_err());
+        assert!(unpack_techniques(&catalog(&[]), &code).is_err());
+        assert!(unpack_techniques(&catalog(&[("Water Walk", DISALLOWED)]), &code).is_err());
+
+        // the same rules in another order
+        let both = [("Super Bunny", ALLOWED), ("Water Walk", ALLOWED)];
+        let code = pack_techniques(&catalog(&both), &[&values[..], &[ALLOWED, DISALLOWED]].concat());
+        assert!(unpack_techniques(&catalog(&[both[1], both[0]]), &code).is_err());
+    }
+
+    #[test]
+    fn test_catalog_version_is_current() {
+        // if this fails, the catalog changed: bump catalog_version in games/alttp/game.yaml and update
+        // the list here
+        assert_eq!(1, default_game().catalog_version);
+        assert_eq!(
+            "SaveAndQuit,BombJump,SilverlessGanon,SuperSpeed,FakeFlippers,ItemDash,AncillaOverload,Hover,\
+             HammerJump,DoorStateExtension,DiverDown,OverworldBunnyRevival,HeraPot,HookShopping,OverworldEG,\
+             OverworldClipping,OverworldMirrorGlitches,OverworldYBA,OverworldSwimmyG,UnderworldClipping,\
+             UnderworldYBA,UnderworldDeathHole,SomariaTransitionCorruption,DoorJukes,Misslotting,LayerDisparity",
+            default_game().catalog.techniques.join(",")
+        );
+        let sm = find_game("sm").unwrap();
+        assert_eq!(1, sm.catalog_version);
+        assert_eq!(
+            "ShortCharge,Mockball,SpringBallJump,GravityJump,InfiniteBombJump,DiagonalBombJump,BlueSuit,\
+             SpikeSuit,XRayClimb,MochtroidClip,DoorIceClip,CrystalFlashClip,ReserveTrick,Moondance,\
+             GreenGateGlitch,GTCode,Spacetime,OutOfBounds",
+            sm.catalog.techniques.join(",")
+        );
+    }
+}
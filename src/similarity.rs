@@ -0,0 +1,155 @@
+//! Which past rulesets and presets a ruleset is closest to, so runners can reuse routes. Distance
+//! is the number of techniques whose status differs (Hamming), or with `Metric::Weighted`, each
+//! difference counts for less the more often that technique changes from one week to the next.
+
+use crate::rules::{diff_rulesets, IsAllowed, RuleChange};
+use crate::techniques::Ruleset;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Metric {
+    Hamming,
+    Weighted,
+}
+
+impl std::str::FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hamming" => Ok(Metric::Hamming),
+            "weighted" => Ok(Metric::Weighted),
+            _ => Err(format!("Expected hamming or weighted, got {}", s)),
+        }
+    }
+}
+
+/// Something to compare against: a saved ruleset or a preset.
+pub(crate) struct Candidate {
+    pub(crate) kind: &'static str,
+    pub(crate) id: Option<u32>,
+    pub(crate) name: String,
+    pub(crate) url: Option<String>,
+    pub(crate) ruleset: Ruleset,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct Similar {
+    pub(crate) kind: &'static str,
+    pub(crate) id: Option<u32>,
+    pub(crate) name: String,
+    pub(crate) url: Option<String>,
+    pub(crate) distance: f64,
+    /// `before` is the ruleset compared, `after` this one.
+    pub(crate) differences: Vec<RuleChange>,
+}
+
+/// How much a difference on each technique counts with `Metric::Weighted`: one minus the chance
+/// two of `history` picked at random differ on it, so a technique that's always the same counts
+/// fully and a coin flip counts half.
+pub(crate) fn technique_weights<'a>(history: impl Iterator<Item = &'a Ruleset>) -> HashMap<&'static str, f64> {
+    let mut counts: HashMap<&'static str, (usize, usize)> = HashMap::new();
+    for r in history {
+        for (t, allowed) in r.techniques() {
+            let (allowed_count, total) = counts.entry(t).or_default();
+            *total += 1;
+            if allowed == IsAllowed::ALLOWED {
+                *allowed_count += 1;
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(t, (allowed, total))| {
+            let p = allowed as f64 / total as f64;
+            (t, 1.0 - 2.0 * p * (1.0 - p))
+        })
+        .collect()
+}
+
+/// The `limit` candidates closest to `ruleset`, closest first; ties keep the candidates' order.
+/// Weighted distances are weighed by the saved rulesets among `candidates`.
+pub(crate) fn rank(ruleset: &Ruleset, candidates: Vec<Candidate>, metric: Metric, limit: usize) -> Vec<Similar> {
+    let weights = match metric {
+        Metric::Hamming => HashMap::new(),
+        Metric::Weighted => technique_weights(candidates.iter().filter(|c| c.id.is_some()).map(|c| &c.ruleset)),
+    };
+    let mut similar: Vec<Similar> = candidates
+        .into_iter()
+        .filter(|c| c.ruleset.game == ruleset.game)
+        .map(|c| {
+            let differences = diff_rulesets(ruleset, &c.ruleset);
+            let distance = differences
+                .iter()
+                .map(|d| weights.get(d.technique.as_str()).copied().unwrap_or(1.0))
+                .fold(0.0, |total, w| total + w);
+            Similar {
+                kind: c.kind,
+                id: c.id,
+                name: c.name,
+                url: c.url,
+                distance,
+                differences,
+            }
+        })
+        .collect();
+    similar.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    similar.truncate(limit);
+    similar
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rank, technique_weights, Candidate, Metric};
+    use crate::rules::IsAllowed;
+    use crate::techniques::{default_catalog, Ruleset};
+
+    fn ruleset(allowed: &[&str]) -> Ruleset {
+        let mut r = Ruleset::unspecified(default_catalog().game, "test".to_string());
+        for t in default_catalog().techniques {
+            r.set(t, IsAllowed::DISALLOWED);
+        }
+        for t in allowed {
+            r.set(t, IsAllowed::ALLOWED);
+        }
+        r
+    }
+
+    fn candidate(id: u32, allowed: &[&str]) -> Candidate {
+        Candidate {
+            kind: "ruleset",
+            id: Some(id),
+            name: id.to_string(),
+            url: None,
+            ruleset: ruleset(allowed),
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let target = ruleset(&["OverworldClipping", "HeraPot"]);
+        let candidates = || {
+            vec![
+                candidate(1, &["SaveAndQuit"]),
+                candidate(2, &["OverworldClipping"]),
+                candidate(3, &["OverworldClipping", "SaveAndQuit"]),
+                candidate(4, &["HeraPot"]),
+            ]
+        };
+        let ranked = rank(&target, candidates(), Metric::Hamming, 3);
+        assert_eq!(vec![Some(2), Some(4), Some(3)], ranked.iter().map(|s| s.id).collect::<Vec<_>>());
+        assert_eq!(1.0, ranked[0].distance);
+        assert_eq!("HeraPot", ranked[0].differences[0].technique);
+        assert_eq!(IsAllowed::DISALLOWED, ranked[0].differences[0].after);
+
+        // OverworldClipping and SaveAndQuit change half the time, HeraPot less often
+        let weights = technique_weights(candidates().iter().map(|c| &c.ruleset));
+        assert_eq!(0.5, weights["OverworldClipping"]);
+        assert_eq!(1.0, weights["Hover"]);
+        let ranked = rank(&target, candidates(), Metric::Weighted, 4);
+        assert_eq!(Some(4), ranked[0].id);
+        assert_eq!(0.5, ranked[0].distance);
+        assert_eq!(Some(1), ranked[3].id);
+    }
+}
@@ -0,0 +1,241 @@
+//! Season standings. Everything here is a pure function of the verified submissions, so standings
+//! can be recomputed from scratch at any time and always come out the same.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// How a single weekly result turns into points.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PointsFormula {
+    /// Points for 1st, 2nd, 3rd... Places past the end of the list get nothing.
+    pub(crate) placement: Vec<u32>,
+    /// Points for matching the winner's time; slower runs get `time_points * winner / time`.
+    pub(crate) time_points: u32,
+    /// Flat points for finishing at all.
+    pub(crate) participation: u32,
+}
+
+impl PointsFormula {
+    /// Parse a comma separated placement table like `25, 18, 15`.
+    pub(crate) fn parse_placement(input: &str) -> Result<Vec<u32>, String> {
+        input
+            .split(',')
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                p.parse::<u32>()
+                    .map_err(|_| format!("Expected a comma separated list of numbers, got {}", input))
+            })
+            .collect()
+    }
+
+    fn points(&self, place: usize, time: u32, winner: u32) -> (u32, u32, u32) {
+        let placement = self.placement.get(place - 1).copied().unwrap_or(0);
+        // integer maths with rounding, so results don't depend on float formatting
+        let time_points = ((self.time_points as u64 * winner as u64 + time as u64 / 2) / time as u64) as u32;
+        (placement, time_points, self.participation)
+    }
+}
+
+/// One verified finish in one week.
+#[derive(Debug, Clone)]
+pub(crate) struct Finish {
+    pub(crate) player: String,
+    pub(crate) time_seconds: u32,
+}
+
+/// All the finishes for a single week of the season.
+#[derive(Debug, Clone)]
+pub(crate) struct WeekResults {
+    pub(crate) ruleset_id: u32,
+    pub(crate) name: String,
+    pub(crate) finishes: Vec<Finish>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct WeekBreakdown {
+    pub(crate) ruleset_id: u32,
+    pub(crate) name: String,
+    pub(crate) place: usize,
+    pub(crate) time_seconds: u32,
+    pub(crate) placement_points: u32,
+    pub(crate) time_points: u32,
+    pub(crate) participation_points: u32,
+    pub(crate) total: u32,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct StandingsRow {
+    pub(crate) place: usize,
+    pub(crate) player: String,
+    pub(crate) total: u32,
+    pub(crate) weeks: Vec<WeekBreakdown>,
+}
+
+/// Players are matched case-insensitively, since names are typed in by hand.
+fn player_key(player: &str) -> String {
+    player.trim().to_lowercase()
+}
+
+/// Rank one week: each player's best time counts, ties share a place.
+fn rank_week(week: &WeekResults) -> Vec<(usize, &Finish)> {
+    let mut best: BTreeMap<String, &Finish> = BTreeMap::new();
+    for f in &week.finishes {
+        let entry = best.entry(player_key(&f.player)).or_insert(f);
+        if f.time_seconds < entry.time_seconds {
+            *entry = f;
+        }
+    }
+    let mut finishes: Vec<(String, &Finish)> = best.into_iter().collect();
+    finishes.sort_by(|(ka, a), (kb, b)| a.time_seconds.cmp(&b.time_seconds).then_with(|| ka.cmp(kb)));
+
+    let mut ranked: Vec<(usize, &Finish)> = Vec::with_capacity(finishes.len());
+    for (i, (_, f)) in finishes.into_iter().enumerate() {
+        let place = match ranked.last() {
+            Some((p, prev)) if prev.time_seconds == f.time_seconds => *p,
+            _ => i + 1,
+        };
+        ranked.push((place, f));
+    }
+    ranked
+}
+
+/// Total up a season. Rows are ordered by points, then by number of weeks played, then by name;
+/// players tied on points and weeks share a place.
+pub(crate) fn compute_standings(formula: &PointsFormula, weeks: &[WeekResults]) -> Vec<StandingsRow> {
+    // key -> (display name, breakdowns)
+    let mut players: BTreeMap<String, (String, Vec<WeekBreakdown>)> = BTreeMap::new();
+    for week in weeks {
+        let ranked = rank_week(week);
+        let winner = match ranked.first() {
+            Some((_, f)) => f.time_seconds,
+            None => continue,
+        };
+        for (place, f) in ranked {
+            let (placement_points, time_points, participation_points) = formula.points(place, f.time_seconds, winner);
+            let (_, breakdowns) = players
+                .entry(player_key(&f.player))
+                .or_insert_with(|| (f.player.trim().to_string(), vec![]));
+            breakdowns.push(WeekBreakdown {
+                ruleset_id: week.ruleset_id,
+                name: week.name.clone(),
+                place,
+                time_seconds: f.time_seconds,
+                placement_points,
+                time_points,
+                participation_points,
+                total: placement_points.saturating_add(time_points).saturating_add(participation_points),
+            });
+        }
+    }
+
+    let mut rows: Vec<StandingsRow> = players
+        .into_iter()
+        .map(|(_, (player, weeks))| StandingsRow {
+            place: 0,
+            total: weeks.iter().fold(0, |total, w| total.saturating_add(w.total)),
+            player,
+            weeks,
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.total
+            .cmp(&a.total)
+            .then_with(|| b.weeks.len().cmp(&a.weeks.len()))
+            .then_with(|| player_key(&a.player).cmp(&player_key(&b.player)))
+    });
+    for i in 0..rows.len() {
+        rows[i].place = if i > 0 && rows[i - 1].total == rows[i].total && rows[i - 1].weeks.len() == rows[i].weeks.len() {
+            rows[i - 1].place
+        } else {
+            i + 1
+        };
+    }
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compute_standings, Finish, PointsFormula, WeekResults};
+
+    fn week(id: u32, finishes: &[(&str, u32)]) -> WeekResults {
+        WeekResults {
+            ruleset_id: id,
+            name: format!("week {}", id),
+            finishes: finishes
+                .iter()
+                .map(|(p, t)| Finish {
+                    player: p.to_string(),
+                    time_seconds: *t,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_placement() {
+        assert_eq!(Ok(vec![25, 18, 15]), PointsFormula::parse_placement("25, 18,15,"));
+        assert_eq!(Ok(vec![]), PointsFormula::parse_placement(""));
+        assert!(PointsFormula::parse_placement("25, lots").is_err());
+    }
+
+    #[test]
+    fn test_placement_and_participation() {
+        let formula = PointsFormula {
+            placement: vec![10, 5],
+            time_points: 0,
+            participation: 1,
+        };
+        let weeks = vec![
+            week(1, &[("fox", 100), ("bob", 200), ("carl", 300)]),
+            week(2, &[("Bob", 100), ("fox", 200)]),
+        ];
+        let standings = compute_standings(&formula, &weeks);
+        let summary: Vec<(usize, &str, u32)> = standings
+            .iter()
+            .map(|r| (r.place, r.player.as_str(), r.total))
+            .collect();
+        assert_eq!(vec![(1, "bob", 17), (1, "fox", 17), (3, "carl", 1)], summary);
+        assert_eq!(2, standings[0].weeks.len());
+    }
+
+    #[test]
+    fn test_time_relative_to_winner() {
+        let formula = PointsFormula {
+            placement: vec![],
+            time_points: 100,
+            participation: 0,
+        };
+        let weeks = vec![week(1, &[("fox", 3000), ("bob", 4000), ("fox", 3600)])];
+        let standings = compute_standings(&formula, &weeks);
+        // fox's slower run doesn't count
+        assert_eq!(1, standings[0].weeks.len());
+        assert_eq!(100, standings[0].total);
+        assert_eq!(75, standings[1].total);
+    }
+
+    #[test]
+    fn test_ties_share_a_place() {
+        let formula = PointsFormula {
+            placement: vec![10, 5, 1],
+            time_points: 0,
+            participation: 0,
+        };
+        let standings = compute_standings(&formula, &[week(1, &[("a", 100), ("b", 100), ("c", 150)])]);
+        let places: Vec<usize> = standings.iter().flat_map(|r| r.weeks.iter().map(|w| w.place)).collect();
+        assert_eq!(vec![1, 1, 3], places);
+        assert_eq!(vec![10, 10, 1], standings.iter().map(|r| r.total).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_totals_saturate() {
+        let formula = PointsFormula {
+            placement: vec![4_000_000_000],
+            time_points: 4_000_000_000,
+            participation: 1,
+        };
+        let standings = compute_standings(&formula, &[week(1, &[("fox", 100)]), week(2, &[("fox", 100)])]);
+        assert_eq!(u32::MAX, standings[0].weeks[0].total);
+        assert_eq!(u32::MAX, standings[0].total);
+    }
+}
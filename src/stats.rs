@@ -0,0 +1,238 @@
+//! Stats over a track's saved rulesets: how often each technique was allowed against how often
+//! it's configured to be, streaks, the weeks most and least like the game's baseline, and how far
+//! weeks fall from each preset. Computed from the rulesets as saved (overrides included), so they
+//! reflect what was actually run rather than what the current template would roll.
+
+use crate::rules::{diff_rulesets, IsAllowed};
+use crate::techniques::{Catalog, Ruleset};
+use serde::Serialize;
+use std::cmp::Reverse;
+
+/// Days from one period to the next.
+const PERIOD_DAYS: i32 = 7;
+
+/// One saved ruleset.
+pub(crate) struct Week {
+    pub(crate) id: u32,
+    /// The day its period starts, from CE.
+    pub(crate) day: i32,
+    pub(crate) name: String,
+    pub(crate) ruleset: Ruleset,
+}
+
+/// A run of weeks from consecutive periods, by the ids and names of its first and last.
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct Streak {
+    pub(crate) weeks: usize,
+    pub(crate) first_id: u32,
+    pub(crate) first_name: String,
+    pub(crate) last_id: u32,
+    pub(crate) last_name: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct TechniqueHistory {
+    pub(crate) technique: String,
+    /// Weeks that ruled on it (`ALLOWED` or `DISALLOWED`), and how many allowed it.
+    pub(crate) weeks: usize,
+    pub(crate) allowed: usize,
+    pub(crate) observed_rate: Option<f64>,
+    /// The chance the track's template gives it now.
+    pub(crate) configured_rate: Option<f64>,
+    pub(crate) longest_allowed: Option<Streak>,
+    pub(crate) longest_banned: Option<Streak>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub(crate) struct WeekDistance {
+    pub(crate) id: u32,
+    pub(crate) name: String,
+    pub(crate) differences: usize,
+}
+
+/// How many weeks differ from a preset on each number of techniques, from 0 up.
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct PresetHistogram {
+    pub(crate) preset: String,
+    pub(crate) counts: Vec<usize>,
+    pub(crate) mean: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct HistoryStats {
+    pub(crate) weeks: usize,
+    pub(crate) techniques: Vec<TechniqueHistory>,
+    pub(crate) baseline: String,
+    pub(crate) most_like_baseline: Vec<WeekDistance>,
+    pub(crate) least_like_baseline: Vec<WeekDistance>,
+    pub(crate) presets: Vec<PresetHistogram>,
+}
+
+/// The longest run of `weeks` with `technique` at `value`; the latest of equal ones. A period with
+/// no saved ruleset (never rolled, or superseded) ends a run.
+fn longest_streak(weeks: &[Week], technique: &str, value: IsAllowed) -> Option<Streak> {
+    let mut longest: Option<(usize, usize)> = None;
+    let mut start = None;
+    for (i, week) in weeks.iter().enumerate() {
+        if i > 0 && week.day - weeks[i - 1].day > PERIOD_DAYS {
+            start = None;
+        }
+        if week.ruleset.get(technique) != Some(value) {
+            start = None;
+            continue;
+        }
+        let first = *start.get_or_insert(i);
+        if longest.is_none_or(|(f, l)| i + 1 - first >= l + 1 - f) {
+            longest = Some((first, i));
+        }
+    }
+    longest.map(|(first, last)| Streak {
+        weeks: last + 1 - first,
+        first_id: weeks[first].id,
+        first_name: weeks[first].name.clone(),
+        last_id: weeks[last].id,
+        last_name: weeks[last].name.clone(),
+    })
+}
+
+/// Stats for `weeks`, oldest first, with `configured` the chance each technique is allowed and
+/// `presets` as (name, ruleset), the first being the baseline. The `extremes` weeks most and least
+/// like the baseline are listed, the latest first among equals.
+pub(crate) fn history_stats(
+    catalog: &Catalog,
+    weeks: &[Week],
+    configured: &[(&str, f64)],
+    presets: &[(&str, &Ruleset)],
+    extremes: usize,
+) -> HistoryStats {
+    let techniques = catalog
+        .techniques
+        .iter()
+        .map(|t| {
+            let ruled: Vec<IsAllowed> = weeks
+                .iter()
+                .filter_map(|w| w.ruleset.get(t))
+                .filter(|a| *a != IsAllowed::UNSPECIFIED)
+                .collect();
+            let allowed = ruled.iter().filter(|a| **a == IsAllowed::ALLOWED).count();
+            TechniqueHistory {
+                technique: t.to_string(),
+                weeks: ruled.len(),
+                allowed,
+                observed_rate: if ruled.is_empty() { None } else { Some(allowed as f64 / ruled.len() as f64) },
+                configured_rate: configured.iter().find(|(c, _)| c == t).map(|(_, rate)| *rate),
+                longest_allowed: longest_streak(weeks, t, IsAllowed::ALLOWED),
+                longest_banned: longest_streak(weeks, t, IsAllowed::DISALLOWED),
+            }
+        })
+        .collect();
+
+    let histograms = presets
+        .iter()
+        .map(|(name, preset)| {
+            let mut counts = vec![];
+            for w in weeks {
+                let d = diff_rulesets(preset, &w.ruleset).len();
+                if counts.len() <= d {
+                    counts.resize(d + 1, 0);
+                }
+                counts[d] += 1;
+            }
+            let total: usize = counts.iter().enumerate().map(|(d, n)| d * n).sum();
+            PresetHistogram {
+                preset: name.to_string(),
+                counts,
+                mean: if weeks.is_empty() { None } else { Some(total as f64 / weeks.len() as f64) },
+            }
+        })
+        .collect();
+
+    // newest first, so the stable sorts keep the latest of equal distances first
+    let mut most_like_baseline: Vec<WeekDistance> = match presets.first() {
+        Some((_, baseline)) => weeks
+            .iter()
+            .rev()
+            .map(|w| WeekDistance {
+                id: w.id,
+                name: w.name.clone(),
+                differences: diff_rulesets(baseline, &w.ruleset).len(),
+            })
+            .collect(),
+        None => vec![],
+    };
+    let mut least_like_baseline = most_like_baseline.clone();
+    most_like_baseline.sort_by_key(|d| d.differences);
+    most_like_baseline.truncate(extremes);
+    least_like_baseline.sort_by_key(|d| Reverse(d.differences));
+    least_like_baseline.truncate(extremes);
+
+    HistoryStats {
+        weeks: weeks.len(),
+        techniques,
+        baseline: presets.first().map(|(name, _)| name.to_string()).unwrap_or_default(),
+        most_like_baseline,
+        least_like_baseline,
+        presets: histograms,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{history_stats, Week};
+    use crate::rules::IsAllowed;
+    use crate::techniques::{default_catalog, Ruleset};
+
+    fn week(id: u32, allowed: &[&str]) -> Week {
+        let mut ruleset = Ruleset::unspecified(default_catalog().game, id.to_string());
+        for t in default_catalog().techniques {
+            ruleset.set(t, IsAllowed::DISALLOWED);
+        }
+        for t in allowed {
+            ruleset.set(t, IsAllowed::ALLOWED);
+        }
+        Week {
+            id,
+            day: id as i32 * 7,
+            name: format!("Week {}", id),
+            ruleset,
+        }
+    }
+
+    #[test]
+    fn test_history_stats() {
+        let weeks = vec![
+            week(1, &["HeraPot"]),
+            week(2, &[]),
+            week(3, &[]),
+            week(4, &["HeraPot", "SaveAndQuit"]),
+            week(5, &[]),
+        ];
+        let baseline = week(0, &["SaveAndQuit"]).ruleset;
+        let stats = history_stats(default_catalog(), &weeks, &[("HeraPot", 0.2)], &[("Base", &baseline)], 2);
+        assert_eq!(5, stats.weeks);
+
+        let hera = stats.techniques.iter().find(|t| t.technique == "HeraPot").unwrap();
+        assert_eq!((5, 2, Some(0.4), Some(0.2)), (hera.weeks, hera.allowed, hera.observed_rate, hera.configured_rate));
+        let banned = hera.longest_banned.as_ref().unwrap();
+        assert_eq!((2, 2, 3), (banned.weeks, banned.first_id, banned.last_id));
+        assert_eq!(4, hera.longest_allowed.as_ref().unwrap().first_id);
+        let hover = stats.techniques.iter().find(|t| t.technique == "Hover").unwrap();
+        assert_eq!((None, 5), (hover.configured_rate, hover.longest_banned.as_ref().unwrap().weeks));
+
+        // week 1 is two off the baseline, the rest one off
+        assert_eq!(vec![5, 4], stats.most_like_baseline.iter().map(|w| w.id).collect::<Vec<_>>());
+        assert_eq!(vec![1, 5], stats.least_like_baseline.iter().map(|w| w.id).collect::<Vec<_>>());
+        assert_eq!(vec![0, 4, 1], stats.presets[0].counts);
+        assert_eq!(Some(1.2), stats.presets[0].mean);
+    }
+
+    #[test]
+    fn test_streaks_stop_at_gaps() {
+        // no ruleset was saved for weeks 3 and 4
+        let weeks = vec![week(1, &[]), week(2, &[]), week(5, &[]), week(6, &[]), week(7, &[])];
+        let stats = history_stats(default_catalog(), &weeks, &[], &[], 0);
+        let hera = stats.techniques.iter().find(|t| t.technique == "HeraPot").unwrap();
+        let banned = hera.longest_banned.as_ref().unwrap();
+        assert_eq!((3, 5, 7), (banned.weeks, banned.first_id, banned.last_id));
+    }
+}
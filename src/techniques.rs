@@ -0,0 +1,429 @@
+//! Technique catalogs and the rulesets built on them. Each game's catalog is read from its
+//! `techniques` file at startup (games are listed in `games/games`, the default first), so adding a
+//! technique only needs the file changed and the server restarted. Supplemental rules organizers
+//! have made randomizable are added after the file's techniques, as a `[Supplemental]` group (see
+//! `crate::web::supplemental`). A ruleset keeps one value per technique of its game, in catalog
+//! order.
+
+use crate::games::games_path;
+use crate::rules::{IsAllowed, TemplateState, UserInputError};
+use rand::Rng;
+use serde::de::Error;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The group randomizable supplemental rules are added to the catalog as.
+pub(crate) const SUPPLEMENTAL_GROUP: &str = "Supplemental";
+
+/// One game's techniques, from `games/<game>/techniques`.
+#[derive(Debug)]
+pub(crate) struct Catalog {
+    pub(crate) game: &'static str,
+    pub(crate) techniques: &'static [&'static str],
+    /// The techniques again, in the sections they're listed in in the catalog.
+    pub(crate) groups: &'static [&'static [&'static str]],
+    /// The name each of `groups` is given in the catalog.
+    pub(crate) group_names: &'static [&'static str],
+    /// Each technique's position in `techniques`.
+    ids: HashMap<&'static str, usize>,
+    /// What a new ruleset has for each technique: `UNSPECIFIED`, except for supplemental rules,
+    /// which start at their ruling.
+    initial: Vec<IsAllowed>,
+}
+
+impl Catalog {
+    /// The techniques in the group called `name`.
+    pub(crate) fn group(&self, name: &str) -> Option<&'static [&'static str]> {
+        self.group_names.iter().position(|n| *n == name).map(|i| self.groups[i])
+    }
+
+    /// The position of `technique` in this catalog, which is where rulesets keep its value.
+    pub(crate) fn id(&self, technique: &str) -> Option<usize> {
+        self.ids.get(technique).copied()
+    }
+
+    /// The supplemental rules randomized like techniques, at the end of `techniques`.
+    pub(crate) fn supplemental(&self) -> &'static [&'static str] {
+        self.group(SUPPLEMENTAL_GROUP).unwrap_or_default()
+    }
+
+    /// Add randomizable supplemental rules, as (name, ruling), in a last `[Supplemental]` group.
+    pub(crate) fn add_supplemental(mut self, rules: &[(&'static str, IsAllowed)]) -> Result<Catalog, UserInputError> {
+        if rules.is_empty() {
+            return Ok(self);
+        }
+        // share codes count them in a byte
+        if rules.len() > u8::MAX as usize {
+            return Err(UserInputError {
+                err: format!("{} can have at most {} randomizable supplemental rules", self.game, u8::MAX),
+            });
+        }
+        if self.group(SUPPLEMENTAL_GROUP).is_some() {
+            return Err(UserInputError {
+                err: format!("games/{}/techniques can't have a [{}] group", self.game, SUPPLEMENTAL_GROUP),
+            });
+        }
+        let mut techniques = self.techniques.to_vec();
+        for (name, allowed) in rules {
+            if let Some(err) = technique_name_error(name, &self.ids) {
+                return Err(UserInputError {
+                    err: format!("Supplemental rule {}: {}", name, err),
+                });
+            }
+            self.ids.insert(name, techniques.len());
+            techniques.push(name);
+            self.initial.push(*allowed);
+        }
+        let names: Vec<&'static str> = rules.iter().map(|(name, _)| *name).collect();
+        let mut groups = self.groups.to_vec();
+        groups.push(Box::leak(names.into_boxed_slice()));
+        let mut group_names = self.group_names.to_vec();
+        group_names.push(SUPPLEMENTAL_GROUP);
+        self.techniques = Box::leak(techniques.into_boxed_slice());
+        self.groups = Box::leak(groups.into_boxed_slice());
+        self.group_names = Box::leak(group_names.into_boxed_slice());
+        Ok(self)
+    }
+}
+
+/// Why `name` can't be added to a catalog that already has `ids`, if it can't.
+pub(crate) fn technique_name_error(name: &str, ids: &HashMap<&'static str, usize>) -> Option<String> {
+    // rulesets are stored as a map with these next to the techniques
+    if name == "name" || name == "game" {
+        Some(format!("{} can't be used as a technique name", name))
+    } else if ids.contains_key(name) {
+        Some(format!("{} is listed more than once", name))
+    } else {
+        None
+    }
+}
+
+/// Parse a game's `techniques` file: blank lines separate groups of related techniques, each
+/// starting with its name in brackets.
+pub(crate) fn parse_catalog(game: &'static str, text: &'static str) -> Result<Catalog, UserInputError> {
+    let err = |err: String| UserInputError {
+        err: format!("In games/{}/techniques: {}", game, err),
+    };
+    let mut groups: Vec<Vec<&'static str>> = vec![vec![]];
+    let mut group_names: Vec<&'static str> = vec![];
+    for l in text.lines() {
+        let trimmed = l.trim();
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+            if !groups.last().unwrap().is_empty() || group_names.len() == groups.len() {
+                return Err(err(format!("Group [{}] needs a blank line before it and techniques after it", name)));
+            }
+            group_names.push(name);
+        } else if !trimmed.is_empty() {
+            groups.last_mut().unwrap().push(trimmed);
+        } else if !groups.last().unwrap().is_empty() {
+            groups.push(vec![]);
+        }
+    }
+    groups.retain(|g| !g.is_empty());
+    if group_names.len() != groups.len() {
+        return Err(err("Every group of techniques needs a [Name] line".to_string()));
+    }
+
+    let techniques: Vec<&'static str> = groups.iter().flatten().copied().collect();
+    let mut ids = HashMap::new();
+    for (i, technique) in techniques.iter().enumerate() {
+        if let Some(e) = technique_name_error(technique, &ids) {
+            return Err(err(e));
+        }
+        ids.insert(*technique, i);
+    }
+    Ok(Catalog {
+        game,
+        initial: vec![IsAllowed::UNSPECIFIED; techniques.len()],
+        techniques: Box::leak(techniques.into_boxed_slice()),
+        groups: Box::leak(groups.into_iter().map(|g| &*Box::leak(g.into_boxed_slice())).collect()),
+        group_names: Box::leak(group_names.into_boxed_slice()),
+        ids,
+    })
+}
+
+/// Read a data file that's kept for the life of the server, so its lines can be borrowed forever.
+pub(crate) fn read_static(path: &Path) -> Result<&'static str, UserInputError> {
+    let text = std::fs::read_to_string(path).map_err(|e| UserInputError {
+        err: format!("Couldn't read {}: {}", path.display(), e),
+    })?;
+    Ok(Box::leak(text.into_boxed_str()))
+}
+
+/// The games listed in `games/games`, the default first.
+pub(crate) fn game_slugs() -> Result<Vec<&'static str>, UserInputError> {
+    let games: Vec<&'static str> = read_static(&games_path().join("games"))?
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect();
+    if games.is_empty() {
+        return Err(UserInputError {
+            err: "games/games needs at least one game".to_string(),
+        });
+    }
+    Ok(games)
+}
+
+/// Read every game's catalog, adding its randomizable supplemental rules from `supplemental`, as
+/// (name, ruling) by game.
+fn load_catalogs(supplemental: &HashMap<String, Vec<(String, IsAllowed)>>) -> Result<Vec<Catalog>, UserInputError> {
+    game_slugs()?
+        .into_iter()
+        .map(|game| {
+            let rules: Vec<(&'static str, IsAllowed)> = supplemental
+                .get(game)
+                .into_iter()
+                .flatten()
+                .map(|(name, allowed)| (&*Box::leak(name.clone().into_boxed_str()), *allowed))
+                .collect();
+            parse_catalog(game, read_static(&games_path().join(game).join("techniques"))?)?.add_supplemental(&rules)
+        })
+        .collect()
+}
+
+static CATALOGS: OnceLock<Vec<Catalog>> = OnceLock::new();
+
+/// Load the catalogs with the randomizable supplemental rules in `supplemental`, as (name, ruling)
+/// by game. This has to happen before anything looks at a catalog; otherwise they're loaded with
+/// none.
+pub(crate) fn init_catalogs(supplemental: &HashMap<String, Vec<(String, IsAllowed)>>) -> Result<(), UserInputError> {
+    CATALOGS.set(load_catalogs(supplemental)?).map_err(|_| UserInputError {
+        err: "The technique catalogs are already loaded".to_string(),
+    })
+}
+
+/// Every game's catalog, in `games/games` order.
+pub(crate) fn catalogs() -> &'static [Catalog] {
+    CATALOGS.get_or_init(|| {
+        load_catalogs(&HashMap::new()).unwrap_or_else(|e| panic!("Error loading technique catalogs: {}", e))
+    })
+}
+
+pub(crate) fn catalog(game: &str) -> Option<&'static Catalog> {
+    catalogs().iter().find(|c| c.game == game)
+}
+
+/// The catalog of the game served at the unprefixed urls.
+pub(crate) fn default_catalog() -> &'static Catalog {
+    &catalogs()[0]
+}
+
+/// A ruleset for one game: a value for each of its catalog's techniques.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Ruleset {
+    pub(crate) name: String,
+    pub(crate) game: String,
+    /// Indexed by `Catalog::id`.
+    values: Vec<IsAllowed>,
+}
+
+impl Ruleset {
+    /// A ruleset for `game` with every technique `UNSPECIFIED`, apart from randomizable
+    /// supplemental rules, which have their ruling.
+    pub(crate) fn unspecified(game: &str, name: String) -> Self {
+        Self {
+            name,
+            game: game.to_string(),
+            values: catalog(game).unwrap_or_else(default_catalog).initial.clone(),
+        }
+    }
+
+    /// This ruleset's game's catalog (the default game's, for a game this server doesn't know).
+    pub(crate) fn catalog(&self) -> &'static Catalog {
+        catalog(&self.game).unwrap_or_else(default_catalog)
+    }
+
+    /// The game's techniques and their values, in catalog order.
+    pub(crate) fn techniques(&self) -> impl Iterator<Item = (&'static str, IsAllowed)> + '_ {
+        self.catalog().techniques.iter().copied().zip(self.values.iter().copied())
+    }
+
+    /// Look up a technique by name. Returns `None` for names that aren't techniques of this game.
+    pub(crate) fn get(&self, technique: &str) -> Option<IsAllowed> {
+        self.catalog().id(technique).map(|i| self.values[i])
+    }
+
+    /// Set a technique by name. Returns `false` (and changes nothing) for names that aren't
+    /// techniques of this game.
+    pub(crate) fn set(&mut self, technique: &str, value: IsAllowed) -> bool {
+        match self.catalog().id(technique) {
+            Some(i) => {
+                self.values[i] = value;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// `ruleset["Hover"]`, for code that knows the technique exists. Panics if it doesn't.
+impl Index<&str> for Ruleset {
+    type Output = IsAllowed;
+
+    fn index(&self, technique: &str) -> &IsAllowed {
+        match self.catalog().id(technique) {
+            Some(i) => &self.values[i],
+            None => panic!("{} isn't a {} technique", technique, self.game),
+        }
+    }
+}
+
+impl IndexMut<&str> for Ruleset {
+    fn index_mut(&mut self, technique: &str) -> &mut IsAllowed {
+        match self.catalog().id(technique) {
+            Some(i) => &mut self.values[i],
+            None => panic!("{} isn't a {} technique", technique, self.game),
+        }
+    }
+}
+
+/// `name`, `game`, then the game's techniques in catalog order.
+impl Serialize for Ruleset {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.values.len() + 2))?;
+        map.serialize_entry("name", &self.name)?;
+        map.serialize_entry("game", &self.game)?;
+        for (technique, allowed) in self.techniques() {
+            map.serialize_entry(technique, &allowed)?;
+        }
+        map.end()
+    }
+}
+
+/// Rulesets saved before there were several games have no `game` and are the default game's.
+/// Techniques the game doesn't have are ignored, and ones left out are as in `Ruleset::unspecified`.
+impl<'de> Deserialize<'de> for Ruleset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut map = HashMap::<String, String>::deserialize(deserializer)?;
+        let game = map.remove("game").unwrap_or_else(|| default_catalog().game.to_string());
+        if catalog(&game).is_none() {
+            return Err(D::Error::custom(format!("Unknown game {}", game)));
+        }
+        let name = map.remove("name").ok_or_else(|| D::Error::missing_field("name"))?;
+        let mut ruleset = Ruleset::unspecified(&game, name);
+        for (technique, value) in map {
+            ruleset.set(&technique, value.parse().map_err(D::Error::custom)?);
+        }
+        Ok(ruleset)
+    }
+}
+
+/// Weights for some techniques, by name. The rest are left at whatever the template is applied to.
+#[derive(Default)]
+pub(crate) struct RulesetTemplate {
+    weights: HashMap<String, TemplateState>,
+}
+
+impl RulesetTemplate {
+    /// Look up the template state for a technique by name.
+    pub(crate) fn get(&self, technique: &str) -> TemplateState {
+        self.weights.get(technique).copied().unwrap_or(TemplateState::USE_DEFAULT)
+    }
+
+    pub(crate) fn from_template_states(weights: &HashMap<String, TemplateState>) -> Self {
+        Self {
+            weights: weights.clone(),
+        }
+    }
+
+    pub(crate) fn apply_rule_with_rng<R: Rng>(&self, default: &IsAllowed, rule: &TemplateState, rng: &mut R) -> IsAllowed {
+        match rule {
+            TemplateState::STATIC(i) => *i,
+            TemplateState::CHANCE(c) => {
+                if c.roll(rng) {
+                    IsAllowed::ALLOWED
+                } else {
+                    IsAllowed::DISALLOWED
+                }
+            },
+            TemplateState::USE_DEFAULT => *default,
+        }
+    }
+
+    /// The chance each of `defaults`' techniques comes out `ALLOWED`, in catalog order.
+    pub(crate) fn allowed_chances(&self, defaults: &Ruleset) -> Vec<(&'static str, f64)> {
+        defaults
+            .techniques()
+            .map(|(technique, default)| {
+                let chance = match self.get(technique) {
+                    TemplateState::CHANCE(c) => c.probability(),
+                    TemplateState::STATIC(IsAllowed::ALLOWED) => 1.0,
+                    TemplateState::USE_DEFAULT if default == IsAllowed::ALLOWED => 1.0,
+                    _ => 0.0,
+                };
+                (technique, chance)
+            })
+            .collect()
+    }
+
+    /// Roll each of `defaults`' techniques in catalog order, so a seed always rolls the same.
+    pub(crate) fn apply_with_rng<R: Rng>(&self, defaults: &Ruleset, rng: &mut R) -> Ruleset {
+        let mut ruleset = defaults.clone();
+        for (value, technique) in ruleset.values.iter_mut().zip(defaults.catalog().techniques) {
+            *value = self.apply_rule_with_rng(value, &self.get(technique), rng);
+        }
+        ruleset
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{catalogs, parse_catalog, Ruleset, SUPPLEMENTAL_GROUP};
+    use crate::rules::{IsAllowed, NMGRules};
+
+    #[test]
+    fn test_parse_catalog() {
+        let c = parse_catalog("test", "[Movement]\nHover\nSuperSpeed\n\n[Clips]\n  Clip\n").unwrap();
+        assert_eq!(["Hover", "SuperSpeed", "Clip"], c.techniques);
+        assert_eq!(Some(&["Clip"][..]), c.group("Clips"));
+        assert_eq!(Some(2), c.id("Clip"));
+        assert_eq!(None, c.id("Misslotting"));
+
+        assert!(parse_catalog("test", "Hover\n").is_err());
+        assert!(parse_catalog("test", "[A]\nHover\n[B]\nClip\n").is_err());
+        assert!(parse_catalog("test", "[A]\nHover\n\n[B]\nHover\n").is_err());
+        assert!(parse_catalog("test", "[A]\nname\n").is_err());
+        for c in catalogs() {
+            assert_eq!(c.techniques.len(), c.groups.iter().map(|g| g.len()).sum::<usize>());
+        }
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut r = NMGRules.clone();
+        r["Hover"] = IsAllowed::UNSPECIFIED;
+        let json = serde_json::to_string(&r).unwrap();
+        assert!(json.starts_with(r#"{"name":"NMG","game":"alttp","SaveAndQuit":"#), "{}", json);
+        assert_eq!(r, serde_json::from_str::<Ruleset>(&json).unwrap());
+
+        // stored before there were several games
+        let old: Ruleset = serde_json::from_str(r#"{"name": "Old", "Hover": "ALLOWED", "BlueSuit": "ALLOWED"}"#).unwrap();
+        assert_eq!("alttp", old.game);
+        assert_eq!(IsAllowed::ALLOWED, old["Hover"]);
+        assert_eq!(IsAllowed::UNSPECIFIED, old["SuperSpeed"]);
+        assert_eq!(None, old.get("BlueSuit"));
+        assert!(serde_json::from_str::<Ruleset>(r#"{"name": "x", "game": "zelda2"}"#).is_err());
+    }
+
+    #[test]
+    fn test_add_supplemental() {
+        let text = "[Movement]\nHover\n";
+        let c = parse_catalog("test", text).unwrap();
+        let c = c.add_supplemental(&[("Super Bunny", IsAllowed::ALLOWED)]).unwrap();
+        assert_eq!(["Hover", "Super Bunny"], c.techniques);
+        assert_eq!(["Super Bunny"], c.supplemental());
+        assert_eq!(Some(1), c.id("Super Bunny"));
+        assert_eq!(vec![IsAllowed::UNSPECIFIED, IsAllowed::ALLOWED], c.initial);
+
+        let c = parse_catalog("test", text).unwrap();
+        assert!(c.add_supplemental(&[("Hover", IsAllowed::ALLOWED)]).is_err());
+        let c = parse_catalog("test", "[Supplemental]\nHover\n").unwrap();
+        assert_eq!(Some(&["Hover"][..]), c.group(SUPPLEMENTAL_GROUP));
+        assert!(c.add_supplemental(&[("Water Walk", IsAllowed::ALLOWED)]).is_err());
+    }
+}
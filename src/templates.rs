@@ -0,0 +1,330 @@
+//! Weight templates that build on each other. On top of the flat `InputWeights` format, a template
+//! may name a parent with `extends` and pull in reusable fragments with `include`:
+//!
+//! ```yaml
+//! name: weekly_no_clips
+//! extends: weekly
+//! include: [overworld_block]
+//! weights:
+//!   OverworldClipping: false
+//! ```
+//!
+//! Precedence, lowest to highest: the parent (itself fully resolved), then each include in the
+//! order listed, then the template's own `weights`. `defaults` is inherited from the parent unless
+//! the template sets its own. `groups` (see `crate::groups`) layer the same way as `weights`, and a
+//! group replaces lower layers' weights for its members (and the other way around).
+//! Fragments may include other fragments, but can't `extends` or set `defaults`. Resolving
+//! flattens everything into a plain `InputWeights`.
+
+use crate::games::{default_game, find_preset};
+use crate::groups::GroupRule;
+use crate::rules::{InputWeights, TemplateState, UserInputError};
+use crate::techniques::Catalog;
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Somewhere templates can be looked up by name.
+pub(crate) trait TemplateSource {
+    fn load(&self, name: &str) -> Result<String, UserInputError>;
+}
+
+/// Templates stored as `<name>.yaml` files in one directory.
+pub(crate) struct TemplateDir(pub(crate) PathBuf);
+
+impl TemplateSource for TemplateDir {
+    fn load(&self, name: &str) -> Result<String, UserInputError> {
+        let safe = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !safe {
+            return Err(UserInputError {
+                err: format!("Template names may only contain letters, digits, _ and -, got {:?}", name),
+            });
+        }
+        let path = self.0.join(format!("{}.yaml", name));
+        std::fs::read_to_string(&path).map_err(|e| UserInputError {
+            err: format!("Couldn't read template {} from {}: {}", name, path.display(), e),
+        })
+    }
+}
+
+impl TemplateSource for HashMap<String, String> {
+    fn load(&self, name: &str) -> Result<String, UserInputError> {
+        self.get(name).cloned().ok_or_else(|| UserInputError {
+            err: format!("Unknown template {}", name),
+        })
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct TemplateFile {
+    name: Option<String>,
+    defaults: Option<String>,
+    extends: Option<String>,
+    include: Vec<String>,
+    weights: HashMap<String, String>,
+    groups: HashMap<String, String>,
+}
+
+fn parse_file(yaml: &str, what: &str) -> Result<TemplateFile, UserInputError> {
+    serde_yaml::from_str(yaml).map_err(|e| UserInputError {
+        err: format!("Invalid input yaml in {}: {}", what, e),
+    })
+}
+
+type Layer = (HashMap<String, String>, HashMap<String, String>);
+
+/// A template with its parent and includes gathered up, as `weights` and `groups` layers lowest
+/// first. Layers are only merged once `defaults`, and so the game, is known.
+#[derive(Default)]
+struct Flattened {
+    name: Option<String>,
+    defaults: Option<String>,
+    layers: Vec<Layer>,
+}
+
+impl Flattened {
+    fn merge(&mut self, weights: HashMap<String, String>, groups: HashMap<String, String>) {
+        self.layers.push((weights, groups));
+    }
+
+    /// The layers merged using `catalog`'s groups. A group replaces any weights for its members
+    /// from lower layers, and a technique weight replaces a lower layer's weight for its group.
+    fn merged(self, catalog: &Catalog) -> Layer {
+        let members = |g: &str| catalog.group(g).unwrap_or_default();
+        let (mut all_weights, mut all_groups) = (HashMap::new(), HashMap::new());
+        for (weights, groups) in self.layers {
+            for g in groups.keys() {
+                for t in members(g) {
+                    all_weights.remove(*t);
+                }
+            }
+            all_groups.retain(|g: &String, _| !members(g).iter().any(|t| weights.contains_key(*t)));
+            all_weights.extend(weights);
+            all_groups.extend(groups);
+        }
+        (all_weights, all_groups)
+    }
+}
+
+/// Walks templates through `source`, keeping the chain of names being resolved to catch cycles.
+struct Resolver<'a, S: TemplateSource> {
+    source: &'a S,
+    stack: Vec<String>,
+}
+
+impl<'a, S: TemplateSource> Resolver<'a, S> {
+    fn enter(&mut self, name: &str) -> Result<(), UserInputError> {
+        if self.stack.iter().any(|n| n == name) {
+            let mut chain = self.stack.clone();
+            chain.push(name.to_string());
+            return Err(UserInputError {
+                err: format!("Template cycle: {}", chain.join(" -> ")),
+            });
+        }
+        self.stack.push(name.to_string());
+        Ok(())
+    }
+
+    /// Merge `include`d fragments into `flat`, in order.
+    fn apply_includes(&mut self, includes: &[String], flat: &mut Flattened) -> Result<(), UserInputError> {
+        for name in includes {
+            self.enter(name)?;
+            let fragment = parse_file(&self.source.load(name)?, name)?;
+            if fragment.extends.is_some() || fragment.defaults.is_some() {
+                return Err(UserInputError {
+                    err: format!("{} is included as a fragment, so it can't use extends or defaults", name),
+                });
+            }
+            self.apply_includes(&fragment.include, flat)?;
+            flat.merge(fragment.weights, fragment.groups);
+            self.stack.pop();
+        }
+        Ok(())
+    }
+
+    fn resolve(&mut self, file: TemplateFile) -> Result<Flattened, UserInputError> {
+        let mut flat = match &file.extends {
+            Some(parent) => {
+                self.enter(parent)?;
+                let resolved = self.resolve(parse_file(&self.source.load(parent)?, parent)?)?;
+                self.stack.pop();
+                resolved
+            }
+            None => Flattened::default(),
+        };
+        self.apply_includes(&file.include, &mut flat)?;
+        flat.merge(file.weights, file.groups);
+        if file.name.is_some() {
+            flat.name = file.name;
+        }
+        if file.defaults.is_some() {
+            flat.defaults = file.defaults;
+        }
+        Ok(flat)
+    }
+}
+
+/// Parse a template and everything it `extends` or `include`s from `source` into flat weights,
+/// ready for `munge_user_input`.
+pub(crate) fn resolve_template<S: TemplateSource>(yaml: &str, source: &S) -> Result<InputWeights, UserInputError> {
+    let mut flat = Resolver { source, stack: vec![] }.resolve(parse_file(yaml, "template")?)?;
+    let name = flat.name.take().ok_or_else(|| UserInputError {
+        err: "Template needs a name".to_string(),
+    })?;
+    let defaults = flat.defaults.take().ok_or_else(|| UserInputError {
+        err: "Template needs defaults, either its own or from a template it extends".to_string(),
+    })?;
+    let (weights, groups) = flat.merged(catalog_for_defaults(&defaults));
+    Ok(InputWeights {
+        name,
+        defaults,
+        weights,
+        groups,
+    })
+}
+
+/// `map` as a YAML mapping, keys in the order of `catalog` (unknown keys last, alphabetically) and
+/// values passed through `canonical`.
+fn in_catalog_order(
+    map: &HashMap<String, String>,
+    catalog: &[&str],
+    canonical: impl Fn(&str, &str) -> Option<String>,
+) -> Mapping {
+    let mut keys: Vec<&str> = catalog.iter().copied().filter(|k| map.contains_key(*k)).collect();
+    let mut unknown: Vec<&str> = map.keys().map(|k| k.as_str()).filter(|k| !catalog.contains(k)).collect();
+    unknown.sort_unstable();
+    keys.extend(unknown);
+
+    let mut ordered = Mapping::new();
+    for k in keys {
+        let value = canonical(k, &map[k]).unwrap_or_else(|| map[k].clone());
+        ordered.insert(Value::from(k), Value::from(value));
+    }
+    ordered
+}
+
+/// The catalog of the game whose preset `defaults` is, or the default game's if there's no such
+/// preset (`munge_user_input` reports that).
+fn catalog_for_defaults(defaults: &str) -> &'static Catalog {
+    find_preset(defaults).map_or_else(|| default_game().catalog, |p| p.catalog())
+}
+
+/// A resolved template as YAML, weights and groups in catalog order and written canonically
+/// (`0.4` as `40%` and so on). Values that don't parse are left as they are.
+pub(crate) fn flattened_yaml(weights: &InputWeights) -> String {
+    let catalog = catalog_for_defaults(&weights.defaults);
+    let mut flat = Mapping::new();
+    flat.insert(Value::from("name"), Value::from(weights.name.as_str()));
+    flat.insert(Value::from("defaults"), Value::from(weights.defaults.as_str()));
+    flat.insert(
+        Value::from("weights"),
+        Value::Mapping(in_catalog_order(&weights.weights, catalog.techniques, |_, v| {
+            TemplateState::from_user_input(v.to_string()).ok()?.to_user_input()
+        })),
+    );
+    if !weights.groups.is_empty() {
+        flat.insert(
+            Value::from("groups"),
+            Value::Mapping(in_catalog_order(&weights.groups, catalog.group_names, |g, v| {
+                Some(GroupRule::from_user_input(catalog, g, v).ok()?.to_user_input())
+            })),
+        );
+    }
+    serde_yaml::to_string(&flat).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{flattened_yaml, resolve_template};
+    use crate::rules::{munge_user_input, TemplateState};
+    use crate::weights::Chance;
+    use std::collections::HashMap;
+
+    fn source() -> HashMap<String, String> {
+        let mut s = HashMap::new();
+        s.insert(
+            "weekly".to_string(),
+            "name: weekly\ndefaults: NMGRules\nweights:\n  Hover: 85%\n  OverworldClipping: 10%\n".to_string(),
+        );
+        s.insert(
+            "overworld_block".to_string(),
+            "include: [clips]\nweights:\n  OverworldEG: 5%\n  OverworldClipping: 20%\n".to_string(),
+        );
+        s.insert("clips".to_string(), "weights:\n  OverworldClipping: 30%\n  UnderworldClipping: 5%\n".to_string());
+        s.insert("loop_a".to_string(), "include: [loop_b]\n".to_string());
+        s.insert("loop_b".to_string(), "include: [loop_a]\n".to_string());
+        s.insert("self_extending".to_string(), "name: self_extending\nextends: self_extending\n".to_string());
+        s.insert("bad_fragment".to_string(), "defaults: NMGRules\n".to_string());
+        s
+    }
+
+    #[test]
+    fn test_precedence() {
+        let yaml = "name: variant\nextends: weekly\ninclude: [overworld_block]\nweights:\n  Hover: false\n";
+        let resolved = resolve_template(yaml, &source()).unwrap();
+        assert_eq!("variant", resolved.name);
+        assert_eq!("NMGRules", resolved.defaults);
+        let w = |k: &str| resolved.weights[k].as_str();
+        // own weights beat everything; a fragment beats what it includes and the parent
+        assert_eq!("false", w("Hover"));
+        assert_eq!("20%", w("OverworldClipping"));
+        assert_eq!("5%", w("UnderworldClipping"));
+        assert_eq!("5%", w("OverworldEG"));
+
+        let (munged, _) = munge_user_input(resolved, true).unwrap();
+        assert_eq!(TemplateState::CHANCE(Chance::per_thousand(200)), munged.weights["OverworldClipping"]);
+    }
+
+    #[test]
+    fn test_flattened_is_canonical() {
+        let yaml = "name: x\ndefaults: NMGRules\nweights:\n  Hover: 0.85\n  HeraPot: 1 in 3\n  BombJump: TRUE\n  Oops: 2\ngroups:\n  Overworld: together rare\n";
+        let flat = flattened_yaml(&resolve_template(yaml, &source()).unwrap());
+        assert_eq!(
+            "---\nname: x\ndefaults: NMGRules\nweights:\n  BombJump: \"true\"\n  Hover: 85%\n  HeraPot: 1/3\n  Oops: \"2\"\ngroups:\n  Overworld: together 5%\n",
+            flat
+        );
+        let again = flattened_yaml(&resolve_template(&flat, &source()).unwrap());
+        assert_eq!(flat, again);
+    }
+
+    #[test]
+    fn test_groups_replace_weights() {
+        let yaml = "name: g\nextends: weekly\ngroups:\n  Overworld: exactly 1\n";
+        let resolved = resolve_template(yaml, &source()).unwrap();
+        assert!(!resolved.weights.contains_key("OverworldClipping"));
+        assert_eq!("85%", resolved.weights["Hover"]);
+        assert_eq!(1, munge_user_input(resolved, true).unwrap().0.groups.len());
+
+        // and a technique weight replaces an inherited group
+        let mut s = source();
+        s.insert("grouped".to_string(), yaml.to_string());
+        let resolved = resolve_template("name: x\nextends: grouped\nweights:\n  OverworldEG: true\n", &s).unwrap();
+        assert!(resolved.groups.is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |yaml: &str| resolve_template(yaml, &source()).err().unwrap().to_string();
+        assert_eq!(
+            "Template cycle: loop_a -> loop_b -> loop_a",
+            err("name: x\ndefaults: NMGRules\ninclude: [loop_a]\n")
+        );
+        assert_eq!(
+            "Template cycle: self_extending -> self_extending",
+            err("name: y\nextends: self_extending\n")
+        );
+        assert_eq!("Unknown template nope", err("name: x\nextends: nope\n"));
+        assert_eq!(
+            "bad_fragment is included as a fragment, so it can't use extends or defaults",
+            err("name: x\ndefaults: NMGRules\ninclude: [bad_fragment]\n")
+        );
+        assert_eq!(
+            "Template needs defaults, either its own or from a template it extends",
+            err("name: x\ninclude: [clips]\n")
+        );
+        // diamonds aren't cycles
+        assert!(resolve_template("name: x\ndefaults: NMGRules\ninclude: [clips, overworld_block]\n", &source()).is_ok());
+    }
+}
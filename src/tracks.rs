@@ -0,0 +1,279 @@
+//! Tracks: weeklies that publish side by side in the same period, each rolled from its own
+//! template. Each game lists its tracks in `tracks.yaml` in its data directory (see `crate::games`).
+//! The default game's original NMG-based weekly is the built-in `weekly` track; every other game
+//! needs to list a `weekly` track of its own.
+//!
+//! ```yaml
+//! tracks:
+//!   - slug: glitched        # used in urls: /weekly/glitched, /sm/weekly/glitched
+//!     name: Glitched
+//!     template: glitched    # glitched.yaml next to tracks.yaml, see `crate::templates`
+//!     defaults: NMGRules    # optional, replaces the template's defaults
+//!     salt: 1               # mixed into the seed so tracks don't roll in lockstep
+//! ```
+
+use crate::rules::{
+    find_default, munge_user_input, roll_weekly_ruleset, weekly_seed, weekly_template, MungedInputWeights, NMGRules,
+    UserInputError,
+};
+use crate::games::{default_game, find_game, games, Game};
+use crate::techniques::{default_catalog, Ruleset};
+use crate::templates::{resolve_template, TemplateDir, TemplateSource};
+use chrono::{Date, TimeZone};
+use lazy_static::lazy_static;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+
+/// Every game's main track. The default game's is built in and rolled from `weekly_template`, as it
+/// was before there were tracks.
+pub(crate) const DEFAULT_TRACK: &str = "weekly";
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Track {
+    /// The game this track is for, from the directory its `tracks.yaml` is in.
+    #[serde(skip)]
+    pub(crate) game: &'static str,
+    pub(crate) slug: String,
+    pub(crate) name: String,
+    /// `None` only for the built-in track.
+    #[serde(default)]
+    template: Option<String>,
+    #[serde(default)]
+    defaults: Option<String>,
+    #[serde(default)]
+    pub(crate) salt: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TracksFile {
+    tracks: Vec<Track>,
+}
+
+pub(crate) fn default_track() -> Track {
+    Track {
+        game: default_catalog().game,
+        slug: DEFAULT_TRACK.to_string(),
+        name: "Weekly".to_string(),
+        template: None,
+        defaults: None,
+        salt: 0,
+    }
+}
+
+impl Track {
+    /// Whether this is the built-in track.
+    pub(crate) fn is_default(&self) -> bool {
+        self.template.is_none()
+    }
+
+    /// Whether this is its game's main track.
+    pub(crate) fn is_weekly(&self) -> bool {
+        self.slug == DEFAULT_TRACK
+    }
+
+    pub(crate) fn game(&self) -> &'static Game {
+        find_game(self.game).unwrap_or_else(default_game)
+    }
+
+    /// This track's weekly page.
+    pub(crate) fn url(&self) -> String {
+        if self.is_weekly() {
+            self.game().url("/weekly")
+        } else {
+            self.game().url(&format!("/weekly/{}", self.slug))
+        }
+    }
+
+    /// The seed this track's ruleset for the period starting on `sunday` is rolled with.
+    pub(crate) fn seed<TZ: TimeZone>(&self, sunday: &Date<TZ>) -> u64 {
+        weekly_seed(sunday) ^ self.salt
+    }
+
+    /// The track's template, parsed. Weights for techniques the game doesn't have are an error, so a
+    /// typo can't quietly leave a technique at its default.
+    fn weights<S: TemplateSource>(&self, source: &S) -> Result<MungedInputWeights, UserInputError> {
+        let template = self.template.as_ref().ok_or_else(|| UserInputError {
+            err: format!("Track {} has no template", self.slug),
+        })?;
+        let mut weights = resolve_template(&source.load(template)?, source)?;
+        weights.name = self.name.clone();
+        if let Some(defaults) = &self.defaults {
+            weights.defaults = defaults.clone();
+        }
+        munge_user_input(weights, true).map(|(munged, _)| munged)
+    }
+
+    fn roll_from<S: TemplateSource>(&self, seed: u64, source: &S) -> Result<Ruleset, UserInputError> {
+        if self.template.is_none() {
+            return Ok(roll_weekly_ruleset(seed));
+        }
+        Ok(self.weights(source)?.roll_with_rng(&mut SmallRng::seed_from_u64(seed)))
+    }
+
+    /// The chance each technique is allowed in this track's rolls, in catalog order.
+    pub(crate) fn allowed_chances(&self) -> Result<Vec<(&'static str, f64)>, UserInputError> {
+        if self.template.is_none() {
+            return Ok(weekly_template().allowed_chances(&NMGRules));
+        }
+        Ok(self.weights(&TemplateDir(self.game().dir()))?.allowed_chances())
+    }
+
+    /// Roll this track's ruleset with `seed`. Templates are read from its game's directory.
+    pub(crate) fn roll(&self, seed: u64) -> Result<Ruleset, UserInputError> {
+        self.roll_from(seed, &TemplateDir(self.game().dir()))
+    }
+}
+
+/// Parse `game`'s `tracks.yaml`. The default game's built-in track comes first.
+fn parse_tracks(game: &'static Game, yaml: &str) -> Result<Vec<Track>, UserInputError> {
+    let file: TracksFile = serde_yaml::from_str(yaml).map_err(|e| UserInputError {
+        err: format!("Error parsing tracks: {}", e),
+    })?;
+    let mut tracks = if game.is_default() { vec![default_track()] } else { vec![] };
+    for mut track in file.tracks {
+        track.game = game.slug;
+        let slug_ok = !track.slug.is_empty()
+            && track.slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !slug_ok {
+            return Err(UserInputError {
+                err: format!("Track slugs may only contain lowercase letters, digits and -, got {:?}", track.slug),
+            });
+        }
+        if tracks.iter().any(|t| t.slug == track.slug) {
+            return Err(UserInputError {
+                err: format!("Track {} is defined more than once", track.slug),
+            });
+        }
+        if track.template.is_none() {
+            return Err(UserInputError {
+                err: format!("Track {} needs a template", track.slug),
+            });
+        }
+        if let Some(defaults) = &track.defaults {
+            if find_default(defaults.clone())?.game != game.slug {
+                return Err(UserInputError {
+                    err: format!("Track {}'s defaults {} aren't a {} preset", track.slug, defaults, game.slug),
+                });
+            }
+        }
+        tracks.push(track);
+    }
+    if !tracks.iter().any(|t| t.is_weekly()) {
+        return Err(UserInputError {
+            err: format!("{} needs a {} track", game.slug, DEFAULT_TRACK),
+        });
+    }
+    Ok(tracks)
+}
+
+/// Every game's tracks, each game's main one first. A missing `tracks.yaml` means the default game
+/// has only the built-in track and any other game has none; a broken one is an error.
+fn load_tracks() -> Result<HashMap<&'static str, Vec<Track>>, UserInputError> {
+    let mut all = HashMap::new();
+    for game in games() {
+        let path = game.dir().join("tracks.yaml");
+        let mut tracks = match std::fs::read_to_string(&path) {
+            Ok(yaml) => parse_tracks(game, &yaml).map_err(|e| UserInputError {
+                err: format!("{}: {}", path.display(), e),
+            })?,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                if game.is_default() { vec![default_track()] } else { vec![] }
+            }
+            Err(e) => {
+                return Err(UserInputError {
+                    err: format!("Error reading {}: {}", path.display(), e),
+                })
+            }
+        };
+        tracks.sort_by_key(|t| !t.is_weekly());
+        all.insert(game.slug, tracks);
+    }
+    Ok(all)
+}
+
+lazy_static! {
+    static ref TRACKS: HashMap<&'static str, Vec<Track>> =
+        load_tracks().unwrap_or_else(|e| panic!("Error loading tracks: {}", e));
+}
+
+/// Every track of `game`, its main one first. Tracks are read once, the first time they're needed.
+pub(crate) fn tracks(game: &'static Game) -> Vec<Track> {
+    TRACKS.get(game.slug).cloned().unwrap_or_default()
+}
+
+pub(crate) fn find_track(game: &'static Game, slug: &str) -> Option<Track> {
+    tracks(game).into_iter().find(|t| t.slug == slug)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_tracks, DEFAULT_TRACK};
+    use crate::games::{default_game, find_game};
+    use crate::rules::{roll_weekly_ruleset, IsAllowed};
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashMap;
+
+    const TRACKS: &str = "
+tracks:
+  - slug: glitched
+    name: Glitched
+    template: glitched
+    salt: 5
+";
+
+    #[test]
+    fn test_parse_tracks() {
+        let tracks = parse_tracks(default_game(), TRACKS).unwrap();
+        assert_eq!(vec![DEFAULT_TRACK, "glitched"], tracks.iter().map(|t| t.slug.as_str()).collect::<Vec<_>>());
+        assert!(parse_tracks(default_game(), "tracks: []").unwrap()[0].is_default());
+
+        for (yaml, err) in [
+            (TRACKS.replace("glitched\n    name", "Glitched\n    name"), "Track slugs may only"),
+            (TRACKS.replace("slug: glitched", "slug: weekly"), "Track weekly is defined more than once"),
+            (TRACKS.replace("    template: glitched\n", ""), "Track glitched needs a template"),
+            (TRACKS.replace("salt: 5", "salt: 5\n    defaults: Nope"), "Unknown value for `defaults`"),
+            (TRACKS.replace("salt: 5", "salt: 5\n    defaults: SMAnyPercent"), "Track glitched's defaults"),
+            (TRACKS.replace("salt", "pepper"), "Error parsing tracks"),
+        ] {
+            let e = parse_tracks(default_game(), &yaml).unwrap_err().to_string();
+            assert!(e.starts_with(err), "{}", e);
+        }
+
+        // other games have no built-in track, so need to list their own
+        let sm = find_game("sm").unwrap();
+        let e = parse_tracks(sm, "tracks: []").unwrap_err().to_string();
+        assert_eq!("sm needs a weekly track", e);
+        let yaml = "tracks:\n  - {slug: weekly, name: Weekly, template: weekly}\n";
+        let tracks = parse_tracks(sm, yaml).unwrap();
+        assert_eq!("sm", tracks[0].game);
+        assert!(tracks[0].is_weekly() && !tracks[0].is_default());
+    }
+
+    #[test]
+    fn test_roll() {
+        let tracks = parse_tracks(default_game(), TRACKS).unwrap();
+        let mut source = HashMap::new();
+        source.insert(
+            "glitched".to_string(),
+            "name: g\ndefaults: NMGRules\nweights:\n  OverworldClipping: true\n".to_string(),
+        );
+        let sunday = Utc.ymd(2021, 10, 3);
+        let (weekly, glitched) = (&tracks[0], &tracks[1]);
+
+        // the built-in track rolls exactly as the weekly always has
+        assert_eq!(
+            roll_weekly_ruleset(weekly.seed(&sunday)),
+            weekly.roll_from(weekly.seed(&sunday), &source).unwrap()
+        );
+        assert_ne!(weekly.seed(&sunday), glitched.seed(&sunday));
+        let r = glitched.roll_from(glitched.seed(&sunday), &source).unwrap();
+        assert_eq!("Glitched", r.name);
+        assert_eq!(IsAllowed::ALLOWED, r["OverworldClipping"]);
+        assert!(glitched.roll_from(1, &HashMap::new()).is_err());
+    }
+}
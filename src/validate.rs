@@ -0,0 +1,445 @@
+//! Checking weight files before they're used. `validate_weights` reports everything it finds, each
+//! with where it is in the file and, for misspellings, what was probably meant, rather than stopping
+//! at the first problem. In strict mode unknown keys are errors rather than warnings.
+
+use crate::groups::GroupRule;
+use crate::rules::{find_default, TemplateState};
+use crate::games::{default_game, find_preset, preset_ids};
+use crate::techniques::Catalog;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+const TOP_LEVEL_KEYS: [&str; 6] = ["name", "defaults", "extends", "include", "weights", "groups"];
+
+#[derive(Serialize, Copy, Clone, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub(crate) struct Diagnostic {
+    pub(crate) severity: Severity,
+    /// Where in the document, e.g. `weights.OverworldClip`; empty for the document itself.
+    pub(crate) path: String,
+    /// 1-based, like editors show them; 0 when not known, as for a template merged from several files.
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) message: String,
+    pub(crate) suggestion: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "{}:{}: ", self.line, self.column)?;
+        }
+        write!(f, "{}: ", self.severity)?;
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        f.write_str(&self.message)?;
+        if let Some(s) = &self.suggestion {
+            write!(f, " (did you mean `{}`?)", s)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The candidate closest to `input`, ignoring case, if any is close enough to be a likely typo.
+pub(crate) fn did_you_mean<'a>(input: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let input = input.to_lowercase();
+    candidates
+        .iter()
+        .map(|c| (edit_distance(&input, &c.to_lowercase()), *c))
+        .filter(|(d, c)| *d <= 2.max(c.len() / 3))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Diagnostics for weights given for `keys`, which aren't techniques in `catalog`: warnings, or
+/// errors if `strict`. These come from already-parsed weights, so they have no position.
+pub(crate) fn unknown_techniques(keys: &[String], catalog: &Catalog, strict: bool) -> Vec<Diagnostic> {
+    let mut keys: Vec<&String> = keys.iter().collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| Diagnostic {
+            severity: if strict { Severity::Error } else { Severity::Warning },
+            path: join("weights", key),
+            line: 0,
+            column: 0,
+            message: format!("unknown technique {}", key),
+            suggestion: did_you_mean(key, catalog.techniques).map(|s| s.to_string()),
+        })
+        .collect()
+}
+
+enum Yaml {
+    Scalar(String),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+    Alias,
+}
+
+/// A YAML value along with where it starts.
+struct Node {
+    value: Yaml,
+    line: usize,
+    column: usize,
+}
+
+impl Node {
+    fn scalar(&self) -> Option<&str> {
+        match &self.value {
+            Yaml::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Builds `Node`s out of parser events, since serde_yaml's values don't keep positions.
+#[derive(Default)]
+struct TreeBuilder {
+    /// Open sequences and mappings, with a mapping's key while waiting for its value.
+    stack: Vec<(Node, Option<Node>)>,
+    root: Option<Node>,
+}
+
+impl TreeBuilder {
+    fn push(&mut self, node: Node) {
+        match self.stack.last_mut() {
+            None => self.root = Some(node),
+            Some((parent, key)) => match &mut parent.value {
+                Yaml::Sequence(items) => items.push(node),
+                Yaml::Mapping(entries) => match key.take() {
+                    Some(k) => entries.push((k, node)),
+                    None => {
+                        // the parser marks block mappings after their first key, so start there
+                        if entries.is_empty() {
+                            parent.line = node.line;
+                            parent.column = node.column;
+                        }
+                        *key = Some(node)
+                    }
+                },
+                _ => unreachable!(),
+            },
+        }
+    }
+}
+
+impl MarkedEventReceiver for TreeBuilder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        let node = |value| Node {
+            value,
+            line: mark.line(),
+            column: mark.col() + 1,
+        };
+        match event {
+            Event::Scalar(s, ..) => self.push(node(Yaml::Scalar(s))),
+            Event::Alias(_) => self.push(node(Yaml::Alias)),
+            Event::SequenceStart(_) => self.stack.push((node(Yaml::Sequence(vec![])), None)),
+            Event::MappingStart(_) => self.stack.push((node(Yaml::Mapping(vec![])), None)),
+            Event::SequenceEnd | Event::MappingEnd => {
+                let (done, _) = self.stack.pop().unwrap();
+                self.push(done);
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Validator {
+    strict: bool,
+    /// The catalog of the game `defaults` is for, or the default game's.
+    catalog: &'static Catalog,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator {
+    fn report(&mut self, severity: Severity, path: &str, at: &Node, message: String, suggestion: Option<&str>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: path.to_string(),
+            line: at.line,
+            column: at.column,
+            message,
+            suggestion: suggestion.map(str::to_string),
+        });
+    }
+
+    fn unknown(&mut self, path: &str, at: &Node, message: String, suggestion: Option<&str>) {
+        let severity = if self.strict { Severity::Error } else { Severity::Warning };
+        self.report(severity, path, at, message, suggestion);
+    }
+
+    /// The entries of a mapping with string keys, reporting anything else.
+    fn entries<'n>(&mut self, path: &str, node: &'n Node) -> Vec<(&'n str, &'n Node, &'n Node)> {
+        let entries = match &node.value {
+            Yaml::Mapping(entries) => entries,
+            _ => {
+                self.report(Severity::Error, path, node, "expected a mapping".to_string(), None);
+                return vec![];
+            }
+        };
+        let mut seen = HashSet::new();
+        let mut out = vec![];
+        for (k, v) in entries {
+            match k.scalar() {
+                Some(key) if !seen.insert(key) => {
+                    let message = format!("{} is listed more than once", key);
+                    self.report(Severity::Error, &join(path, key), k, message, None);
+                }
+                Some(key) => out.push((key, k, v)),
+                None => self.report(Severity::Error, path, k, "expected a plain key".to_string(), None),
+            }
+        }
+        out
+    }
+
+    fn scalar<'n>(&mut self, path: &str, node: &'n Node) -> Option<&'n str> {
+        let s = node.scalar();
+        if s.is_none() {
+            self.report(Severity::Error, path, node, "expected a single value".to_string(), None);
+        }
+        s
+    }
+
+    fn weights(&mut self, node: &Node) -> HashSet<&'static str> {
+        let mut weighted = HashSet::new();
+        for (key, k, v) in self.entries("weights", node) {
+            let path = join("weights", key);
+            match self.catalog.techniques.iter().find(|t| **t == key) {
+                Some(technique) => {
+                    weighted.insert(*technique);
+                    if let Some(value) = self.scalar(&path, v) {
+                        if let Err(e) = TemplateState::from_user_input(value.to_string()) {
+                            self.report(Severity::Error, &path, v, format!("{} (got {})", e, value), None);
+                        }
+                    }
+                }
+                None if self.catalog.group_names.contains(&key) => {
+                    let message = format!("{} is a group of techniques; weight it under groups", key);
+                    self.unknown(&path, k, message, None);
+                }
+                None => {
+                    let message = format!("unknown technique {}", key);
+                    self.unknown(&path, k, message, did_you_mean(key, self.catalog.techniques));
+                }
+            }
+        }
+        weighted
+    }
+
+    fn groups(&mut self, node: &Node, weighted: &HashSet<&str>) {
+        for (key, k, v) in self.entries("groups", node) {
+            let path = join("groups", key);
+            let members = match self.catalog.group(key) {
+                Some(members) => members,
+                None => {
+                    let message = format!("unknown group {}", key);
+                    let suggestion = did_you_mean(key, self.catalog.group_names);
+                    self.report(Severity::Error, &path, k, message, suggestion);
+                    continue;
+                }
+            };
+            if let Some(value) = self.scalar(&path, v) {
+                if let Err(e) = GroupRule::from_user_input(self.catalog, key, value) {
+                    self.report(Severity::Error, &path, v, e.to_string(), None);
+                }
+            }
+            if let Some(t) = members.iter().find(|t| weighted.contains(*t)) {
+                let message = format!("{} is weighted both on its own and as part of this group", t);
+                self.report(Severity::Error, &path, k, message, None);
+            }
+        }
+    }
+
+    fn document(&mut self, root: &Node) {
+        let entries = self.entries("", root);
+        let defaults = entries.iter().find(|(key, ..)| *key == "defaults").and_then(|(.., v)| v.scalar());
+        if let Some(preset) = defaults.and_then(find_preset) {
+            self.catalog = preset.catalog();
+        }
+        let mut weighted = HashSet::new();
+        let mut groups = None;
+        for (key, k, v) in &entries {
+            match *key {
+                "name" => {
+                    self.scalar("name", v);
+                }
+                "defaults" => {
+                    if let Some(d) = self.scalar("defaults", v) {
+                        if let Err(e) = find_default(d.to_string()) {
+                            self.report(Severity::Error, "defaults", v, e.to_string(), did_you_mean(d, &preset_ids()));
+                        }
+                    }
+                }
+                "extends" => {
+                    self.scalar("extends", v);
+                }
+                "include" => match &v.value {
+                    Yaml::Sequence(items) => {
+                        for (i, item) in items.iter().enumerate() {
+                            self.scalar(&format!("include[{}]", i), item);
+                        }
+                    }
+                    _ => self.report(Severity::Error, "include", v, "expected a list of templates".to_string(), None),
+                },
+                "weights" => weighted = self.weights(v),
+                "groups" => groups = Some(*v),
+                _ => {
+                    let message = format!("unknown field {}", key);
+                    self.unknown(key, k, message, did_you_mean(key, &TOP_LEVEL_KEYS));
+                }
+            }
+        }
+        // after weights, to check for overlap
+        if let Some(g) = groups {
+            self.groups(g, &weighted);
+        }
+
+        let has = |key: &str| entries.iter().any(|(k, ..)| *k == key);
+        if !has("name") && !has("extends") {
+            self.report(Severity::Error, "", root, "missing field name".to_string(), None);
+        }
+        if !has("defaults") && !has("extends") {
+            self.report(Severity::Error, "", root, "missing field defaults".to_string(), None);
+        }
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Everything wrong with the weights file `yaml`, in the order it appears. Errors would stop the
+/// file being used; warnings are for things that would be silently ignored.
+pub(crate) fn validate_weights(yaml: &str, strict: bool) -> Vec<Diagnostic> {
+    let mut builder = TreeBuilder::default();
+    if let Err(e) = Parser::new(yaml.chars()).load(&mut builder, false) {
+        let message = e.to_string();
+        return vec![Diagnostic {
+            severity: Severity::Error,
+            path: String::new(),
+            line: e.marker().line(),
+            column: e.marker().col() + 1,
+            message: message.split(" at line ").next().unwrap_or(&message).to_string(),
+            suggestion: None,
+        }];
+    }
+    let root = match builder.root {
+        Some(root) => root,
+        None => {
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                path: String::new(),
+                line: 1,
+                column: 1,
+                message: "the file is empty".to_string(),
+                suggestion: None,
+            }]
+        }
+    };
+    let mut validator = Validator {
+        strict,
+        catalog: default_game().catalog,
+        diagnostics: vec![],
+    };
+    validator.document(&root);
+    let mut diagnostics = validator.diagnostics;
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::{did_you_mean, has_errors, validate_weights, Severity};
+    use crate::games::default_game;
+
+    #[test]
+    fn test_did_you_mean() {
+        let techniques = default_game().catalog.techniques;
+        assert_eq!(Some("OverworldClipping"), did_you_mean("OverworldClip", techniques));
+        assert_eq!(Some("Hover"), did_you_mean("hover", techniques));
+        assert_eq!(Some("HeraPot"), did_you_mean("HerraPot", techniques));
+        assert_eq!(None, did_you_mean("Speedrun", techniques));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let yaml = "name: x\ndefaults: NMGRules\nweights:\n  OverworldClip: 40%\n  Hover: sometimes\ngroups:\n  Overwrld: each 5\n";
+        let d = validate_weights(yaml, false);
+        assert_eq!(3, d.len());
+        assert_eq!(
+            "4:3: warning: weights.OverworldClip: unknown technique OverworldClip (did you mean `OverworldClipping`?)",
+            d[0].to_string()
+        );
+        assert_eq!((5, 10, Severity::Error), (d[1].line, d[1].column, d[1].severity));
+        assert_eq!(Some("Overworld".to_string()), d[2].suggestion);
+        assert_eq!("groups.Overwrld", d[2].path);
+
+        let strict = validate_weights("name: x\ndefaults: NMGRules\nweights:\n  OverworldClip: 40%\n", true);
+        assert!(has_errors(&strict));
+        assert!(!has_errors(&validate_weights("name: x\ndefaults: NMGRules\nweights:\n  OverworldClip: 40%\n", false)));
+
+        assert!(validate_weights("name: x\ndefaults: NMGRules\nweights:\n  Hover: 1 in 5\n", true).is_empty());
+        let d = validate_weights("nmae: x\ndefaults: NMGRule\n", false);
+        let messages: Vec<String> = d.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            vec![
+                "1:1: warning: nmae: unknown field nmae (did you mean `name`?)",
+                "1:1: error: missing field name",
+                "2:11: error: defaults: Unknown value for `defaults` field: NMGRule (did you mean `NMGRules`?)",
+            ],
+            messages
+        );
+        let d = validate_weights("name: x\nweights: [\n", false);
+        assert_eq!(1, d.len());
+        assert_eq!(Severity::Error, d[0].severity);
+        assert_eq!(
+            "weights.Hover: Hover is listed more than once",
+            validate_weights("name: x\ndefaults: NMGRules\nweights:\n  Hover: 5%\n  Hover: 6%\n", false)[0]
+                .to_string()
+                .split_once(": error: ")
+                .unwrap()
+                .1
+        );
+    }
+}
@@ -0,0 +1,393 @@
+//! Organizer tools for fixing up a published ruleset: re-rolling, hand edits, announcement notes,
+//! and marking things superseded. Every change lands in `ruleset_audit_log`.
+
+use crate::rules::{diff_rulesets, IsAllowed};
+use crate::games::find_game;
+use crate::techniques::Ruleset;
+use crate::tracks::{find_track, Track};
+use crate::web::notes::{get_moderation_entries, RenderedNote};
+use crate::web::submissions::{get_review_entries, ReviewEntry};
+use crate::web::webhooks::announce_ruleset;
+use crate::web::weekly::{get_stored_ruleset, save_override, RulesetRecord, RECORD_COLUMNS};
+use crate::webhooks::EVENT_OVERRIDDEN;
+use chrono::{NaiveDate, TimeZone, Utc};
+use rocket::form::{Form, FromForm};
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::status::{BadRequest, NotFound};
+use rocket::response::Redirect;
+use rocket::{catch, get, post, Build, Request, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::env::var;
+
+const ADMIN_COOKIE: &str = "admin_token";
+
+/// An organizer. Tokens are configured as `name:token` pairs, comma separated, in `ADMIN_TOKENS`,
+/// and are presented either in the `X-Admin-Token` header or the cookie set by `/admin/login`.
+pub(crate) struct Admin {
+    pub(crate) name: String,
+}
+
+fn find_admin(token: &str) -> Option<String> {
+    let tokens = var("ADMIN_TOKENS").unwrap_or_default();
+    tokens.split(',').find_map(|pair| {
+        let (name, t) = pair.trim().split_once(':')?;
+        if !t.is_empty() && t == token {
+            Some(name.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = request
+            .headers()
+            .get_one("X-Admin-Token")
+            .map(|t| t.to_string())
+            .or_else(|| request.cookies().get(ADMIN_COOKIE).map(|c| c.value().to_string()));
+        match token.and_then(|t| find_admin(&t)) {
+            Some(name) => Outcome::Success(Admin { name }),
+            None => Outcome::Failure((Status::Unauthorized, ())),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Serialize)]
+struct AuditEntry {
+    actor: String,
+    action: String,
+    details: String,
+    created_at: String,
+}
+
+pub(crate) async fn record_audit(
+    ruleset_id: u32,
+    actor: &str,
+    action: &str,
+    details: &str,
+    pool: &SqlitePool,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO ruleset_audit_log (ruleset_id, actor, action, details, created_at) VALUES (?, ?, ?, ?, ?)")
+        .bind(ruleset_id)
+        .bind(actor)
+        .bind(action)
+        .bind(details)
+        .bind(Utc::now().to_rfc3339())
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Human readable list of what changed between two rulesets, e.g. `HeraPot: DISALLOWED -> ALLOWED`.
+fn describe_changes(before: &Ruleset, after: &Ruleset) -> Vec<String> {
+    diff_rulesets(before, after)
+        .into_iter()
+        .map(|c| format!("{}: {} -> {}", c.technique, c.before.as_str(), c.after.as_str()))
+        .collect()
+}
+
+fn login_page(error: Option<&str>) -> Template {
+    let mut context: HashMap<String, String> = Default::default();
+    context.insert("active_tab".to_string(), "admin".to_string());
+    if let Some(e) = error {
+        context.insert("error".to_string(), e.to_string());
+    }
+    Template::render("admin_login", context)
+}
+
+#[catch(401)]
+fn unauthorized() -> Template {
+    login_page(None)
+}
+
+#[get("/admin/login")]
+async fn login_form() -> Template {
+    login_page(None)
+}
+
+#[derive(FromForm)]
+struct LoginForm {
+    token: String,
+}
+
+#[post("/admin/login", data = "<form>")]
+async fn login(form: Form<LoginForm>, cookies: &CookieJar<'_>) -> Result<Redirect, Template> {
+    if find_admin(&form.token).is_none() {
+        return Err(login_page(Some("Unknown token")));
+    }
+    // the cookie is the token itself, so it's never sent over plain http (browsers make an
+    // exception for localhost)
+    cookies.add(
+        Cookie::build(ADMIN_COOKIE, form.token.clone())
+            .http_only(true)
+            .secure(true)
+            .same_site(SameSite::Strict)
+            .finish(),
+    );
+    Ok(Redirect::to("/admin"))
+}
+
+#[post("/admin/logout")]
+async fn logout(cookies: &CookieJar<'_>) -> Redirect {
+    cookies.remove(Cookie::named(ADMIN_COOKIE));
+    Redirect::to("/weekly")
+}
+
+#[get("/admin")]
+async fn admin_index(admin: Admin, pool: &State<SqlitePool>) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        admin: String,
+        rulesets: Vec<RulesetRecord>,
+    }
+
+    let rulesets = match sqlx::query_as(
+        &format!("SELECT {} FROM rulesets ORDER BY day DESC, id", RECORD_COLUMNS))
+        .fetch_all(&**pool)
+        .await {
+        Ok(o) => o,
+        Err(e) => {
+            println!("Error fetching rulesets: {:?}", e);
+            vec![]
+        }
+    };
+    Template::render(
+        "admin",
+        Ctx {
+            active_tab: "admin".to_string(),
+            admin: admin.name,
+            rulesets,
+        },
+    )
+}
+
+#[get("/admin/rulesets/<id>")]
+async fn admin_ruleset(id: u32, admin: Admin, pool: &State<SqlitePool>) -> Result<Template, NotFound<String>> {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        admin: String,
+        id: u32,
+        name: String,
+        default_seed: u64,
+        ruleset: HashMap<String, String>,
+        technique_names: &'static [&'static str],
+        options: Vec<&'static str>,
+        note: Option<String>,
+        superseded: bool,
+        overridden: bool,
+        audit_log: Vec<AuditEntry>,
+        submissions: Vec<ReviewEntry>,
+        route_notes: Vec<RenderedNote>,
+    }
+
+    let stored = get_stored_ruleset(id, pool).await.map_err(NotFound)?;
+    let (track, day) = track_and_day(&stored.record).map_err(NotFound)?;
+    let audit_log = sqlx::query_as(
+        "SELECT actor, action, details, created_at FROM ruleset_audit_log WHERE ruleset_id = ? ORDER BY id DESC")
+        .bind(id)
+        .fetch_all(&**pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching audit log: {:?}", e);
+            vec![]
+        });
+    let submissions = get_review_entries(id, pool).await;
+    let route_notes = get_moderation_entries(id, pool).await;
+    let current = stored.current().to_ruleset();
+    Ok(Template::render(
+        "admin_ruleset",
+        Ctx {
+            active_tab: "admin".to_string(),
+            admin: admin.name,
+            id,
+            name: stored.record.name.clone(),
+            default_seed: track.seed(&day),
+            ruleset: current.techniques().map(|(t, allowed)| (t.to_string(), allowed.as_str().to_string())).collect(),
+            technique_names: current.catalog().techniques,
+            options: IsAllowed::ALL.iter().map(|a| a.as_str()).collect(),
+            note: stored.record.note,
+            superseded: stored.record.superseded,
+            overridden: stored.overridden.is_some(),
+            audit_log,
+            submissions,
+            route_notes,
+        },
+    ))
+}
+
+/// The track a saved ruleset was published on, and the first day of its period.
+fn track_and_day(record: &RulesetRecord) -> Result<(Track, chrono::Date<Utc>), String> {
+    let game = find_game(&record.game).ok_or_else(|| format!("Unknown game {}", record.game))?;
+    let track = find_track(game, &record.track).ok_or_else(|| format!("Unknown track {}", record.track))?;
+    let naive = NaiveDate::from_num_days_from_ce(record.day);
+    Ok((track, Utc.from_utc_date(&naive)))
+}
+
+async fn apply_override(
+    id: u32,
+    admin: &Admin,
+    action: &str,
+    summary: Option<String>,
+    new_ruleset: Ruleset,
+    pool: &SqlitePool,
+) -> Result<Redirect, BadRequest<String>> {
+    let stored = get_stored_ruleset(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    let changes = describe_changes(&stored.current().to_ruleset(), &new_ruleset);
+    if changes.is_empty() && summary.is_none() {
+        return Ok(Redirect::to(format!("/admin/rulesets/{}", id)));
+    }
+    save_override(id, new_ruleset, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    let details = summary.into_iter().chain(changes).collect::<Vec<_>>().join("\n");
+    record_audit(id, &admin.name, action, &details, pool)
+        .await
+        .map_err(|e| BadRequest(Some(e)))?;
+    announce_ruleset(id, EVENT_OVERRIDDEN, pool).await;
+    Ok(Redirect::to(format!("/admin/rulesets/{}", id)))
+}
+
+#[derive(FromForm)]
+struct RerollForm {
+    seed: u64,
+}
+
+#[post("/admin/rulesets/<id>/reroll", data = "<form>")]
+async fn reroll(
+    id: u32,
+    form: Form<RerollForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    let stored = get_stored_ruleset(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    let (track, _) = track_and_day(&stored.record).map_err(|e| BadRequest(Some(e)))?;
+    let rolled = track.roll(form.seed).map_err(|e| BadRequest(Some(e.to_string())))?;
+    apply_override(
+        id,
+        &admin,
+        "reroll",
+        Some(format!("Re-rolled {} with seed {}", stored.record.name, form.seed)),
+        rolled,
+        pool,
+    )
+    .await
+}
+
+#[derive(FromForm)]
+struct EditForm {
+    values: HashMap<String, String>,
+}
+
+#[post("/admin/rulesets/<id>/edit", data = "<form>")]
+async fn edit(
+    id: u32,
+    form: Form<EditForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    let stored = get_stored_ruleset(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    let mut edited = stored.current().to_ruleset();
+    for (technique, value) in &form.values {
+        let allowed = value.parse::<IsAllowed>().map_err(|e| BadRequest(Some(e.to_string())))?;
+        if !edited.set(technique, allowed) {
+            return Err(BadRequest(Some(format!("Unknown technique {}", technique))));
+        }
+    }
+    apply_override(id, &admin, "edit", None, edited, pool).await
+}
+
+#[derive(FromForm)]
+struct NoteForm {
+    note: String,
+}
+
+#[post("/admin/rulesets/<id>/note", data = "<form>")]
+async fn note(
+    id: u32,
+    form: Form<NoteForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Option<Redirect>, BadRequest<String>> {
+    let note = form.note.trim();
+    let note = if note.is_empty() { None } else { Some(note.to_string()) };
+    let updated = sqlx::query("UPDATE rulesets SET note = ? WHERE id = ?")
+        .bind(&note)
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?
+        .rows_affected();
+    if updated == 0 {
+        return Ok(None);
+    }
+    let details = match note {
+        Some(n) => format!("Note set to: {}", n),
+        None => "Note cleared".to_string(),
+    };
+    record_audit(id, &admin.name, "note", &details, pool)
+        .await
+        .map_err(|e| BadRequest(Some(e)))?;
+    Ok(Some(Redirect::to(format!("/admin/rulesets/{}", id))))
+}
+
+#[derive(FromForm)]
+struct SupersededForm {
+    superseded: bool,
+}
+
+#[post("/admin/rulesets/<id>/superseded", data = "<form>")]
+async fn superseded(
+    id: u32,
+    form: Form<SupersededForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Option<Redirect>, BadRequest<String>> {
+    let updated = sqlx::query("UPDATE rulesets SET superseded = ? WHERE id = ?")
+        .bind(form.superseded)
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?
+        .rows_affected();
+    if updated == 0 {
+        return Ok(None);
+    }
+    let details = if form.superseded {
+        "Marked superseded"
+    } else {
+        "Unmarked superseded"
+    };
+    record_audit(id, &admin.name, "superseded", details, pool)
+        .await
+        .map_err(|e| BadRequest(Some(e)))?;
+    Ok(Some(Redirect::to(format!("/admin/rulesets/{}", id))))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket
+        .mount(
+            "/",
+            rocket::routes![
+                login_form,
+                login,
+                logout,
+                admin_index,
+                admin_ruleset,
+                reroll,
+                edit,
+                note,
+                superseded
+            ],
+        )
+        .register("/", rocket::catchers![unauthorized])
+}
@@ -0,0 +1,141 @@
+//! Route analytics page, built on top of `crate::analytics`. Only covers the default game, whose
+//! weekly weights it shows alongside.
+
+use crate::analytics::{technique_stats, RunRecord, TechniqueStats};
+use crate::games::{default_game, Game};
+use crate::rules::weekly_template;
+use crate::techniques::Ruleset;
+use crate::web::submissions::{format_time, STATUS_VERIFIED};
+use crate::web::weekly::get_stored_ruleset;
+use rocket::{get, Build, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Every saved ruleset for `game` as it currently stands (overrides applied), keyed by id.
+pub(crate) async fn get_all_rulesets(game: &Game, pool: &SqlitePool) -> Result<HashMap<u32, Ruleset>, sqlx::Error> {
+    let ids: Vec<(u32,)> = sqlx::query_as("SELECT id FROM rulesets WHERE game = ?")
+        .bind(game.slug)
+        .fetch_all(pool)
+        .await?;
+    let mut rulesets = HashMap::new();
+    for (id,) in ids {
+        match get_stored_ruleset(id, pool).await {
+            Ok(stored) => {
+                rulesets.insert(id, stored.current().to_ruleset());
+            }
+            Err(e) => println!("Skipping ruleset {}: {}", id, e),
+        }
+    }
+    Ok(rulesets)
+}
+
+async fn get_runs(goal: &str, pool: &SqlitePool) -> Result<Vec<RunRecord>, sqlx::Error> {
+    let submissions: Vec<(i64, u32, u32)> = sqlx::query_as(
+        "SELECT id, ruleset_id, time_seconds FROM submissions WHERE status = ? AND goal = ?")
+        .bind(STATUS_VERIFIED)
+        .bind(goal)
+        .fetch_all(pool)
+        .await?;
+    let declared: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT st.submission_id, st.technique FROM submission_techniques st \
+         JOIN submissions s ON s.id = st.submission_id WHERE s.status = ? AND s.goal = ?")
+        .bind(STATUS_VERIFIED)
+        .bind(goal)
+        .fetch_all(pool)
+        .await?;
+    let mut used: HashMap<i64, Vec<String>> = HashMap::new();
+    for (id, technique) in declared {
+        used.entry(id).or_default().push(technique);
+    }
+    Ok(submissions
+        .into_iter()
+        .map(|(id, ruleset_id, time_seconds)| RunRecord {
+            ruleset_id,
+            time_seconds,
+            used: used.remove(&id).unwrap_or_default(),
+        })
+        .collect())
+}
+
+#[derive(Serialize)]
+struct StatsRow {
+    technique: String,
+    weekly_weight: String,
+    weeks_legal: String,
+    usage: String,
+    mean_time_legal: String,
+    mean_time_banned: String,
+    correlation: String,
+}
+
+impl From<TechniqueStats> for StatsRow {
+    fn from(s: TechniqueStats) -> Self {
+        let time = |t: Option<f64>| t.map_or("-".to_string(), |t| format_time(t.round() as u32));
+        Self {
+            weekly_weight: weekly_template().get(&s.technique).describe(),
+            weeks_legal: format!("{} / {}", s.weeks_legal, s.weeks),
+            usage: s.usage_rate.map_or("-".to_string(), |r| {
+                format!("{:.0}% ({} / {})", r * 100.0, s.uses, s.runs_legal)
+            }),
+            mean_time_legal: time(s.mean_time_legal),
+            mean_time_banned: time(s.mean_time_banned),
+            correlation: s.correlation.map_or("-".to_string(), |c| format!("{:+.2}", c)),
+            technique: s.technique,
+        }
+    }
+}
+
+#[get("/analytics?<goal>")]
+async fn analytics(goal: Option<String>, pool: &State<SqlitePool>) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        goals: Vec<(String, u32)>,
+        goal: Option<String>,
+        runs: usize,
+        stats: Vec<StatsRow>,
+    }
+
+    let goals: Vec<(String, u32)> = sqlx::query_as(
+        "SELECT goal, COUNT(*) FROM submissions WHERE status = ? GROUP BY goal ORDER BY COUNT(*) DESC, goal")
+        .bind(STATUS_VERIFIED)
+        .fetch_all(&**pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching goals: {:?}", e);
+            vec![]
+        });
+    let goal = goal.or_else(|| goals.first().map(|g| g.0.clone()));
+
+    let (runs, stats) = match &goal {
+        Some(goal) => {
+            let rulesets = get_all_rulesets(default_game(), pool).await.unwrap_or_else(|e| {
+                println!("Error fetching rulesets: {:?}", e);
+                HashMap::new()
+            });
+            let runs = get_runs(goal, pool).await.unwrap_or_else(|e| {
+                println!("Error fetching runs: {:?}", e);
+                vec![]
+            });
+            (runs.len(), technique_stats(default_game().catalog, &rulesets, &runs))
+        }
+        None => (0, vec![]),
+    };
+
+    Template::render(
+        "analytics",
+        Ctx {
+            active_tab: "analytics".to_string(),
+            goals,
+            goal,
+            runs,
+            stats: stats.into_iter().map(StatsRow::from).collect(),
+        },
+    )
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![analytics])
+}
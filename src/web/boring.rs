@@ -6,8 +6,6 @@ use rocket::{Request, get, Rocket, Build};
 use std::path::{Path, PathBuf};
 use rocket_dyn_templates::Template;
 use std::collections::HashMap;
-use serde::Serialize;
-use crate::rules::IsAllowed;
 
 const STATIC_SUFFIXES: [&str; 8] = [
     &"js", &"css", &"png", &"mp3", &"html", &"jpg", &"ttf", &"otf",
@@ -58,43 +56,9 @@ async fn about() -> Template {
     Template::render("about", context)
 }
 
-#[derive(Serialize)]
-struct SupplementalContext {
-    active_tab: String,
-    rules: Vec<(String, IsAllowed)>,
-}
-
-#[get("/supplemental")]
-async fn supplemental() -> Template {
-    let rules = vec![
-        ("Spooky Action".to_string(), IsAllowed::ALLOWED),
-        ("Torch Glitch".to_string(), IsAllowed::ALLOWED),
-        ("Block Clips".to_string(), IsAllowed::ALLOWED),
-        ("Big Bomb Dupe".to_string(), IsAllowed::ALLOWED),
-        ("Water Walk".to_string(), IsAllowed::ALLOWED),
-        ("Houlihan".to_string(), IsAllowed::ALLOWED),
-        ("Medallion Cancel".to_string(), IsAllowed::ALLOWED),
-        ("Super Bunny".to_string(), IsAllowed::ALLOWED),
-        ("Dungeon Revival".to_string(), IsAllowed::ALLOWED),
-        ("Surfing Bunny".to_string(), IsAllowed::ALLOWED),
-        ("Bunny Pocket".to_string(), IsAllowed::ALLOWED),
-        ("UnBunnyBeam".to_string(), IsAllowed::ALLOWED),
-        (
-            "Arbitrary Code Execution".to_string(),
-            IsAllowed::DISALLOWED,
-        ),
-    ];
-    let c = SupplementalContext {
-        active_tab: "supplemental".to_string(),
-        rules,
-    };
-    Template::render("supplemental", c)
-}
-
-
 pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
     rocket
-        .mount("/", rocket::routes![favicon, about, supplemental])
+        .mount("/", rocket::routes![favicon, about])
         .mount("/static", rocket::routes![statics])
 }
 
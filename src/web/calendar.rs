@@ -0,0 +1,109 @@
+//! iCalendar feed of the weekly rotation, so the schedule can live in people's calendars. It has
+//! every track of every game, or just one game's or track's with `?game=` and `?track=`.
+
+use crate::games::games;
+use crate::ics::{render_calendar, IcsEvent};
+use crate::rules::most_recent_sunday;
+use crate::tracks::{tracks, Track};
+use crate::web::feeds::ruleset_summary;
+use crate::web::public_url;
+use crate::web::weekly::{game_or_default, get_stored_ruleset, track_or_weekly, weekly_name};
+use chrono::{Date, Datelike, Duration, Utc};
+use rocket::http::ContentType;
+use rocket::response::status::NotFound;
+use rocket::{get, Build, Rocket, State};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// How far back and ahead of the current period the calendar reaches, in periods.
+const PAST_PERIODS: i64 = 52;
+const UPCOMING_PERIODS: i64 = 8;
+
+/// The ids of the rulesets saved for periods starting from `first` to `last`, by game, track and
+/// day. Periods nobody visited were never rolled, so most upcoming ones aren't here.
+async fn saved_ids(first: &Date<Utc>, last: &Date<Utc>, pool: &SqlitePool) -> HashMap<(String, String, i32), u32> {
+    let rows: Vec<(u32, String, String, i32)> =
+        sqlx::query_as("SELECT id, game, track, day FROM rulesets WHERE day >= ? AND day <= ?")
+            .bind(first.num_days_from_ce())
+            .bind(last.num_days_from_ce())
+            .fetch_all(pool)
+            .await
+            .unwrap_or_else(|e| {
+                println!("Error fetching rulesets: {:?}", e);
+                vec![]
+            });
+    rows.into_iter().map(|(id, game, track, day)| ((game, track, day), id)).collect()
+}
+
+/// The uid of `track`'s event for the period starting on `start`. The built-in track's are as they
+/// were before there were other tracks, so subscribed calendars don't get duplicates.
+fn event_uid(track: &Track, start: &Date<Utc>, host: &str) -> String {
+    let day = start.num_days_from_ce();
+    if track.is_default() {
+        format!("weekly-{}@{}", day, host)
+    } else {
+        format!("weekly-{}-{}-{}@{}", track.game, track.slug, day, host)
+    }
+}
+
+#[get("/calendar.ics?<game>&<track>")]
+async fn calendar(
+    game: Option<&str>,
+    track: Option<&str>,
+    pool: &State<SqlitePool>,
+) -> Result<(ContentType, String), NotFound<String>> {
+    let schedules: Vec<Track> = match (game, track) {
+        (None, None) => games().iter().flat_map(tracks).collect(),
+        (game, None) => tracks(game_or_default(game)?),
+        (game, Some(track)) => vec![track_or_weekly(game_or_default(game)?, Some(track))?],
+    };
+    let title = match (game, track) {
+        (None, None) => "Ruleset Randomizer weeklies".to_string(),
+        (game, None) => format!("Ruleset Randomizer {} weeklies", game_or_default(game)?.name),
+        (_, Some(_)) => format!("Ruleset Randomizer {} weekly", schedules[0].name),
+    };
+
+    let now = Utc::now();
+    let current = most_recent_sunday(now.date());
+    let first = current - Duration::weeks(PAST_PERIODS);
+    let last = current + Duration::weeks(UPCOMING_PERIODS);
+    let saved = saved_ids(&first, &last, pool).await;
+    let base = public_url();
+    let host = base.split("://").nth(1).unwrap_or(&base).to_string();
+
+    let mut events = vec![];
+    for track in &schedules {
+        for offset in -PAST_PERIODS..=UPCOMING_PERIODS {
+            let start = current + Duration::weeks(offset);
+            let key = (track.game.to_string(), track.slug.clone(), start.num_days_from_ce());
+            // only saved periods get a description
+            let (description, url) = match saved.get(&key) {
+                Some(id) => match get_stored_ruleset(*id, pool).await {
+                    Ok(stored) => {
+                        let link = format!("{}/history/{}", base, id);
+                        let summary = ruleset_summary(&stored.current().to_ruleset(), &stored.record.note);
+                        (Some(format!("{}\n{}", summary, link)), Some(link))
+                    }
+                    Err(e) => {
+                        println!("Leaving ruleset {} out of the calendar: {}", id, e);
+                        (None, None)
+                    }
+                },
+                None => (None, None),
+            };
+            events.push(IcsEvent {
+                uid: event_uid(track, &start, &host),
+                start: start.and_hms(0, 0, 0),
+                end: (start + Duration::weeks(1)).and_hms(0, 0, 0),
+                summary: format!("Weekly ruleset: {}", weekly_name(track, &start)),
+                description,
+                url,
+            });
+        }
+    }
+    Ok((ContentType::Calendar, render_calendar(&title, &events, now)))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![calendar])
+}
@@ -0,0 +1,38 @@
+//! Rules card images for saved rulesets; rendering lives in `crate::card`.
+
+use crate::card::{render_card_png, render_card_svg};
+use crate::export::week_title;
+use crate::web::weekly::get_stored_ruleset;
+use rocket::http::ContentType;
+use rocket::response::status::NotFound;
+use rocket::{get, Build, Rocket, State};
+use sqlx::SqlitePool;
+
+async fn card_svg(id: u32, pool: &SqlitePool) -> Result<String, NotFound<String>> {
+    let stored = get_stored_ruleset(id, pool).await.map_err(NotFound)?;
+    Ok(render_card_svg(&week_title(&stored.record.name), &stored.current().to_ruleset()))
+}
+
+#[get("/history/<id>/card.svg")]
+async fn svg_card(id: u32, pool: &State<SqlitePool>) -> Result<(ContentType, String), NotFound<String>> {
+    Ok((ContentType::SVG, card_svg(id, pool).await?))
+}
+
+#[get("/history/<id>/card.png")]
+async fn png_card(id: u32, pool: &State<SqlitePool>) -> Result<(ContentType, Vec<u8>), NotFound<String>> {
+    let svg = card_svg(id, pool).await?;
+    // rasterizing takes long enough that it shouldn't hold up the async workers
+    let png = rocket::tokio::task::spawn_blocking(move || render_card_png(&svg))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map_err(|e| {
+            println!("Error rendering card for {}: {}", id, e);
+            NotFound(format!("Couldn't render a card for {}", id))
+        })?;
+    Ok((ContentType::PNG, png))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![svg_card, png_card])
+}
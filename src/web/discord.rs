@@ -0,0 +1,53 @@
+//! API for Discord-ready announcements of a saved ruleset. See `crate::discord` for the format.
+
+use crate::discord::{render_embeds, render_markdown};
+use crate::web::webhooks::ruleset_payload;
+use crate::web::weekly::{game_or_default, materialize_track_weekly, materialize_weekly};
+use crate::webhooks::EVENT_PUBLISHED;
+use rocket::response::status::NotFound;
+use rocket::serde::json::Json;
+use rocket::{get, Build, Rocket, State};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+/// `messages` are plain markdown messages; `embeds` are complete webhook bodies. Either list can
+/// be posted in order as-is.
+#[derive(Serialize)]
+pub(crate) struct DiscordAnnouncement {
+    pub(crate) messages: Vec<String>,
+    pub(crate) embeds: Vec<Value>,
+}
+
+pub(crate) async fn discord_announcement(id: u32, pool: &SqlitePool) -> Result<DiscordAnnouncement, String> {
+    let payload = ruleset_payload(id, EVENT_PUBLISHED, pool).await?;
+    Ok(DiscordAnnouncement {
+        messages: render_markdown(&payload),
+        embeds: render_embeds(&payload),
+    })
+}
+
+#[get("/api/rulesets/<id>/discord")]
+async fn ruleset_discord(id: u32, pool: &State<SqlitePool>) -> Result<Json<DiscordAnnouncement>, NotFound<String>> {
+    discord_announcement(id, pool).await.map(Json).map_err(NotFound)
+}
+
+#[get("/api/weekly/discord")]
+async fn weekly_discord(pool: &State<SqlitePool>) -> Result<Json<DiscordAnnouncement>, NotFound<String>> {
+    let id = materialize_weekly(pool).await.map_err(NotFound)?;
+    discord_announcement(id, pool).await.map(Json).map_err(NotFound)
+}
+
+#[get("/api/weekly/<track>/discord?<game>")]
+async fn track_weekly_discord(
+    track: &str,
+    game: Option<&str>,
+    pool: &State<SqlitePool>,
+) -> Result<Json<DiscordAnnouncement>, NotFound<String>> {
+    let id = materialize_track_weekly(game_or_default(game)?, track, pool).await.map_err(NotFound)?;
+    discord_announcement(id, pool).await.map(Json).map_err(NotFound)
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![ruleset_discord, weekly_discord, track_weekly_discord])
+}
@@ -0,0 +1,94 @@
+//! Content negotiation for ruleset pages: `?format=` or the `Accept` header picks an export
+//! instead of the HTML page. Rendering lives in `crate::export`.
+
+use crate::export::{export_ruleset, week_title, ExportFormat};
+use crate::techniques::Ruleset;
+use rocket::http::{ContentType, Header, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, Responder, Response};
+use rocket::Request;
+use rocket_dyn_templates::Template;
+use std::io::Cursor;
+
+/// The export a request asked for, if any. `?format=` wins over `Accept`; an `Accept` whose
+/// preferred type isn't one we export to (browsers, `*/*`) gets the normal page.
+pub(crate) struct Export(pub(crate) Option<ExportFormat>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Export {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if let Some(format) = request.query_value::<&str>("format") {
+            return match format.map_err(|e| e.to_string()).and_then(|f| f.parse().map_err(|e| format!("{}", e))) {
+                Ok(f) => Outcome::Success(Export(Some(f))),
+                Err(e) => Outcome::Failure((Status::BadRequest, e)),
+            };
+        }
+        let format = request.accept().and_then(|accept| {
+            let preferred = accept.preferred().media_type();
+            ExportFormat::from_media_type(preferred.top().as_str(), preferred.sub().as_str())
+        });
+        Outcome::Success(Export(format))
+    }
+}
+
+/// An exported ruleset, served inline with a filename for when it's saved.
+pub(crate) struct Exported {
+    format: ExportFormat,
+    filename: String,
+    body: String,
+}
+
+impl Exported {
+    /// The export of the weekly ruleset `id`, published as `name`.
+    pub(crate) fn new(format: ExportFormat, id: u32, name: &str, ruleset: &Ruleset, note: Option<&str>) -> Self {
+        Self {
+            format,
+            filename: format!("ruleset-{}.{}", id, format.as_str()),
+            body: export_ruleset(format, &week_title(name), name, ruleset, note),
+        }
+    }
+
+    /// An export of a ruleset that isn't a weekly, titled and named `name`, whose filename is built
+    /// from `key`.
+    pub(crate) fn named(format: ExportFormat, key: &str, name: &str, ruleset: &Ruleset, note: Option<&str>) -> Self {
+        Self {
+            format,
+            filename: format!("ruleset-{}.{}", key, format.as_str()),
+            body: export_ruleset(format, name, name, ruleset, note),
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for Exported {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let (top, sub) = self.format.media_type();
+        Response::build()
+            .header(ContentType::with_params(top, sub, ("charset", "utf-8")))
+            .header(Header::new(
+                "Content-Disposition",
+                format!("inline; filename=\"{}\"", self.filename),
+            ))
+            .sized_body(self.body.len(), Cursor::new(self.body))
+            .ok()
+    }
+}
+
+/// A ruleset page, or its export. Which one depends on `Accept`, so both say so in `Vary`, and a
+/// cache won't hand one to a client that asked for the other.
+pub(crate) enum Negotiated {
+    Page(Template),
+    Export(Exported),
+}
+
+impl<'r> Responder<'r, 'static> for Negotiated {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = match self {
+            Negotiated::Page(page) => page.respond_to(request)?,
+            Negotiated::Export(export) => export.respond_to(request)?,
+        };
+        response.set_raw_header("Vary", "Accept");
+        Ok(response)
+    }
+}
@@ -0,0 +1,156 @@
+//! Atom and RSS feeds of every published ruleset, newest first. They're complete feeds (RFC 5005),
+//! so a reader subscribing late still sees every week.
+
+use crate::games::Game;
+use crate::rules::{diff_rulesets, IsAllowed};
+use crate::techniques::Ruleset;
+use crate::web::public_url;
+use crate::web::weekly::{get_stored_ruleset, RulesetRecord, RECORD_COLUMNS};
+use chrono::{DateTime, NaiveDate, Utc};
+use rocket::http::ContentType;
+use rocket::{get, Build, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// Techniques this ruleset allows that its game's baseline (NMG for ALttP) doesn't, which is what
+/// makes a week interesting.
+fn notable_techniques(ruleset: &Ruleset) -> Vec<String> {
+    diff_rulesets(Game::of(ruleset).baseline(), ruleset)
+        .into_iter()
+        .filter(|c| c.after == IsAllowed::ALLOWED)
+        .map(|c| c.technique)
+        .collect()
+}
+
+/// One-line description of a ruleset for feeds and calendars.
+pub(crate) fn ruleset_summary(ruleset: &Ruleset, note: &Option<String>) -> String {
+    let notable = notable_techniques(ruleset);
+    let mut summary = if notable.is_empty() {
+        format!("Nothing beyond the usual {} techniques is allowed.", Game::of(ruleset).baseline().name)
+    } else {
+        format!("Notable legal techniques: {}.", notable.join(", "))
+    };
+    if let Some(note) = note {
+        summary.push(' ');
+        summary.push_str(note);
+    }
+    summary
+}
+
+#[derive(Serialize)]
+struct FeedEntry {
+    id: u32,
+    title: String,
+    link: String,
+    summary: String,
+    /// RFC 3339, for Atom
+    updated: String,
+    /// RFC 2822, for RSS
+    pub_date: String,
+}
+
+#[derive(Serialize)]
+struct FeedContext {
+    title: String,
+    site_link: String,
+    self_link: String,
+    updated: String,
+    entries: Vec<FeedEntry>,
+}
+
+fn published_at(day: i32) -> DateTime<Utc> {
+    DateTime::from_utc(NaiveDate::from_num_days_from_ce(day).and_hms(0, 0, 0), Utc)
+}
+
+async fn feed_context(self_path: &str, pool: &SqlitePool) -> FeedContext {
+    let records: Vec<RulesetRecord> =
+        sqlx::query_as(&format!("SELECT {} FROM rulesets ORDER BY day DESC, id", RECORD_COLUMNS))
+            .fetch_all(pool)
+            .await
+            .unwrap_or_else(|e| {
+                println!("Error fetching rulesets: {:?}", e);
+                vec![]
+            });
+    let base = public_url();
+    let mut entries = vec![];
+    for record in records {
+        let stored = match get_stored_ruleset(record.id, pool).await {
+            Ok(s) => s,
+            Err(e) => {
+                println!("Skipping ruleset {} in feed: {}", record.id, e);
+                continue;
+            }
+        };
+        let published = published_at(record.day);
+        entries.push(FeedEntry {
+            id: record.id,
+            title: if record.superseded {
+                format!("{} (superseded)", record.name)
+            } else {
+                record.name.clone()
+            },
+            link: format!("{}/history/{}", base, record.id),
+            summary: ruleset_summary(&stored.current().to_ruleset(), &record.note),
+            updated: published.to_rfc3339(),
+            pub_date: published.to_rfc2822(),
+        });
+    }
+    FeedContext {
+        title: "Ruleset Randomizer weeklies".to_string(),
+        self_link: format!("{}{}", base, self_path),
+        updated: entries
+            .first()
+            .map_or_else(|| published_at(0).to_rfc3339(), |e| e.updated.clone()),
+        site_link: base,
+        entries,
+    }
+}
+
+#[get("/feed.atom")]
+async fn atom(pool: &State<SqlitePool>) -> (ContentType, Template) {
+    (
+        ContentType::new("application", "atom+xml"),
+        Template::render("feed_atom", feed_context("/feed.atom", pool).await),
+    )
+}
+
+#[get("/feed.rss")]
+async fn rss(pool: &State<SqlitePool>) -> (ContentType, Template) {
+    (
+        ContentType::new("application", "rss+xml"),
+        Template::render("feed_rss", feed_context("/feed.rss", pool).await),
+    )
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![atom, rss])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{notable_techniques, ruleset_summary};
+    use crate::games::find_game;
+    use crate::rules::{IsAllowed, NMGRules};
+
+    #[test]
+    fn test_notable_techniques() {
+        let mut r = NMGRules.clone();
+        assert!(notable_techniques(&r).is_empty());
+        r["HeraPot"] = IsAllowed::ALLOWED;
+        r["OverworldClipping"] = IsAllowed::ALLOWED;
+        // banning something NMG allows isn't notable
+        r["Hover"] = IsAllowed::DISALLOWED;
+        assert_eq!(vec!["HeraPot", "OverworldClipping"], notable_techniques(&r));
+    }
+
+    #[test]
+    fn test_ruleset_summary() {
+        assert_eq!("Nothing beyond the usual NMG techniques is allowed.", ruleset_summary(&NMGRules, &None));
+        let sm = find_game("sm").unwrap().baseline();
+        assert_eq!(
+            "Nothing beyond the usual No Major Glitches techniques is allowed. Short week.",
+            ruleset_summary(sm, &Some("Short week.".to_string()))
+        );
+    }
+}
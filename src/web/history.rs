@@ -0,0 +1,586 @@
+//! Past rulesets: `/history` and its API, paged and searchable by date and by technique ("weeks
+//! where OverworldClipping was ALLOWED and SaveAndQuit was DISALLOWED"). Searching by technique
+//! uses `ruleset_techniques`, which keeps each saved ruleset's values as in force.
+
+use crate::games::default_game;
+use crate::rules::IsAllowed;
+use crate::techniques::{Catalog, Ruleset};
+use crate::tracks::Track;
+use crate::web::weekly::{
+    game_or_default, game_tabs, get_stored_ruleset, track_or_weekly, track_tabs, RulesetRecord, TrackTab,
+    RECORD_COLUMNS,
+};
+use chrono::{Datelike, NaiveDate};
+use rocket::form::FromForm;
+use rocket::response::status::{BadRequest, NotFound};
+use rocket::serde::json::Json;
+use rocket::{get, Build, Either, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::query::QueryAs;
+use sqlx::sqlite::{Sqlite, SqliteArguments};
+use sqlx::SqlitePool;
+
+const PAGE_SIZE: u32 = 25;
+const MAX_PAGE_SIZE: u32 = 200;
+
+/// Store the values of the saved ruleset `id`, replacing any stored before.
+pub(crate) async fn index_ruleset(id: u32, values: &[(String, IsAllowed)], pool: &SqlitePool) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM ruleset_techniques WHERE ruleset_id = ?")
+        .bind(id)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    for (technique, allowed) in values {
+        sqlx::query("INSERT INTO ruleset_techniques (ruleset_id, technique, allowed) VALUES (?, ?, ?)")
+            .bind(id)
+            .bind(technique)
+            .bind(allowed.as_str())
+            .execute(&mut tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// `ruleset`'s values, for `index_ruleset`.
+pub(crate) fn ruleset_values(ruleset: &Ruleset) -> Vec<(String, IsAllowed)> {
+    ruleset.techniques().map(|(t, allowed)| (t.to_string(), allowed)).collect()
+}
+
+/// Store the values of every saved ruleset that doesn't have them yet, i.e. those saved before
+/// `ruleset_techniques` existed. Only the techniques in its file are stored.
+pub(crate) async fn index_saved_rulesets(pool: &SqlitePool) -> Result<(), String> {
+    let ids: Vec<(u32,)> = sqlx::query_as(
+        "SELECT id FROM rulesets WHERE id NOT IN (SELECT DISTINCT ruleset_id FROM ruleset_techniques) ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    for (id,) in &ids {
+        let stored = match get_stored_ruleset(*id, pool).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                println!("Error reading ruleset {} to index it: {}", id, e);
+                continue;
+            }
+        };
+        let values: Vec<(String, IsAllowed)> = stored
+            .current()
+            .ruleset
+            .iter()
+            .filter_map(|(t, v)| v.parse().ok().map(|allowed| (t.clone(), allowed)))
+            .collect();
+        index_ruleset(*id, &values, pool).await?;
+    }
+    if !ids.is_empty() {
+        println!("Indexed the techniques of {} saved rulesets", ids.len());
+    }
+    Ok(())
+}
+
+/// A saved ruleset as stored in `ruleset_techniques`.
+pub(crate) struct IndexedRuleset {
+    pub(crate) id: u32,
+    pub(crate) day: i32,
+    pub(crate) name: String,
+    pub(crate) ruleset: Ruleset,
+}
+
+/// `game`'s saved rulesets that aren't superseded, on every track or just `track`, oldest first.
+/// Techniques not stored for a ruleset are as in a new one.
+pub(crate) async fn indexed_rulesets(game: &str, track: Option<&str>, pool: &SqlitePool) -> Vec<IndexedRuleset> {
+    fetch_indexed_rulesets(game, track, None, None, pool).await
+}
+
+/// `indexed_rulesets`, but only the `limit` latest from before `before_day`.
+pub(crate) async fn recent_indexed_rulesets(
+    game: &str,
+    track: Option<&str>,
+    before_day: i32,
+    limit: u32,
+    pool: &SqlitePool,
+) -> Vec<IndexedRuleset> {
+    fetch_indexed_rulesets(game, track, Some(before_day), Some(limit), pool).await
+}
+
+async fn fetch_indexed_rulesets(
+    game: &str,
+    track: Option<&str>,
+    before_day: Option<i32>,
+    limit: Option<u32>,
+    pool: &SqlitePool,
+) -> Vec<IndexedRuleset> {
+    // a negative LIMIT is no limit
+    let rows: Vec<(u32, i32, String, String, String)> = sqlx::query_as(
+        "SELECT r.id, r.day, r.name, t.technique, t.allowed FROM rulesets r \
+         JOIN ruleset_techniques t ON t.ruleset_id = r.id \
+         WHERE r.id IN (SELECT id FROM rulesets WHERE game = ? AND (? IS NULL OR track = ?) AND superseded = 0 \
+         AND (? IS NULL OR day < ?) ORDER BY day DESC, id DESC LIMIT ?) \
+         ORDER BY r.day, r.id")
+        .bind(game)
+        .bind(track)
+        .bind(track)
+        .bind(before_day)
+        .bind(before_day)
+        .bind(limit.map_or(-1, i64::from))
+        .fetch_all(pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching stored rulesets: {:?}", e);
+            vec![]
+        });
+    let mut rulesets: Vec<IndexedRuleset> = vec![];
+    for (id, day, name, technique, allowed) in rows {
+        if rulesets.last().is_none_or(|r| r.id != id) {
+            rulesets.push(IndexedRuleset {
+                id,
+                day,
+                ruleset: Ruleset::unspecified(game, name.clone()),
+                name,
+            });
+        }
+        if let (Some(r), Ok(allowed)) = (rulesets.last_mut(), allowed.parse::<IsAllowed>()) {
+            r.ruleset.set(&technique, allowed);
+        }
+    }
+    rulesets
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Sort {
+    Newest,
+    Oldest,
+    MostAllowed,
+    FewestAllowed,
+}
+
+/// Each sort as it's written in urls, and described on the page.
+const SORTS: [(Sort, &str, &str); 4] = [
+    (Sort::Newest, "newest", "Newest first"),
+    (Sort::Oldest, "oldest", "Oldest first"),
+    (Sort::MostAllowed, "most-allowed", "Most techniques allowed"),
+    (Sort::FewestAllowed, "fewest-allowed", "Fewest techniques allowed"),
+];
+
+const ALLOWED_COUNT: &str =
+    "(SELECT COUNT(*) FROM ruleset_techniques t WHERE t.ruleset_id = r.id AND t.allowed = 'ALLOWED')";
+
+impl Sort {
+    fn slug(self) -> &'static str {
+        SORTS.iter().find(|(s, _, _)| *s == self).map(|(_, slug, _)| *slug).unwrap()
+    }
+
+    fn order_by(self) -> String {
+        match self {
+            Sort::Newest => "r.day DESC, r.id DESC".to_string(),
+            Sort::Oldest => "r.day, r.id".to_string(),
+            Sort::MostAllowed => format!("{} DESC, r.day DESC", ALLOWED_COUNT),
+            Sort::FewestAllowed => format!("{}, r.day DESC", ALLOWED_COUNT),
+        }
+    }
+}
+
+/// The query string of `/history` and its API. Techniques are filtered on by listing them as
+/// `allowed` or `disallowed`, as many times as needed; dates are the first day of a period, as
+/// `2022-04-03`, and both ends are included.
+#[derive(FromForm, Default)]
+pub(crate) struct HistoryFilter {
+    page: Option<u32>,
+    per_page: Option<u32>,
+    sort: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    allowed: Vec<String>,
+    disallowed: Vec<String>,
+}
+
+/// A `HistoryFilter` checked against a game's catalog.
+#[derive(PartialEq, Debug)]
+struct Search {
+    from: Option<i32>,
+    to: Option<i32>,
+    techniques: Vec<(&'static str, IsAllowed)>,
+    sort: Sort,
+    page: u32,
+    /// `None` for everything on one page.
+    per_page: Option<u32>,
+}
+
+enum Arg {
+    Int(i64),
+    Text(String),
+}
+
+fn parse_date(date: &Option<String>) -> Result<Option<i32>, String> {
+    match date.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d")
+            .map(|d| Some(d.num_days_from_ce()))
+            .map_err(|_| format!("Expected a date like 2022-04-03, got {}", d)),
+    }
+}
+
+fn date_string(day: i32) -> String {
+    NaiveDate::from_num_days_from_ce(day).format("%Y-%m-%d").to_string()
+}
+
+impl HistoryFilter {
+    /// Check the filter against `catalog`. Without a page, `paged` decides whether there's one
+    /// page of everything.
+    fn search(&self, catalog: &Catalog, default_sort: Sort, paged: bool) -> Result<Search, String> {
+        let sort = match self.sort.as_deref().map(str::trim) {
+            None | Some("") => default_sort,
+            Some(s) => SORTS
+                .iter()
+                .find(|(_, slug, _)| *slug == s)
+                .map(|(sort, _, _)| *sort)
+                .ok_or_else(|| format!("Unknown sort {}", s))?,
+        };
+        let (from, to) = (parse_date(&self.from)?, parse_date(&self.to)?);
+        let mut techniques = vec![];
+        for (names, allowed) in [(&self.allowed, IsAllowed::ALLOWED), (&self.disallowed, IsAllowed::DISALLOWED)] {
+            for name in names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+                let technique = catalog
+                    .id(name)
+                    .map(|id| catalog.techniques[id])
+                    .ok_or_else(|| format!("Unknown technique {}", name))?;
+                if techniques.iter().any(|(t, _)| *t == technique) {
+                    return Err(format!("{} is filtered on more than once", technique));
+                }
+                techniques.push((technique, allowed));
+            }
+        }
+        let per_page = match (self.per_page, paged || self.page.is_some()) {
+            (Some(n), _) if n == 0 || n > MAX_PAGE_SIZE => {
+                return Err(format!("A page has from 1 to {} rulesets", MAX_PAGE_SIZE))
+            }
+            (Some(n), _) => Some(n),
+            (None, true) => Some(PAGE_SIZE),
+            (None, false) => None,
+        };
+        Ok(Search {
+            from,
+            to,
+            techniques,
+            sort,
+            page: self.page.unwrap_or(1).max(1),
+            per_page,
+        })
+    }
+}
+
+impl Search {
+    /// The conditions on `rulesets r` for `track`, and what to bind to them.
+    fn conditions(&self, track: &Track) -> (String, Vec<Arg>) {
+        let mut conditions = vec!["r.game = ? AND r.track = ?".to_string()];
+        let mut args = vec![Arg::Text(track.game.to_string()), Arg::Text(track.slug.clone())];
+        if let Some(from) = self.from {
+            conditions.push("r.day >= ?".to_string());
+            args.push(Arg::Int(from as i64));
+        }
+        if let Some(to) = self.to {
+            conditions.push("r.day <= ?".to_string());
+            args.push(Arg::Int(to as i64));
+        }
+        for (technique, allowed) in &self.techniques {
+            conditions.push(
+                "r.id IN (SELECT ruleset_id FROM ruleset_techniques WHERE technique = ? AND allowed = ?)".to_string(),
+            );
+            args.push(Arg::Text(technique.to_string()));
+            args.push(Arg::Text(allowed.as_str().to_string()));
+        }
+        (conditions.join(" AND "), args)
+    }
+
+    /// The query string for `page` of these results.
+    fn query_string(&self, page: u32) -> String {
+        let mut query = format!("sort={}", self.sort.slug());
+        if let Some(from) = self.from {
+            query.push_str(&format!("&from={}", date_string(from)));
+        }
+        if let Some(to) = self.to {
+            query.push_str(&format!("&to={}", date_string(to)));
+        }
+        for (technique, allowed) in &self.techniques {
+            let key = if *allowed == IsAllowed::ALLOWED { "allowed" } else { "disallowed" };
+            query.push_str(&format!("&{}={}", key, technique));
+        }
+        if let Some(per_page) = self.per_page.filter(|n| *n != PAGE_SIZE) {
+            query.push_str(&format!("&per_page={}", per_page));
+        }
+        query.push_str(&format!("&page={}", page));
+        query
+    }
+
+    /// The matching rulesets on this page, and how many match in all.
+    async fn run(&self, track: &Track, pool: &SqlitePool) -> Result<(Vec<RulesetRecord>, u32), String> {
+        let (conditions, args) = self.conditions(track);
+        let count_sql = format!("SELECT COUNT(*) FROM rulesets r WHERE {}", conditions);
+        let (total,): (u32,) = bind_args(sqlx::query_as(&count_sql), &args)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut sql = format!(
+            "SELECT {} FROM rulesets r WHERE {} ORDER BY {}",
+            RECORD_COLUMNS,
+            conditions,
+            self.sort.order_by()
+        );
+        if let Some(per_page) = self.per_page {
+            sql.push_str(&format!(" LIMIT {} OFFSET {}", per_page, (self.page - 1) as u64 * per_page as u64));
+        }
+        let rulesets = bind_args(sqlx::query_as(&sql), &args)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok((rulesets, total))
+    }
+}
+
+fn bind_args<'q, O>(
+    mut query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    args: &'q [Arg],
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    for arg in args {
+        query = match arg {
+            Arg::Int(i) => query.bind(*i),
+            Arg::Text(s) => query.bind(s.as_str()),
+        };
+    }
+    query
+}
+
+#[derive(Serialize)]
+struct SortOption {
+    slug: &'static str,
+    name: &'static str,
+}
+
+async fn render_history(track: Track, filter: HistoryFilter, pool: &SqlitePool) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        rulesets: Vec<RulesetRecord>,
+        games: Vec<TrackTab>,
+        tracks: Vec<TrackTab>,
+        active_tab: String,
+        track: Option<String>,
+        techniques: &'static [&'static str],
+        sorts: Vec<SortOption>,
+        sort: &'static str,
+        from: String,
+        to: String,
+        allowed: Vec<&'static str>,
+        disallowed: Vec<&'static str>,
+        error: Option<String>,
+        total: u32,
+        page: u32,
+        pages: u32,
+        previous_url: Option<String>,
+        next_url: Option<String>,
+    }
+
+    let game = track.game();
+    let path = game.url("/history");
+    let track_query = if track.is_weekly() { String::new() } else { format!("track={}&", track.slug) };
+    let mut ctx = Ctx {
+        rulesets: vec![],
+        games: game_tabs(game, "/history"),
+        tracks: track_tabs(&track, |t| {
+            if t.is_weekly() {
+                path.clone()
+            } else {
+                format!("{}?track={}", path, t.slug)
+            }
+        }),
+        active_tab: "history".to_string(),
+        track: Some(track.slug.clone()).filter(|_| !track.is_weekly()),
+        techniques: game.catalog.techniques,
+        sorts: SORTS.iter().map(|(_, slug, name)| SortOption { slug, name }).collect(),
+        sort: Sort::Newest.slug(),
+        from: String::new(),
+        to: String::new(),
+        allowed: vec![],
+        disallowed: vec![],
+        error: None,
+        total: 0,
+        page: 1,
+        pages: 1,
+        previous_url: None,
+        next_url: None,
+    };
+    let search = match filter.search(game.catalog, Sort::Newest, true) {
+        Ok(search) => search,
+        Err(e) => {
+            ctx.error = Some(e);
+            return Template::render("history", ctx);
+        }
+    };
+    ctx.sort = search.sort.slug();
+    ctx.from = search.from.map(date_string).unwrap_or_default();
+    ctx.to = search.to.map(date_string).unwrap_or_default();
+    for (technique, allowed) in &search.techniques {
+        match allowed {
+            IsAllowed::ALLOWED => ctx.allowed.push(technique),
+            _ => ctx.disallowed.push(technique),
+        }
+    }
+    match search.run(&track, pool).await {
+        Ok((rulesets, total)) => {
+            let per_page = search.per_page.unwrap_or(PAGE_SIZE);
+            ctx.rulesets = rulesets;
+            ctx.total = total;
+            ctx.page = search.page;
+            ctx.pages = total.div_ceil(per_page).max(1);
+            let url = |page| format!("{}?{}{}", path, track_query, search.query_string(page));
+            if search.page > 1 {
+                ctx.previous_url = Some(url(search.page.min(ctx.pages + 1) - 1));
+            }
+            if search.page < ctx.pages {
+                ctx.next_url = Some(url(search.page + 1));
+            }
+        }
+        Err(e) => {
+            println!("Error searching rulesets: {}", e);
+            ctx.error = Some("Couldn't search the history".to_string());
+        }
+    }
+    Template::render("history", ctx)
+}
+
+#[get("/history?<track>&<filter..>")]
+async fn history(
+    track: Option<&str>,
+    filter: HistoryFilter,
+    pool: &State<SqlitePool>,
+) -> Result<Template, NotFound<String>> {
+    Ok(render_history(track_or_weekly(default_game(), track)?, filter, pool).await)
+}
+
+#[get("/<game>/history?<track>&<filter..>", rank = 2)]
+async fn game_history(
+    game: &str,
+    track: Option<&str>,
+    filter: HistoryFilter,
+    pool: &State<SqlitePool>,
+) -> Result<Template, NotFound<String>> {
+    let game = game_or_default(Some(game))?;
+    Ok(render_history(track_or_weekly(game, track)?, filter, pool).await)
+}
+
+/// Oldest first and all on one page unless asked otherwise, as before there were filters.
+#[get("/api/tracks/<track>/history?<game>&<filter..>")]
+async fn api_track_history(
+    track: &str,
+    game: Option<&str>,
+    filter: HistoryFilter,
+    pool: &State<SqlitePool>,
+) -> Result<Json<Vec<RulesetRecord>>, Either<NotFound<String>, BadRequest<String>>> {
+    let track = track_or_weekly(game_or_default(game).map_err(Either::Left)?, Some(track)).map_err(Either::Left)?;
+    let search = filter
+        .search(track.game().catalog, Sort::Oldest, false)
+        .map_err(|e| Either::Right(BadRequest(Some(e))))?;
+    match search.run(&track, pool).await {
+        Ok((rulesets, _)) => Ok(Json(rulesets)),
+        Err(e) => {
+            println!("Error searching rulesets: {}", e);
+            Ok(Json(vec![]))
+        }
+    }
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![history, game_history, api_track_history])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{indexed_rulesets, recent_indexed_rulesets, HistoryFilter, IndexedRuleset, Sort};
+    use crate::rules::IsAllowed;
+    use crate::techniques::default_catalog;
+    use crate::tracks::default_track;
+    use sqlx::migrate::Migrator;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::path::Path;
+
+    fn filter(allowed: &[&str], disallowed: &[&str]) -> HistoryFilter {
+        HistoryFilter {
+            allowed: allowed.iter().map(|s| s.to_string()).collect(),
+            disallowed: disallowed.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let catalog = default_catalog();
+        let search = filter(&["OverworldClipping"], &["SaveAndQuit", ""])
+            .search(catalog, Sort::Newest, true)
+            .unwrap();
+        assert_eq!(
+            vec![("OverworldClipping", IsAllowed::ALLOWED), ("SaveAndQuit", IsAllowed::DISALLOWED)],
+            search.techniques
+        );
+        assert_eq!((Sort::Newest, 1, Some(25)), (search.sort, search.page, search.per_page));
+        let (conditions, args) = search.conditions(&default_track());
+        assert_eq!(2, conditions.matches("ruleset_techniques").count());
+        assert_eq!(6, args.len());
+        assert_eq!(
+            "sort=newest&allowed=OverworldClipping&disallowed=SaveAndQuit&page=2",
+            search.query_string(2)
+        );
+
+        assert_eq!(None, filter(&[], &[]).search(catalog, Sort::Oldest, false).unwrap().per_page);
+        assert!(filter(&["Flying"], &[]).search(catalog, Sort::Newest, true).is_err());
+        assert!(filter(&["SaveAndQuit"], &["SaveAndQuit"]).search(catalog, Sort::Newest, true).is_err());
+
+        let mut dated = filter(&[], &[]);
+        dated.from = Some("2022-04-03".to_string());
+        dated.sort = Some("oldest".to_string());
+        let search = dated.search(catalog, Sort::Newest, true).unwrap();
+        assert_eq!((Sort::Oldest, Some(738248)), (search.sort, search.from));
+        dated.to = Some("April".to_string());
+        assert!(dated.search(catalog, Sort::Newest, true).is_err());
+        dated.to = None;
+        dated.sort = Some("random".to_string());
+        assert!(dated.search(catalog, Sort::Newest, true).is_err());
+    }
+
+    #[rocket::async_test]
+    async fn test_recent_indexed_rulesets() {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        Migrator::new(Path::new("migrations")).await.unwrap().run(&pool).await.unwrap();
+        let saved = [
+            (10, "weekly", 10, false),
+            (17, "weekly", 17, true),
+            (24, "weekly", 24, false),
+            (25, "glitched", 24, false),
+            (31, "weekly", 31, false),
+            (38, "weekly", 38, false),
+        ];
+        for (id, track, day, superseded) in saved {
+            sqlx::query(
+                "INSERT INTO rulesets (id, game, track, day, name, filename, superseded) VALUES (?, 'alttp', ?, ?, ?, '', ?)")
+                .bind(id)
+                .bind(track)
+                .bind(day)
+                .bind(format!("Week {}", day))
+                .bind(superseded)
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO ruleset_techniques (ruleset_id, technique, allowed) VALUES (?, 'Hover', 'ALLOWED')")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let ids = |rulesets: Vec<IndexedRuleset>| rulesets.iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(vec![10, 24, 25, 31, 38], ids(indexed_rulesets("alttp", None, &pool).await));
+        assert_eq!(vec![24, 31], ids(recent_indexed_rulesets("alttp", Some("weekly"), 38, 2, &pool).await));
+        let recent = recent_indexed_rulesets("alttp", Some("glitched"), 38, 2, &pool).await;
+        assert_eq!(vec![25], ids(recent));
+        assert_eq!(Some(IsAllowed::ALLOWED), indexed_rulesets("alttp", None, &pool).await[0].ruleset.get("Hover"));
+    }
+}
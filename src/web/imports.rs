@@ -0,0 +1,257 @@
+//! Importing rulesets kept elsewhere (spreadsheets, other tools, share codes) as custom presets or
+//! one-off rulesets. Parsing lives in `crate::import`.
+
+use crate::import::{import_ruleset, ImportFormat};
+use crate::rules::{IsAllowed, UserInputError};
+use crate::share::encode_ruleset;
+use crate::games::{find_game, games, default_game};
+use crate::techniques::{default_catalog, Ruleset};
+use crate::web::admin::Admin;
+use crate::web::export::{Export, Exported, Negotiated};
+use crate::web::public_url;
+use chrono::Utc;
+use rocket::form::{Form, FromForm};
+use rocket::response::status::{BadRequest, NotFound};
+use rocket::response::Redirect;
+use rocket::serde::json::Json;
+use rocket::{get, post, Build, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+pub(crate) const KIND_PRESET: &str = "preset";
+pub(crate) const KIND_ONE_OFF: &str = "one_off";
+
+#[derive(sqlx::FromRow, Serialize)]
+pub(crate) struct CustomRuleset {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) kind: String,
+    pub(crate) source_format: String,
+    pub(crate) ruleset: String,
+    pub(crate) imported_by: String,
+    pub(crate) imported_at: String,
+}
+
+const CUSTOM_COLUMNS: &str = "id, name, kind, source_format, ruleset, imported_by, imported_at";
+
+impl CustomRuleset {
+    /// Techniques added to the catalog since this was imported come back `UNSPECIFIED`. Rulesets
+    /// imported before there were several games are for the default one.
+    pub(crate) fn to_ruleset(&self) -> Ruleset {
+        let saved: HashMap<String, String> = serde_json::from_str(&self.ruleset).unwrap_or_else(|e| {
+            println!("Error reading custom ruleset {}: {}", self.id, e);
+            HashMap::new()
+        });
+        let game = saved.get("game").map_or(default_catalog().game, |g| g.as_str());
+        let mut r = Ruleset::unspecified(game, self.name.clone());
+        for (k, v) in &saved {
+            if let Ok(allowed) = v.parse::<IsAllowed>() {
+                r.set(k, allowed);
+            }
+        }
+        r
+    }
+}
+
+/// Parse `data` as a ruleset for `game` (the default game if empty) and store it. A non-empty `name`
+/// wins over any name given in `data`. Returns the new id.
+pub(crate) async fn import_custom_ruleset(
+    format: ImportFormat,
+    data: &str,
+    game: &str,
+    name: &str,
+    kind: &str,
+    actor: &str,
+    pool: &SqlitePool,
+) -> Result<i64, String> {
+    if kind != KIND_PRESET && kind != KIND_ONE_OFF {
+        return Err(format!("Expected a kind of {} or {}, got {}", KIND_PRESET, KIND_ONE_OFF, kind));
+    }
+    let game = if game.is_empty() {
+        default_game()
+    } else {
+        find_game(game).ok_or_else(|| format!("Unknown game {}", game))?
+    };
+    let mut ruleset = import_ruleset(format, data, game, name.trim().to_string()).map_err(|e| e.to_string())?;
+    if !name.trim().is_empty() {
+        ruleset.name = name.trim().to_string();
+    }
+    if ruleset.name.is_empty() {
+        return Err("Imported rulesets need a name".to_string());
+    }
+    sqlx::query(
+        "INSERT INTO custom_rulesets (name, kind, source_format, ruleset, imported_by, imported_at) \
+         VALUES (?, ?, ?, ?, ?, ?)")
+        .bind(&ruleset.name)
+        .bind(kind)
+        .bind(format.as_str())
+        .bind(serde_json::to_string(&ruleset).unwrap())
+        .bind(actor)
+        .bind(Utc::now().to_rfc3339())
+        .execute(pool)
+        .await
+        .map(|r| r.last_insert_rowid())
+        .map_err(|e| match e {
+            sqlx::Error::Database(d) if d.message().contains("UNIQUE") => {
+                format!("There's already a preset called {}", ruleset.name)
+            }
+            e => e.to_string(),
+        })
+}
+
+async fn get_custom_rulesets(pool: &SqlitePool) -> Vec<CustomRuleset> {
+    sqlx::query_as(&format!("SELECT {} FROM custom_rulesets ORDER BY id DESC", CUSTOM_COLUMNS))
+        .fetch_all(pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching custom rulesets: {:?}", e);
+            vec![]
+        })
+}
+
+/// Imported presets, oldest first, for comparing against.
+pub(crate) async fn get_presets(pool: &SqlitePool) -> Vec<Ruleset> {
+    sqlx::query_as::<_, CustomRuleset>(&format!(
+        "SELECT {} FROM custom_rulesets WHERE kind = ? ORDER BY id",
+        CUSTOM_COLUMNS
+    ))
+    .bind(KIND_PRESET)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_else(|e| {
+        println!("Error fetching presets: {:?}", e);
+        vec![]
+    })
+    .iter()
+    .map(CustomRuleset::to_ruleset)
+    .collect()
+}
+
+#[derive(FromForm, Deserialize, Serialize, Default)]
+#[serde(default)]
+struct ImportForm {
+    format: String,
+    game: String,
+    name: String,
+    kind: String,
+    data: String,
+}
+
+fn import_page(admin: String, form: ImportForm, error: Option<String>, custom: Vec<CustomRuleset>) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        admin: String,
+        form: ImportForm,
+        error: Option<String>,
+        custom_rulesets: Vec<CustomRuleset>,
+        games: Vec<(&'static str, &'static str)>,
+    }
+
+    Template::render(
+        "admin_import",
+        Ctx {
+            active_tab: "admin".to_string(),
+            games: games().iter().map(|g| (g.slug, g.name.as_str())).collect(),
+            admin,
+            form,
+            error,
+            custom_rulesets: custom,
+        },
+    )
+}
+
+#[get("/admin/import")]
+async fn import_form(admin: Admin, pool: &State<SqlitePool>) -> Template {
+    let form = ImportForm {
+        format: "csv".to_string(),
+        kind: KIND_ONE_OFF.to_string(),
+        ..Default::default()
+    };
+    import_page(admin.name, form, None, get_custom_rulesets(pool).await)
+}
+
+#[post("/admin/import", data = "<form>")]
+async fn import(form: Form<ImportForm>, admin: Admin, pool: &State<SqlitePool>) -> Result<Redirect, Template> {
+    let form = form.into_inner();
+    let result = match form.format.parse::<ImportFormat>() {
+        Ok(format) => import_custom_ruleset(format, &form.data, &form.game, &form.name, &form.kind, &admin.name, pool).await,
+        Err(e) => Err(e.to_string()),
+    };
+    match result {
+        Ok(id) => Ok(Redirect::to(format!("/custom/{}", id))),
+        Err(e) => Err(import_page(admin.name, form, Some(e), get_custom_rulesets(pool).await)),
+    }
+}
+
+#[post("/admin/custom/<id>/delete")]
+async fn delete_custom(id: i64, _admin: Admin, pool: &State<SqlitePool>) -> Result<Redirect, BadRequest<String>> {
+    sqlx::query("DELETE FROM custom_rulesets WHERE id = ?")
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to("/admin/import"))
+}
+
+#[derive(Serialize)]
+struct Imported {
+    id: i64,
+    url: String,
+}
+
+/// The same as the import form, as JSON: `{"format", "game", "name", "kind", "data"}`.
+#[post("/api/import", data = "<form>")]
+async fn api_import(form: Json<ImportForm>, admin: Admin, pool: &State<SqlitePool>) -> Result<Json<Imported>, BadRequest<String>> {
+    let format: ImportFormat = form.format.parse().map_err(|e: UserInputError| BadRequest(Some(e.to_string())))?;
+    let kind = if form.kind.is_empty() { KIND_ONE_OFF } else { &form.kind };
+    let id = import_custom_ruleset(format, &form.data, &form.game, &form.name, kind, &admin.name, pool)
+        .await
+        .map_err(|e| BadRequest(Some(e)))?;
+    Ok(Json(Imported {
+        id,
+        url: format!("{}/custom/{}", public_url(), id),
+    }))
+}
+
+#[get("/custom/<id>")]
+async fn custom_ruleset(id: i64, export: Export, pool: &State<SqlitePool>) -> Result<Negotiated, NotFound<String>> {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        custom: CustomRuleset,
+        ruleset: HashMap<String, String>,
+        technique_names: Vec<String>,
+        share_code: String,
+    }
+
+    let custom: CustomRuleset = sqlx::query_as(&format!("SELECT {} FROM custom_rulesets WHERE id = ?", CUSTOM_COLUMNS))
+        .bind(id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|_| NotFound("Unknown ruleset id".to_string()))?;
+    let ruleset = custom.to_ruleset();
+    if let Export(Some(format)) = export {
+        let key = format!("custom-{}", id);
+        return Ok(Negotiated::Export(Exported::named(format, &key, &custom.name, &ruleset, None)));
+    }
+    Ok(Negotiated::Page(Template::render(
+        "custom_ruleset",
+        Ctx {
+            active_tab: "".to_string(),
+            ruleset: ruleset.techniques().map(|(t, allowed)| (t.to_string(), allowed.as_str().to_string())).collect(),
+            technique_names: ruleset.catalog().techniques.iter().map(|s| s.to_string()).collect(),
+            share_code: encode_ruleset(&ruleset),
+            custom,
+        },
+    )))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount(
+        "/",
+        rocket::routes![import_form, import, delete_custom, api_import, custom_ruleset],
+    )
+}
@@ -1,14 +1,83 @@
 use rocket::{Rocket, Build};
+use std::env::var;
 
+mod admin;
+mod analytics;
 mod boring;
+mod calendar;
+mod card;
+mod discord;
+mod export;
+mod feeds;
+mod history;
+mod imports;
+mod notes;
+mod seasons;
+mod share;
+mod similar;
+mod stats;
+mod submissions;
+mod supplemental;
+mod validate;
+mod webhooks;
 mod weekly;
 
+use admin::{add_routes as add_admin_routes};
+use analytics::{add_routes as add_analytics_routes};
 use boring::{add_routes as add_boring_routes};
+use calendar::{add_routes as add_calendar_routes};
+use card::{add_routes as add_card_routes};
+use discord::{add_routes as add_discord_routes};
+use feeds::{add_routes as add_feed_routes};
+use history::{add_routes as add_history_routes};
+use imports::{add_routes as add_import_routes};
+use notes::{add_routes as add_note_routes};
+use seasons::{add_routes as add_season_routes};
+use share::{add_routes as add_share_routes};
+use similar::{add_routes as add_similar_routes};
+use stats::{add_routes as add_stats_routes};
+use submissions::{add_routes as add_submission_routes};
+use supplemental::{add_routes as add_supplemental_routes};
+use validate::{add_routes as add_validate_routes};
+use webhooks::{add_routes as add_webhook_routes};
 use weekly::{add_routes as add_weekly_routes};
 
+pub(crate) use discord::discord_announcement;
+pub(crate) use history::index_saved_rulesets;
+pub(crate) use imports::{get_presets, import_custom_ruleset, KIND_ONE_OFF, KIND_PRESET};
+pub(crate) use share::{ruleset_share_code, ShareCode};
+pub(crate) use supplemental::init_supplemental_rules;
+pub(crate) use weekly::{current_weekly_ruleset, materialize_weekly};
+
+/// Where the site is publicly reachable, for links that leave the site (webhooks, feeds...).
+/// Set `PUBLIC_URL` when running anywhere but production.
+pub(crate) fn public_url() -> String {
+    var("PUBLIC_URL")
+        .unwrap_or_else(|_| "https://rulesetrando.foxlisk.com".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
 pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
     let mut r = add_boring_routes(rocket);
     r = add_weekly_routes(r);
+    r = add_history_routes(r);
+    r = add_admin_routes(r);
+    r = add_submission_routes(r);
+    r = add_season_routes(r);
+    r = add_analytics_routes(r);
+    r = add_note_routes(r);
+    r = add_webhook_routes(r);
+    r = add_discord_routes(r);
+    r = add_feed_routes(r);
+    r = add_calendar_routes(r);
+    r = add_validate_routes(r);
+    r = add_card_routes(r);
+    r = add_share_routes(r);
+    r = add_similar_routes(r);
+    r = add_stats_routes(r);
+    r = add_import_routes(r);
+    r = add_supplemental_routes(r);
     r
 
 }
\ No newline at end of file
@@ -0,0 +1,178 @@
+//! Community route notes: markdown write-ups attached to a ruleset. Notes show up as soon as
+//! they're posted; organizers can hide them from the admin page.
+
+use crate::markdown::render_markdown;
+use crate::rules::UserInputError;
+use crate::web::admin::Admin;
+use crate::web::weekly::get_record;
+use chrono::Utc;
+use rocket::form::{Form, FromForm};
+use rocket::response::status::BadRequest;
+use rocket::response::Redirect;
+use rocket::{post, Build, Rocket, State};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+const MAX_AUTHOR_LEN: usize = 64;
+const MAX_BODY_LEN: usize = 10000;
+
+pub(crate) const STATUS_VISIBLE: &str = "visible";
+pub(crate) const STATUS_HIDDEN: &str = "hidden";
+
+#[derive(sqlx::FromRow, Serialize)]
+pub(crate) struct RouteNote {
+    pub(crate) id: i64,
+    pub(crate) ruleset_id: u32,
+    pub(crate) author: String,
+    pub(crate) body: String,
+    pub(crate) status: String,
+    pub(crate) posted_at: String,
+    pub(crate) moderated_by: Option<String>,
+    pub(crate) moderated_at: Option<String>,
+}
+
+const NOTE_COLUMNS: &str = "id, ruleset_id, author, body, status, posted_at, moderated_by, moderated_at";
+
+/// A note ready for display, with its body rendered to HTML.
+#[derive(Serialize)]
+pub(crate) struct RenderedNote {
+    #[serde(flatten)]
+    note: RouteNote,
+    html: String,
+}
+
+impl From<RouteNote> for RenderedNote {
+    fn from(note: RouteNote) -> Self {
+        Self {
+            html: render_markdown(&note.body),
+            note,
+        }
+    }
+}
+
+/// Everything the `route_notes` template needs for one ruleset.
+#[derive(Serialize)]
+pub(crate) struct RouteNotesContext {
+    ruleset_id: u32,
+    notes: Vec<RenderedNote>,
+}
+
+async fn get_notes(ruleset_id: u32, status: Option<&str>, pool: &SqlitePool) -> Vec<RenderedNote> {
+    let result: Result<Vec<RouteNote>, _> = match status {
+        Some(status) => {
+            sqlx::query_as(&format!(
+                "SELECT {} FROM route_notes WHERE ruleset_id = ? AND status = ? ORDER BY id",
+                NOTE_COLUMNS
+            ))
+            .bind(ruleset_id)
+            .bind(status)
+            .fetch_all(pool)
+            .await
+        }
+        None => {
+            sqlx::query_as(&format!("SELECT {} FROM route_notes WHERE ruleset_id = ? ORDER BY id", NOTE_COLUMNS))
+                .bind(ruleset_id)
+                .fetch_all(pool)
+                .await
+        }
+    };
+    result
+        .unwrap_or_else(|e| {
+            println!("Error fetching route notes: {:?}", e);
+            vec![]
+        })
+        .into_iter()
+        .map(RenderedNote::from)
+        .collect()
+}
+
+pub(crate) async fn get_route_notes(ruleset_id: u32, pool: &SqlitePool) -> RouteNotesContext {
+    RouteNotesContext {
+        ruleset_id,
+        notes: get_notes(ruleset_id, Some(STATUS_VISIBLE), pool).await,
+    }
+}
+
+/// Every note on a ruleset, hidden ones included, for the admin page.
+pub(crate) async fn get_moderation_entries(ruleset_id: u32, pool: &SqlitePool) -> Vec<RenderedNote> {
+    get_notes(ruleset_id, None, pool).await
+}
+
+#[derive(FromForm)]
+struct NoteForm {
+    author: String,
+    body: String,
+}
+
+impl NoteForm {
+    fn validate(&self) -> Result<(), UserInputError> {
+        let too_long = |field: &str, max: usize| UserInputError {
+            err: format!("{} must be at most {} characters", field, max),
+        };
+        if self.author.trim().is_empty() || self.body.trim().is_empty() {
+            return Err(UserInputError {
+                err: "Name and note are required".to_string(),
+            });
+        }
+        if self.author.trim().len() > MAX_AUTHOR_LEN {
+            return Err(too_long("Name", MAX_AUTHOR_LEN));
+        }
+        if self.body.len() > MAX_BODY_LEN {
+            return Err(too_long("Note", MAX_BODY_LEN));
+        }
+        Ok(())
+    }
+}
+
+#[post("/history/<id>/notes", data = "<form>")]
+async fn post_note(id: u32, form: Form<NoteForm>, pool: &State<SqlitePool>) -> Result<Redirect, BadRequest<String>> {
+    get_record(id, pool)
+        .await
+        .map_err(|_| BadRequest(Some("Unknown ruleset id".to_string())))?;
+    form.validate().map_err(|e| BadRequest(Some(e.to_string())))?;
+    sqlx::query("INSERT INTO route_notes (ruleset_id, author, body, status, posted_at) VALUES (?, ?, ?, ?, ?)")
+        .bind(id)
+        .bind(form.author.trim())
+        .bind(form.body.trim_end())
+        .bind(STATUS_VISIBLE)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to(format!("/history/{}#route-notes", id)))
+}
+
+#[derive(FromForm)]
+struct ModerationForm {
+    status: String,
+}
+
+#[post("/admin/notes/<id>/moderate", data = "<form>")]
+async fn moderate(
+    id: i64,
+    form: Form<ModerationForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    if ![STATUS_VISIBLE, STATUS_HIDDEN].contains(&form.status.as_str()) {
+        return Err(BadRequest(Some(format!("Unknown status {}", form.status))));
+    }
+    let (ruleset_id,): (u32,) = sqlx::query_as("SELECT ruleset_id FROM route_notes WHERE id = ?")
+        .bind(id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|_| BadRequest(Some("Unknown note id".to_string())))?;
+    sqlx::query("UPDATE route_notes SET status = ?, moderated_by = ?, moderated_at = ? WHERE id = ?")
+        .bind(&form.status)
+        .bind(&admin.name)
+        .bind(Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to(format!("/admin/rulesets/{}#route-notes", ruleset_id)))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![post_note, moderate])
+}
@@ -0,0 +1,451 @@
+//! Seasons: named date ranges of weeklies with a points formula. Standings are recomputed from the
+//! verified submissions on every request (see `crate::standings`), so fixing a submission or
+//! changing the formula is reflected immediately. Edits land in `season_audit_log`, since they
+//! change everyone's standings.
+
+use crate::games::{find_game, games};
+use crate::standings::{compute_standings, Finish, PointsFormula, StandingsRow, WeekBreakdown, WeekResults};
+use crate::tracks::{find_track, tracks, Track};
+use crate::web::admin::Admin;
+use crate::web::submissions::{canonical_goal, format_time, STATUS_VERIFIED};
+use crate::web::weekly::day_to_nice_string;
+use chrono::{Datelike, NaiveDate, TimeZone, Utc};
+use rocket::form::{Form, FromForm};
+use rocket::response::status::{BadRequest, NotFound};
+use rocket::response::Redirect;
+use rocket::{get, post, Build, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+#[derive(sqlx::FromRow, Serialize)]
+pub(crate) struct Season {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) start_day: i32,
+    pub(crate) end_day: i32,
+    pub(crate) game: String,
+    pub(crate) track: String,
+    pub(crate) goal: String,
+    pub(crate) placement_points: String,
+    pub(crate) time_points: u32,
+    pub(crate) participation_points: u32,
+    pub(crate) created_by: String,
+    pub(crate) created_at: String,
+}
+
+const SEASON_COLUMNS: &str = "id, name, start_day, end_day, game, track, goal, placement_points, time_points, \
+    participation_points, created_by, created_at";
+
+impl Season {
+    fn formula(&self) -> Result<PointsFormula, String> {
+        Ok(PointsFormula {
+            placement: PointsFormula::parse_placement(&self.placement_points)?,
+            time_points: self.time_points,
+            participation: self.participation_points,
+        })
+    }
+}
+
+fn day_to_date(day: i32) -> NaiveDate {
+    NaiveDate::from_num_days_from_ce(day)
+}
+
+fn nice_day(day: i32) -> String {
+    day_to_nice_string(&Utc.from_utc_date(&day_to_date(day)))
+}
+
+/// `track` of `game`, as picked on the season form.
+fn schedule_value(game: &str, track: &str) -> String {
+    format!("{}/{}", game, track)
+}
+
+/// A season as listed on the site, with its dates and track spelled out.
+#[derive(Serialize)]
+struct SeasonSummary {
+    #[serde(flatten)]
+    season: Season,
+    schedule: String,
+    /// The track's name, and its game's when that isn't the only one.
+    track_name: String,
+    starts: String,
+    ends: String,
+    start_date: String,
+    end_date: String,
+}
+
+fn schedule_name(track: &Track) -> String {
+    if games().len() > 1 {
+        format!("{} {}", track.game().name, track.name)
+    } else {
+        track.name.clone()
+    }
+}
+
+impl From<Season> for SeasonSummary {
+    fn from(season: Season) -> Self {
+        let track = find_game(&season.game).and_then(|g| find_track(g, &season.track));
+        Self {
+            schedule: schedule_value(&season.game, &season.track),
+            track_name: match track {
+                Some(t) => schedule_name(&t),
+                None => format!("{} {}", season.game, season.track),
+            },
+            starts: nice_day(season.start_day),
+            ends: nice_day(season.end_day),
+            start_date: day_to_date(season.start_day).format("%Y-%m-%d").to_string(),
+            end_date: day_to_date(season.end_day).format("%Y-%m-%d").to_string(),
+            season,
+        }
+    }
+}
+
+async fn get_seasons(pool: &SqlitePool) -> Vec<Season> {
+    sqlx::query_as(&format!("SELECT {} FROM seasons ORDER BY start_day DESC, id DESC", SEASON_COLUMNS))
+        .fetch_all(pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching seasons: {:?}", e);
+            vec![]
+        })
+}
+
+async fn get_season(id: i64, pool: &SqlitePool) -> Result<Season, String> {
+    sqlx::query_as(&format!("SELECT {} FROM seasons WHERE id = ?", SEASON_COLUMNS))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            println!("Error fetching season: {:?}", e);
+            "Unknown season id".to_string()
+        })
+}
+
+/// The fields of a season an organizer sets, as they'd read them.
+fn season_fields(season: &Season) -> Vec<(&'static str, String)> {
+    let date = |day| NaiveDate::from_num_days_from_ce(day).format("%Y-%m-%d").to_string();
+    vec![
+        ("name", season.name.clone()),
+        ("start", date(season.start_day)),
+        ("end", date(season.end_day)),
+        ("track", format!("{}/{}", season.game, season.track)),
+        ("goal", season.goal.clone()),
+        ("placement points", season.placement_points.clone()),
+        ("time points", season.time_points.to_string()),
+        ("participation points", season.participation_points.to_string()),
+    ]
+}
+
+async fn record_season_audit(
+    season_id: i64,
+    actor: &str,
+    action: &str,
+    details: &str,
+    pool: &SqlitePool,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO season_audit_log (season_id, actor, action, details, created_at) VALUES (?, ?, ?, ?, ?)")
+        .bind(season_id)
+        .bind(actor)
+        .bind(action)
+        .bind(details)
+        .bind(Utc::now().to_rfc3339())
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Gather every non-superseded weekly on the season's track in the season along with its verified
+/// finishes for the season's goal, whatever its case. Weeks with nobody finishing are still
+/// included, they just score nothing.
+async fn get_season_results(season: &Season, pool: &SqlitePool) -> Result<Vec<WeekResults>, sqlx::Error> {
+    #[derive(sqlx::FromRow)]
+    struct Row {
+        ruleset_id: u32,
+        name: String,
+        player: Option<String>,
+        time_seconds: Option<u32>,
+    }
+
+    let rows: Vec<Row> = sqlx::query_as(
+        "SELECT r.id AS ruleset_id, r.name AS name, s.player AS player, s.time_seconds AS time_seconds \
+         FROM rulesets r \
+         LEFT JOIN submissions s ON s.ruleset_id = r.id AND s.status = ? AND LOWER(s.goal) = LOWER(?) \
+         WHERE r.game = ? AND r.track = ? AND r.day BETWEEN ? AND ? AND r.superseded = 0 \
+         ORDER BY r.day, s.id")
+        .bind(STATUS_VERIFIED)
+        .bind(season.goal.trim())
+        .bind(&season.game)
+        .bind(&season.track)
+        .bind(season.start_day)
+        .bind(season.end_day)
+        .fetch_all(pool)
+        .await?;
+
+    let mut weeks: Vec<WeekResults> = vec![];
+    for row in rows {
+        if weeks.last().is_none_or(|w| w.ruleset_id != row.ruleset_id) {
+            weeks.push(WeekResults {
+                ruleset_id: row.ruleset_id,
+                name: row.name,
+                finishes: vec![],
+            });
+        }
+        if let (Some(player), Some(time_seconds)) = (row.player, row.time_seconds) {
+            weeks.last_mut().unwrap().finishes.push(Finish { player, time_seconds });
+        }
+    }
+    Ok(weeks)
+}
+
+#[get("/seasons")]
+async fn seasons(pool: &State<SqlitePool>) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        seasons: Vec<SeasonSummary>,
+    }
+
+    Template::render(
+        "seasons",
+        Ctx {
+            active_tab: "seasons".to_string(),
+            seasons: get_seasons(pool).await.into_iter().map(SeasonSummary::from).collect(),
+        },
+    )
+}
+
+#[get("/seasons/<id>")]
+async fn season(id: i64, pool: &State<SqlitePool>) -> Result<Template, NotFound<String>> {
+    #[derive(Serialize)]
+    struct BreakdownView {
+        #[serde(flatten)]
+        week: WeekBreakdown,
+        time: String,
+    }
+
+    #[derive(Serialize)]
+    struct RowView {
+        place: usize,
+        player: String,
+        total: u32,
+        weeks: Vec<BreakdownView>,
+    }
+
+    impl From<StandingsRow> for RowView {
+        fn from(row: StandingsRow) -> Self {
+            Self {
+                place: row.place,
+                player: row.player,
+                total: row.total,
+                weeks: row
+                    .weeks
+                    .into_iter()
+                    .map(|week| BreakdownView {
+                        time: format_time(week.time_seconds),
+                        week,
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        season: SeasonSummary,
+        weeks: Vec<(u32, String)>,
+        standings: Vec<RowView>,
+    }
+
+    let season = get_season(id, pool).await.map_err(NotFound)?;
+    let formula = season.formula().map_err(NotFound)?;
+    let results = get_season_results(&season, pool).await.map_err(|e| NotFound(e.to_string()))?;
+    let standings = compute_standings(&formula, &results);
+    Ok(Template::render(
+        "season",
+        Ctx {
+            active_tab: "seasons".to_string(),
+            season: season.into(),
+            weeks: results.into_iter().map(|w| (w.ruleset_id, w.name)).collect(),
+            standings: standings.into_iter().map(RowView::from).collect(),
+        },
+    ))
+}
+
+#[derive(FromForm)]
+struct SeasonForm {
+    name: String,
+    start_date: String,
+    end_date: String,
+    /// `schedule_value` of the track the season scores.
+    schedule: String,
+    goal: String,
+    placement_points: String,
+    time_points: u32,
+    participation_points: u32,
+}
+
+/// The most points any one part of the formula can give, well short of overflowing a season's total.
+const MAX_POINTS: u32 = 10_000;
+
+impl SeasonForm {
+    /// Validate the form, returning the start and end as day numbers and the season's track.
+    fn validate(&self) -> Result<(i32, i32, Track), String> {
+        if self.name.trim().is_empty() || self.goal.trim().is_empty() {
+            return Err("Name and goal are required".to_string());
+        }
+        let placement = PointsFormula::parse_placement(&self.placement_points)?;
+        if placement.iter().chain([&self.time_points, &self.participation_points]).any(|p| *p > MAX_POINTS) {
+            return Err(format!("Points can be at most {}", MAX_POINTS));
+        }
+        let parse = |d: &str| {
+            NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d")
+                .map(|d| d.num_days_from_ce())
+                .map_err(|_| format!("Expected a date like 2022-04-03, got {}", d))
+        };
+        let (start, end) = (parse(&self.start_date)?, parse(&self.end_date)?);
+        if end < start {
+            return Err("The season ends before it starts".to_string());
+        }
+        let track = self
+            .schedule
+            .split_once('/')
+            .and_then(|(game, track)| find_track(find_game(game)?, track))
+            .ok_or_else(|| format!("Unknown track {}", self.schedule))?;
+        Ok((start, end, track))
+    }
+}
+
+#[get("/admin/seasons")]
+async fn admin_seasons(admin: Admin, pool: &State<SqlitePool>) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        admin: String,
+        seasons: Vec<SeasonSummary>,
+        /// Every game's tracks, as (`schedule_value`, name).
+        schedules: Vec<(String, String)>,
+    }
+
+    Template::render(
+        "admin_seasons",
+        Ctx {
+            active_tab: "admin".to_string(),
+            admin: admin.name,
+            seasons: get_seasons(pool).await.into_iter().map(SeasonSummary::from).collect(),
+            schedules: games()
+                .iter()
+                .flat_map(tracks)
+                .map(|t| (schedule_value(t.game, &t.slug), schedule_name(&t)))
+                .collect(),
+        },
+    )
+}
+
+#[post("/admin/seasons", data = "<form>")]
+async fn create_season(
+    form: Form<SeasonForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    let (start, end, track) = form.validate().map_err(|e| BadRequest(Some(e)))?;
+    let goal = canonical_goal(&form.goal, pool).await.map_err(|e| BadRequest(Some(e.to_string())))?;
+    sqlx::query(
+        "INSERT INTO seasons (name, start_day, end_day, game, track, goal, placement_points, time_points, \
+         participation_points, created_by, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+        .bind(form.name.trim())
+        .bind(start)
+        .bind(end)
+        .bind(track.game)
+        .bind(&track.slug)
+        .bind(goal)
+        .bind(form.placement_points.trim())
+        .bind(form.time_points)
+        .bind(form.participation_points)
+        .bind(&admin.name)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to("/admin/seasons"))
+}
+
+#[post("/admin/seasons/<id>", data = "<form>")]
+async fn update_season(
+    id: i64,
+    form: Form<SeasonForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    let before = get_season(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    let (start, end, track) = form.validate().map_err(|e| BadRequest(Some(e)))?;
+    let goal = canonical_goal(&form.goal, pool).await.map_err(|e| BadRequest(Some(e.to_string())))?;
+    sqlx::query(
+        "UPDATE seasons SET name = ?, start_day = ?, end_day = ?, game = ?, track = ?, goal = ?, \
+         placement_points = ?, time_points = ?, participation_points = ? WHERE id = ?")
+        .bind(form.name.trim())
+        .bind(start)
+        .bind(end)
+        .bind(track.game)
+        .bind(&track.slug)
+        .bind(&goal)
+        .bind(form.placement_points.trim())
+        .bind(form.time_points)
+        .bind(form.participation_points)
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    let old_fields = season_fields(&before);
+    let after = Season {
+        name: form.name.trim().to_string(),
+        start_day: start,
+        end_day: end,
+        game: track.game.to_string(),
+        track: track.slug.clone(),
+        goal,
+        placement_points: form.placement_points.trim().to_string(),
+        time_points: form.time_points,
+        participation_points: form.participation_points,
+        ..before
+    };
+    let details = old_fields
+        .into_iter()
+        .zip(season_fields(&after))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| format!("{}: {} -> {}", field, old, new))
+        .collect::<Vec<_>>()
+        .join("\n");
+    record_season_audit(id, &admin.name, "edit", &details, pool)
+        .await
+        .map_err(|e| BadRequest(Some(e)))?;
+    Ok(Redirect::to("/admin/seasons"))
+}
+
+#[post("/admin/seasons/<id>/delete")]
+async fn delete_season(id: i64, admin: Admin, pool: &State<SqlitePool>) -> Result<Redirect, BadRequest<String>> {
+    let season = get_season(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    sqlx::query("DELETE FROM seasons WHERE id = ?")
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    let details = season_fields(&season)
+        .into_iter()
+        .map(|(field, value)| format!("{}: {}", field, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    record_season_audit(id, &admin.name, "delete", &details, pool)
+        .await
+        .map_err(|e| BadRequest(Some(e)))?;
+    Ok(Redirect::to("/admin/seasons"))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount(
+        "/",
+        rocket::routes![seasons, season, admin_seasons, create_season, update_season, delete_season],
+    )
+}
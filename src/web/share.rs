@@ -0,0 +1,129 @@
+//! Share code pages and API. The encoding lives in `crate::share`.
+
+use crate::rules::{IsAllowed, UserInputError};
+use crate::share::{decode_ruleset, encode_ruleset};
+use crate::games::find_game;
+use crate::techniques::{default_catalog, Ruleset};
+use crate::web::export::{Export, Exported, Negotiated};
+use crate::web::public_url;
+use crate::web::weekly::{game_or_default, get_stored_ruleset, materialize_track_weekly, materialize_weekly};
+use rocket::response::status::{BadRequest, NotFound};
+use rocket::serde::json::Json;
+use rocket::{get, post, Build, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+const SHARED_NAME: &str = "Shared ruleset";
+
+#[derive(Serialize)]
+pub(crate) struct ShareCode {
+    pub(crate) code: String,
+    pub(crate) url: String,
+    pub(crate) ruleset: Ruleset,
+}
+
+impl ShareCode {
+    pub(crate) fn new(ruleset: Ruleset) -> Self {
+        let code = encode_ruleset(&ruleset);
+        Self {
+            url: format!("{}/r/{}", public_url(), code),
+            code,
+            ruleset,
+        }
+    }
+}
+
+/// The share code for saved ruleset `id`, as it currently stands.
+pub(crate) async fn ruleset_share_code(id: u32, pool: &SqlitePool) -> Result<ShareCode, String> {
+    let stored = get_stored_ruleset(id, pool).await?;
+    let mut ruleset = stored.current().to_ruleset();
+    ruleset.name = stored.record.name;
+    Ok(ShareCode::new(ruleset))
+}
+
+#[get("/r/<code>")]
+fn shared_ruleset(code: &str, export: Export) -> Result<Negotiated, BadRequest<String>> {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        code: String,
+        ruleset: HashMap<String, String>,
+        technique_names: Vec<String>,
+    }
+
+    let ruleset = decode_ruleset(code, SHARED_NAME.to_string()).map_err(|e| BadRequest(Some(e.to_string())))?;
+    if let Export(Some(format)) = export {
+        return Ok(Negotiated::Export(Exported::named(format, code, SHARED_NAME, &ruleset, None)));
+    }
+    Ok(Negotiated::Page(Template::render(
+        "shared_ruleset",
+        Ctx {
+            active_tab: "".to_string(),
+            code: code.to_string(),
+            ruleset: ruleset.techniques().map(|(t, allowed)| (t.to_string(), allowed.as_str().to_string())).collect(),
+            technique_names: ruleset.catalog().techniques.iter().map(|s| s.to_string()).collect(),
+        },
+    )))
+}
+
+#[get("/api/share/<code>")]
+fn decode_share_code(code: &str) -> Result<Json<ShareCode>, BadRequest<String>> {
+    decode_ruleset(code, SHARED_NAME.to_string())
+        .map(|r| Json(ShareCode::new(r)))
+        .map_err(|e| BadRequest(Some(e.to_string())))
+}
+
+/// Encode a ruleset given as `{"Technique": "ALLOWED", ...}`, plus `"game"` for any but the default
+/// game. Techniques left out are unspecified.
+#[post("/api/share", data = "<ruleset>")]
+fn encode_share_code(ruleset: Json<HashMap<String, String>>) -> Result<Json<ShareCode>, BadRequest<String>> {
+    let game = ruleset.get("game").map_or(default_catalog().game, |g| g.as_str());
+    if find_game(game).is_none() {
+        return Err(BadRequest(Some(format!("Unknown game {}", game))));
+    }
+    let mut r = Ruleset::unspecified(game, SHARED_NAME.to_string());
+    for (technique, value) in ruleset.iter().filter(|(k, _)| *k != "game") {
+        let allowed: IsAllowed = value.parse().map_err(|e: UserInputError| BadRequest(Some(e.to_string())))?;
+        if !r.set(technique, allowed) {
+            return Err(BadRequest(Some(format!("Unknown technique {}", technique))));
+        }
+    }
+    Ok(Json(ShareCode::new(r)))
+}
+
+#[get("/api/rulesets/<id>/share")]
+async fn ruleset_share(id: u32, pool: &State<SqlitePool>) -> Result<Json<ShareCode>, NotFound<String>> {
+    ruleset_share_code(id, pool).await.map(Json).map_err(NotFound)
+}
+
+#[get("/api/weekly/share")]
+async fn weekly_share(pool: &State<SqlitePool>) -> Result<Json<ShareCode>, NotFound<String>> {
+    let id = materialize_weekly(pool).await.map_err(NotFound)?;
+    ruleset_share_code(id, pool).await.map(Json).map_err(NotFound)
+}
+
+#[get("/api/weekly/<track>/share?<game>")]
+async fn track_weekly_share(
+    track: &str,
+    game: Option<&str>,
+    pool: &State<SqlitePool>,
+) -> Result<Json<ShareCode>, NotFound<String>> {
+    let id = materialize_track_weekly(game_or_default(game)?, track, pool).await.map_err(NotFound)?;
+    ruleset_share_code(id, pool).await.map(Json).map_err(NotFound)
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount(
+        "/",
+        rocket::routes![
+            shared_ruleset,
+            decode_share_code,
+            encode_share_code,
+            ruleset_share,
+            weekly_share,
+            track_weekly_share
+        ],
+    )
+}
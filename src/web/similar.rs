@@ -0,0 +1,156 @@
+//! The rulesets and presets closest to a given one: the API, and the "closest past week" on weekly
+//! pages. The ranking lives in `crate::similarity`; past rulesets come from `ruleset_techniques`.
+
+use crate::games::{find_game, Game};
+use crate::rules::{IsAllowed, UserInputError};
+use crate::share::decode_ruleset;
+use crate::similarity::{rank, Candidate, Metric, Similar};
+use crate::techniques::{default_catalog, Ruleset};
+use crate::web::get_presets;
+use crate::web::history::{indexed_rulesets, recent_indexed_rulesets, IndexedRuleset};
+use crate::web::weekly::{get_stored_ruleset, materialize_weekly};
+use rocket::response::status::BadRequest;
+use rocket::serde::json::Json;
+use rocket::{get, post, Build, Rocket, State};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+const DEFAULT_LIMIT: usize = 5;
+const MAX_LIMIT: usize = 50;
+const COMPARED_NAME: &str = "Compared ruleset";
+/// How many of the latest weeks the weekly page looks through for the closest one, so it doesn't
+/// load the whole history on every view.
+const CLOSEST_PAST_WEEKS: u32 = 104;
+
+/// `rulesets`, newest first, as candidates, leaving out `exclude`.
+fn candidates(rulesets: Vec<IndexedRuleset>, exclude: Option<u32>) -> Vec<Candidate> {
+    rulesets
+        .into_iter()
+        .rev()
+        .filter(|r| Some(r.id) != exclude)
+        .map(|r| Candidate {
+            kind: "ruleset",
+            id: Some(r.id),
+            name: r.name,
+            url: Some(format!("/history/{}", r.id)),
+            ruleset: r.ruleset,
+        })
+        .collect()
+}
+
+/// `game`'s presets, built in and imported.
+async fn presets(game: &Game, pool: &SqlitePool) -> Vec<Candidate> {
+    let mut presets: Vec<Candidate> = game
+        .presets
+        .iter()
+        .map(|p| Candidate {
+            kind: "preset",
+            id: None,
+            name: p.id.clone(),
+            url: None,
+            ruleset: p.ruleset.clone(),
+        })
+        .collect();
+    presets.extend(get_presets(pool).await.into_iter().filter(|r| r.game == game.slug).map(|r| Candidate {
+        kind: "preset",
+        id: None,
+        name: r.name.clone(),
+        url: None,
+        ruleset: r,
+    }));
+    presets
+}
+
+/// The rulesets on `track` closest to `ruleset`, from the `CLOSEST_PAST_WEEKS` saved before
+/// `before_day`.
+pub(crate) async fn closest_past_rulesets(
+    ruleset: &Ruleset,
+    track: &str,
+    before_day: i32,
+    limit: usize,
+    pool: &SqlitePool,
+) -> Vec<Similar> {
+    let past = recent_indexed_rulesets(&ruleset.game, Some(track), before_day, CLOSEST_PAST_WEEKS, pool).await;
+    rank(ruleset, candidates(past, None), Metric::Hamming, limit)
+}
+
+/// The past rulesets and presets closest to `ruleset`, other than the saved ruleset `exclude`.
+async fn similar(
+    ruleset: &Ruleset,
+    exclude: Option<u32>,
+    metric: Option<&str>,
+    limit: Option<usize>,
+    pool: &SqlitePool,
+) -> Result<Json<Vec<Similar>>, BadRequest<String>> {
+    let metric: Metric = metric.unwrap_or("hamming").parse().map_err(|e| BadRequest(Some(e)))?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(BadRequest(Some(format!("The limit is from 1 to {}", MAX_LIMIT))));
+    }
+    let game = find_game(&ruleset.game).ok_or_else(|| BadRequest(Some(format!("Unknown game {}", ruleset.game))))?;
+    let mut candidates = candidates(indexed_rulesets(game.slug, None, pool).await, exclude);
+    candidates.extend(presets(game, pool).await);
+    Ok(Json(rank(ruleset, candidates, metric, limit)))
+}
+
+#[get("/api/similar/<code>?<metric>&<limit>")]
+async fn similar_to_code(
+    code: &str,
+    metric: Option<&str>,
+    limit: Option<usize>,
+    pool: &State<SqlitePool>,
+) -> Result<Json<Vec<Similar>>, BadRequest<String>> {
+    let ruleset = decode_ruleset(code, COMPARED_NAME.to_string()).map_err(|e| BadRequest(Some(e.to_string())))?;
+    similar(&ruleset, None, metric, limit, pool).await
+}
+
+/// Compare a ruleset given as `{"Technique": "ALLOWED", ...}`, like `POST /api/share`.
+#[post("/api/similar?<metric>&<limit>", data = "<ruleset>")]
+async fn similar_to_upload(
+    ruleset: Json<HashMap<String, String>>,
+    metric: Option<&str>,
+    limit: Option<usize>,
+    pool: &State<SqlitePool>,
+) -> Result<Json<Vec<Similar>>, BadRequest<String>> {
+    let game = ruleset.get("game").map_or(default_catalog().game, |g| g.as_str());
+    if find_game(game).is_none() {
+        return Err(BadRequest(Some(format!("Unknown game {}", game))));
+    }
+    let mut r = Ruleset::unspecified(game, COMPARED_NAME.to_string());
+    for (technique, value) in ruleset.iter().filter(|(k, _)| *k != "game") {
+        let allowed: IsAllowed = value.parse().map_err(|e: UserInputError| BadRequest(Some(e.to_string())))?;
+        if !r.set(technique, allowed) {
+            return Err(BadRequest(Some(format!("Unknown technique {}", technique))));
+        }
+    }
+    similar(&r, None, metric, limit, pool).await
+}
+
+#[get("/api/rulesets/<id>/similar?<metric>&<limit>")]
+async fn similar_to_saved(
+    id: u32,
+    metric: Option<&str>,
+    limit: Option<usize>,
+    pool: &State<SqlitePool>,
+) -> Result<Json<Vec<Similar>>, BadRequest<String>> {
+    let stored = get_stored_ruleset(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    similar(&stored.current().to_ruleset(), Some(id), metric, limit, pool).await
+}
+
+#[get("/api/weekly/similar?<metric>&<limit>")]
+async fn similar_to_weekly(
+    metric: Option<&str>,
+    limit: Option<usize>,
+    pool: &State<SqlitePool>,
+) -> Result<Json<Vec<Similar>>, BadRequest<String>> {
+    let id = materialize_weekly(pool).await.map_err(|e| BadRequest(Some(e)))?;
+    let stored = get_stored_ruleset(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    similar(&stored.current().to_ruleset(), Some(id), metric, limit, pool).await
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount(
+        "/",
+        rocket::routes![similar_to_code, similar_to_upload, similar_to_saved, similar_to_weekly],
+    )
+}
@@ -0,0 +1,183 @@
+//! The `/stats` page and its API, built on `crate::stats` from the rulesets stored in
+//! `ruleset_techniques`, one track at a time.
+
+use crate::games::{default_game, Game};
+use crate::stats::{history_stats, HistoryStats, PresetHistogram, Streak, TechniqueHistory, Week, WeekDistance};
+use crate::techniques::Ruleset;
+use crate::tracks::Track;
+use crate::web::get_presets;
+use crate::web::history::indexed_rulesets;
+use crate::web::weekly::{game_or_default, game_tabs, track_or_weekly, track_tabs, TrackTab};
+use rocket::response::status::NotFound;
+use rocket::serde::json::Json;
+use rocket::{get, Build, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// How many weeks to list as most and least like the baseline.
+const EXTREMES: usize = 5;
+
+async fn track_stats(track: &Track, pool: &SqlitePool) -> HistoryStats {
+    let game = track.game();
+    let weeks: Vec<Week> = indexed_rulesets(game.slug, Some(&track.slug), pool)
+        .await
+        .into_iter()
+        .map(|r| Week {
+            id: r.id,
+            day: r.day,
+            name: r.name,
+            ruleset: r.ruleset,
+        })
+        .collect();
+    let configured = track.allowed_chances().unwrap_or_else(|e| {
+        println!("Error reading the weights of track {}: {}", track.slug, e);
+        vec![]
+    });
+    let imported = get_presets(pool).await;
+    let presets: Vec<(&str, &Ruleset)> = game_presets(game)
+        .chain(imported.iter().filter(|r| r.game == game.slug).map(|r| (r.name.as_str(), r)))
+        .collect();
+    history_stats(game.catalog, &weeks, &configured, &presets, EXTREMES)
+}
+
+/// `game`'s built-in presets, its baseline first.
+fn game_presets(game: &Game) -> impl Iterator<Item = (&str, &Ruleset)> {
+    let baseline = game.baseline();
+    let (first, rest): (Vec<_>, Vec<_>) = game.presets.iter().partition(|p| std::ptr::eq(&p.ruleset, baseline));
+    first.into_iter().chain(rest).map(|p| (p.id.as_str(), &p.ruleset))
+}
+
+fn percent(rate: Option<f64>) -> String {
+    rate.map_or("-".to_string(), |r| format!("{:.0}%", r * 100.0))
+}
+
+#[derive(Serialize)]
+struct TechniqueRow {
+    technique: String,
+    observed: String,
+    configured: String,
+    /// Observed minus configured, in percentage points.
+    gap: String,
+    longest_allowed: Option<Streak>,
+    longest_banned: Option<Streak>,
+}
+
+impl From<TechniqueHistory> for TechniqueRow {
+    fn from(t: TechniqueHistory) -> Self {
+        Self {
+            observed: match t.observed_rate {
+                Some(_) => format!("{} ({} / {})", percent(t.observed_rate), t.allowed, t.weeks),
+                None => "-".to_string(),
+            },
+            configured: percent(t.configured_rate),
+            gap: match (t.observed_rate, t.configured_rate) {
+                (Some(o), Some(c)) => format!("{:+.0}", (o - c) * 100.0),
+                _ => "-".to_string(),
+            },
+            technique: t.technique,
+            longest_allowed: t.longest_allowed,
+            longest_banned: t.longest_banned,
+        }
+    }
+}
+
+/// A bar of a preset's histogram: how many weeks were `differences` off it.
+#[derive(Serialize)]
+struct Bar {
+    differences: usize,
+    weeks: usize,
+    /// Relative to the tallest bar.
+    percent: usize,
+}
+
+#[derive(Serialize)]
+struct HistogramRow {
+    preset: String,
+    mean: String,
+    bars: Vec<Bar>,
+}
+
+impl From<PresetHistogram> for HistogramRow {
+    fn from(h: PresetHistogram) -> Self {
+        let tallest = h.counts.iter().copied().max().unwrap_or(0).max(1);
+        Self {
+            preset: h.preset,
+            mean: h.mean.map_or("-".to_string(), |m| format!("{:.1}", m)),
+            bars: h
+                .counts
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| **n > 0)
+                .map(|(differences, weeks)| Bar {
+                    differences,
+                    weeks: *weeks,
+                    percent: weeks * 100 / tallest,
+                })
+                .collect(),
+        }
+    }
+}
+
+async fn render_stats(track: Track, pool: &SqlitePool) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        games: Vec<TrackTab>,
+        tracks: Vec<TrackTab>,
+        weeks: usize,
+        techniques: Vec<TechniqueRow>,
+        baseline: String,
+        most_like_baseline: Vec<WeekDistance>,
+        least_like_baseline: Vec<WeekDistance>,
+        presets: Vec<HistogramRow>,
+    }
+
+    let game = track.game();
+    let stats = track_stats(&track, pool).await;
+    Template::render(
+        "stats",
+        Ctx {
+            active_tab: "stats".to_string(),
+            games: game_tabs(game, "/stats"),
+            tracks: track_tabs(&track, |t| {
+                if t.is_weekly() {
+                    game.url("/stats")
+                } else {
+                    format!("{}?track={}", game.url("/stats"), t.slug)
+                }
+            }),
+            weeks: stats.weeks,
+            techniques: stats.techniques.into_iter().map(TechniqueRow::from).collect(),
+            baseline: stats.baseline,
+            most_like_baseline: stats.most_like_baseline,
+            least_like_baseline: stats.least_like_baseline,
+            presets: stats.presets.into_iter().map(HistogramRow::from).collect(),
+        },
+    )
+}
+
+#[get("/stats?<track>")]
+async fn stats(track: Option<&str>, pool: &State<SqlitePool>) -> Result<Template, NotFound<String>> {
+    Ok(render_stats(track_or_weekly(default_game(), track)?, pool).await)
+}
+
+#[get("/<game>/stats?<track>", rank = 2)]
+async fn game_stats(game: &str, track: Option<&str>, pool: &State<SqlitePool>) -> Result<Template, NotFound<String>> {
+    let game = game_or_default(Some(game))?;
+    Ok(render_stats(track_or_weekly(game, track)?, pool).await)
+}
+
+#[get("/api/tracks/<track>/stats?<game>")]
+async fn api_track_stats(
+    track: &str,
+    game: Option<&str>,
+    pool: &State<SqlitePool>,
+) -> Result<Json<HistoryStats>, NotFound<String>> {
+    let track = track_or_weekly(game_or_default(game)?, Some(track))?;
+    Ok(Json(track_stats(&track, pool).await))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![stats, game_stats, api_track_stats])
+}
@@ -0,0 +1,470 @@
+//! Run submissions and per-ruleset leaderboards. Anyone can submit a time; organizers verify or
+//! reject it from the admin page before it shows up in the standings.
+
+use crate::rules::{IsAllowed, UserInputError};
+use crate::techniques::{Catalog, Ruleset};
+use crate::web::admin::Admin;
+use crate::web::weekly::get_stored_ruleset;
+use chrono::Utc;
+use rocket::form::{Form, FromForm};
+use rocket::response::status::BadRequest;
+use rocket::response::Redirect;
+use rocket::{post, Build, Rocket, State};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Goals offered as suggestions on the submission form. Players can type anything.
+pub(crate) const GOAL_SUGGESTIONS: [&str; 2] = ["All Dungeons", "Any%"];
+
+const MAX_PLAYER_LEN: usize = 64;
+const MAX_GOAL_LEN: usize = 64;
+const MAX_NOTES_LEN: usize = 2000;
+
+#[derive(sqlx::FromRow, Serialize, Clone)]
+pub(crate) struct Submission {
+    pub(crate) id: i64,
+    pub(crate) ruleset_id: u32,
+    pub(crate) player: String,
+    pub(crate) time_seconds: u32,
+    pub(crate) goal: String,
+    pub(crate) vod_url: Option<String>,
+    pub(crate) notes: Option<String>,
+    pub(crate) status: String,
+    pub(crate) submitted_at: String,
+    pub(crate) reviewed_by: Option<String>,
+    pub(crate) reviewed_at: Option<String>,
+}
+
+pub(crate) const STATUS_PENDING: &str = "pending";
+pub(crate) const STATUS_VERIFIED: &str = "verified";
+pub(crate) const STATUS_REJECTED: &str = "rejected";
+
+const SUBMISSION_COLUMNS: &str =
+    "id, ruleset_id, player, time_seconds, goal, vod_url, notes, status, submitted_at, reviewed_by, reviewed_at";
+
+/// `goal` as it's stored: trimmed, and spelled like the first of `known` it matches ignoring ASCII
+/// case, so "all dungeons " and "All Dungeons" are the same goal.
+pub(crate) fn normalize_goal<'a>(goal: &str, known: impl IntoIterator<Item = &'a str>) -> String {
+    let goal = goal.trim();
+    known
+        .into_iter()
+        .find(|k| k.eq_ignore_ascii_case(goal))
+        .unwrap_or(goal)
+        .to_string()
+}
+
+/// `normalize_goal` against the goals already submitted, then the suggestions.
+pub(crate) async fn canonical_goal(goal: &str, pool: &SqlitePool) -> Result<String, sqlx::Error> {
+    let submitted: Option<(String,)> =
+        sqlx::query_as("SELECT goal FROM submissions WHERE LOWER(goal) = LOWER(?) ORDER BY id LIMIT 1")
+            .bind(goal.trim())
+            .fetch_optional(pool)
+            .await?;
+    Ok(normalize_goal(goal, submitted.iter().map(|(g,)| g.as_str()).chain(GOAL_SUGGESTIONS)))
+}
+
+/// Parse a finish time like `1:23:45` or `58:12` into seconds.
+pub(crate) fn parse_time(input: &str) -> Result<u32, UserInputError> {
+    let err = || UserInputError {
+        err: format!("Expected a time like 1:23:45 or 58:12, got {}", input),
+    };
+    let parts = input
+        .trim()
+        .split(':')
+        .map(|p| p.parse::<u32>().map_err(|_| err()))
+        .collect::<Result<Vec<u32>, _>>()?;
+    let (h, m, s) = match parts.as_slice() {
+        [m, s] => (0, *m, *s),
+        [h, m, s] if *m < 60 => (*h, *m, *s),
+        _ => return Err(err()),
+    };
+    if s >= 60 {
+        return Err(err());
+    }
+    let total = m
+        .checked_mul(60)
+        .and_then(|m| m.checked_add(s))
+        .and_then(|ms| h.checked_mul(3600).and_then(|h| h.checked_add(ms)))
+        .ok_or_else(err)?;
+    if total == 0 {
+        return Err(err());
+    }
+    Ok(total)
+}
+
+/// Check declared techniques against the ruleset the run was done under. Anything the ruleset
+/// bans is rejected, as are names that aren't techniques at all. Returns the techniques deduplicated
+/// and in catalog order.
+pub(crate) fn validate_techniques(ruleset: &Ruleset, used: &[String]) -> Result<Vec<String>, UserInputError> {
+    for t in used {
+        match ruleset.get(t) {
+            None => {
+                return Err(UserInputError {
+                    err: format!("Unknown technique {}", t),
+                })
+            }
+            Some(IsAllowed::DISALLOWED) => {
+                return Err(UserInputError {
+                    err: format!("{} was not allowed that week", t),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(ruleset
+        .catalog()
+        .techniques
+        .iter()
+        .filter(|t| used.iter().any(|u| u == *t))
+        .map(|t| t.to_string())
+        .collect())
+}
+
+/// Techniques a player may declare for a run under `ruleset`: everything not banned.
+fn declarable_techniques(ruleset: &Ruleset) -> Vec<String> {
+    ruleset
+        .techniques()
+        .filter(|(_, allowed)| *allowed != IsAllowed::DISALLOWED)
+        .map(|(t, _)| t.to_string())
+        .collect()
+}
+
+pub(crate) fn format_time(seconds: u32) -> String {
+    format!("{}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60)
+}
+
+#[derive(Serialize)]
+pub(crate) struct LeaderboardEntry {
+    id: i64,
+    place: usize,
+    player: String,
+    time: String,
+    /// Only set for http(s) urls, so nothing else ends up in an `href`.
+    vod_link: Option<String>,
+    notes: Option<String>,
+    techniques: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct GoalLeaderboard {
+    goal: String,
+    entries: Vec<LeaderboardEntry>,
+}
+
+/// Everything the `leaderboard` template needs for one ruleset.
+#[derive(Serialize)]
+pub(crate) struct LeaderboardContext {
+    ruleset_id: u32,
+    goals: Vec<GoalLeaderboard>,
+    pending: Vec<LeaderboardEntry>,
+    goal_suggestions: &'static [&'static str],
+    declarable_techniques: Vec<String>,
+}
+
+fn entry(s: Submission, place: usize, techniques: &HashMap<i64, Vec<String>>) -> LeaderboardEntry {
+    LeaderboardEntry {
+        techniques: techniques.get(&s.id).cloned().unwrap_or_default(),
+        id: s.id,
+        place,
+        player: s.player,
+        time: format_time(s.time_seconds),
+        vod_link: s
+            .vod_url
+            .filter(|u| u.starts_with("https://") || u.starts_with("http://")),
+        notes: s.notes,
+    }
+}
+
+/// Rank verified submissions per goal, fastest first. Tied times share a place.
+/// Expects `submissions` sorted by goal, then time.
+fn rank(submissions: Vec<Submission>, techniques: &HashMap<i64, Vec<String>>) -> Vec<GoalLeaderboard> {
+    let mut goals: Vec<GoalLeaderboard> = vec![];
+    let mut last_time = 0;
+    for s in submissions {
+        let new_goal = goals.last().is_none_or(|g| g.goal != s.goal);
+        if new_goal {
+            goals.push(GoalLeaderboard {
+                goal: s.goal.clone(),
+                entries: vec![],
+            });
+        }
+        let g = goals.last_mut().unwrap();
+        let place = match g.entries.last() {
+            Some(prev) if s.time_seconds == last_time => prev.place,
+            _ => g.entries.len() + 1,
+        };
+        last_time = s.time_seconds;
+        g.entries.push(entry(s, place, techniques));
+    }
+    goals
+}
+
+pub(crate) async fn get_submissions(
+    ruleset_id: u32,
+    status: Option<&str>,
+    pool: &SqlitePool,
+) -> Result<Vec<Submission>, sqlx::Error> {
+    match status {
+        Some(status) => {
+            sqlx::query_as(&format!(
+                "SELECT {} FROM submissions WHERE ruleset_id = ? AND status = ? ORDER BY goal, time_seconds, id",
+                SUBMISSION_COLUMNS
+            ))
+            .bind(ruleset_id)
+            .bind(status)
+            .fetch_all(pool)
+            .await
+        }
+        None => {
+            sqlx::query_as(&format!(
+                "SELECT {} FROM submissions WHERE ruleset_id = ? ORDER BY goal, time_seconds, id",
+                SUBMISSION_COLUMNS
+            ))
+            .bind(ruleset_id)
+            .fetch_all(pool)
+            .await
+        }
+    }
+}
+
+/// Declared techniques for every submission to a ruleset, keyed by submission id, in the order of
+/// `catalog`.
+pub(crate) async fn get_declared_techniques(
+    ruleset_id: u32,
+    catalog: &Catalog,
+    pool: &SqlitePool,
+) -> Result<HashMap<i64, Vec<String>>, sqlx::Error> {
+    let rows: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT st.submission_id, st.technique FROM submission_techniques st \
+         JOIN submissions s ON s.id = st.submission_id WHERE s.ruleset_id = ?")
+        .bind(ruleset_id)
+        .fetch_all(pool)
+        .await?;
+    let mut by_submission: HashMap<i64, Vec<String>> = HashMap::new();
+    for (id, technique) in rows {
+        by_submission.entry(id).or_default().push(technique);
+    }
+    for techniques in by_submission.values_mut() {
+        techniques.sort_by_key(|t| catalog.techniques.iter().position(|n| n == t));
+    }
+    Ok(by_submission)
+}
+
+pub(crate) async fn get_leaderboard(ruleset_id: u32, ruleset: &Ruleset, pool: &SqlitePool) -> LeaderboardContext {
+    let verified = get_submissions(ruleset_id, Some(STATUS_VERIFIED), pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching submissions: {:?}", e);
+            vec![]
+        });
+    let pending = get_submissions(ruleset_id, Some(STATUS_PENDING), pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching submissions: {:?}", e);
+            vec![]
+        });
+    let techniques = get_declared_techniques(ruleset_id, ruleset.catalog(), pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching declared techniques: {:?}", e);
+            HashMap::new()
+        });
+    LeaderboardContext {
+        ruleset_id,
+        goals: rank(verified, &techniques),
+        pending: pending.into_iter().map(|s| entry(s, 0, &techniques)).collect(),
+        goal_suggestions: &GOAL_SUGGESTIONS,
+        declarable_techniques: declarable_techniques(ruleset),
+    }
+}
+
+/// A submission as shown to organizers on the admin page, any status.
+#[derive(Serialize)]
+pub(crate) struct ReviewEntry {
+    #[serde(flatten)]
+    submission: Submission,
+    time: String,
+}
+
+pub(crate) async fn get_review_entries(ruleset_id: u32, pool: &SqlitePool) -> Vec<ReviewEntry> {
+    get_submissions(ruleset_id, None, pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching submissions: {:?}", e);
+            vec![]
+        })
+        .into_iter()
+        .map(|s| ReviewEntry {
+            time: format_time(s.time_seconds),
+            submission: s,
+        })
+        .collect()
+}
+
+fn non_empty(s: &Option<String>) -> Option<String> {
+    s.as_ref()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+#[derive(FromForm)]
+struct SubmissionForm {
+    player: String,
+    time: String,
+    goal: String,
+    vod_url: Option<String>,
+    notes: Option<String>,
+    techniques: Vec<String>,
+}
+
+impl SubmissionForm {
+    fn validate(&self) -> Result<u32, UserInputError> {
+        let too_long = |field: &str, max: usize| UserInputError {
+            err: format!("{} must be at most {} characters", field, max),
+        };
+        if self.player.trim().is_empty() || self.goal.trim().is_empty() {
+            return Err(UserInputError {
+                err: "Player and goal are required".to_string(),
+            });
+        }
+        if self.player.trim().len() > MAX_PLAYER_LEN {
+            return Err(too_long("Player", MAX_PLAYER_LEN));
+        }
+        if self.goal.trim().len() > MAX_GOAL_LEN {
+            return Err(too_long("Goal", MAX_GOAL_LEN));
+        }
+        if self.notes.as_ref().map_or(0, |n| n.len()) > MAX_NOTES_LEN {
+            return Err(too_long("Notes", MAX_NOTES_LEN));
+        }
+        parse_time(&self.time)
+    }
+}
+
+#[post("/history/<id>/submissions", data = "<form>")]
+async fn submit(
+    id: u32,
+    form: Form<SubmissionForm>,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    let stored = get_stored_ruleset(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    let time_seconds = form.validate().map_err(|e| BadRequest(Some(e.to_string())))?;
+    let techniques = validate_techniques(&stored.current().to_ruleset(), &form.techniques)
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    let goal = canonical_goal(&form.goal, pool).await.map_err(|e| BadRequest(Some(e.to_string())))?;
+
+    let mut tx = pool.begin().await.map_err(|e| BadRequest(Some(e.to_string())))?;
+    let submission_id = sqlx::query(
+        "INSERT INTO submissions (ruleset_id, player, time_seconds, goal, vod_url, notes, status, submitted_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+        .bind(id)
+        .bind(form.player.trim())
+        .bind(time_seconds)
+        .bind(goal)
+        .bind(non_empty(&form.vod_url))
+        .bind(non_empty(&form.notes))
+        .bind(STATUS_PENDING)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut tx)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?
+        .last_insert_rowid();
+    for technique in techniques {
+        sqlx::query("INSERT INTO submission_techniques (submission_id, technique) VALUES (?, ?)")
+            .bind(submission_id)
+            .bind(technique)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| BadRequest(Some(e.to_string())))?;
+    }
+    tx.commit().await.map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to(format!("/history/{}#leaderboard", id)))
+}
+
+#[derive(FromForm)]
+struct ReviewForm {
+    status: String,
+}
+
+#[post("/admin/submissions/<id>/review", data = "<form>")]
+async fn review(
+    id: i64,
+    form: Form<ReviewForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    if ![STATUS_PENDING, STATUS_VERIFIED, STATUS_REJECTED].contains(&form.status.as_str()) {
+        return Err(BadRequest(Some(format!("Unknown status {}", form.status))));
+    }
+    let submission: Submission = sqlx::query_as(&format!(
+        "SELECT {} FROM submissions WHERE id = ?",
+        SUBMISSION_COLUMNS
+    ))
+    .bind(id)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|_| BadRequest(Some("Unknown submission id".to_string())))?;
+    sqlx::query("UPDATE submissions SET status = ?, reviewed_by = ?, reviewed_at = ? WHERE id = ?")
+        .bind(&form.status)
+        .bind(&admin.name)
+        .bind(Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to(format!("/admin/rulesets/{}#submissions", submission.ruleset_id)))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![submit, review])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_time, normalize_goal, parse_time, validate_techniques, GOAL_SUGGESTIONS};
+    use crate::rules::NMGRules;
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(Ok(5025), parse_time("1:23:45"));
+        assert_eq!(Ok(3492), parse_time("58:12"));
+        assert_eq!(Ok(3492), parse_time(" 0:58:12 "));
+        assert!(parse_time("12").is_err());
+        assert!(parse_time("1:60").is_err());
+        assert!(parse_time("1:60:00").is_err());
+        assert!(parse_time("0:00").is_err());
+        assert!(parse_time("a:bc").is_err());
+        assert_eq!(Ok(4500), parse_time("75:00"));
+        assert!(parse_time("99999999:00").is_err());
+        assert!(parse_time("9999999:00:00").is_err());
+    }
+
+    #[test]
+    fn test_normalize_goal() {
+        assert_eq!("All Dungeons", normalize_goal("  all DUNGEONS ", GOAL_SUGGESTIONS));
+        assert_eq!("any% nmg", normalize_goal("Any% NMG", ["any% nmg", "Any%"]));
+        assert_eq!("Pedestal", normalize_goal(" Pedestal", GOAL_SUGGESTIONS));
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!("1:23:45", format_time(5025));
+        assert_eq!("0:58:12", format_time(3492));
+    }
+
+    #[test]
+    fn test_validate_techniques() {
+        let used = vec!["Hover".to_string(), "FakeFlippers".to_string(), "Hover".to_string()];
+        assert_eq!(
+            Ok(vec!["FakeFlippers".to_string(), "Hover".to_string()]),
+            validate_techniques(&NMGRules, &used)
+        );
+        // unspecified in NMG, so fine to declare
+        assert!(validate_techniques(&NMGRules, &["HeraPot".to_string()]).is_ok());
+        assert_eq!(
+            "OverworldClipping was not allowed that week",
+            validate_techniques(&NMGRules, &["OverworldClipping".to_string()]).unwrap_err().to_string()
+        );
+        assert!(validate_techniques(&NMGRules, &["OverworldClip".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,629 @@
+//! Supplemental rules: rulings on techniques outside the randomized catalog, like Spooky Action.
+//! They live in `supplemental_rules`, one row per version of a rule with the periods it's in force
+//! for, and organizers edit them at `/admin/supplemental`; corrections and deletions land in
+//! `supplemental_audit_log`. A game starts out with the rules in its `supplemental.yaml`. Saved
+//! rulesets keep the set that was in force when they were rolled, so `/history/<id>` shows the
+//! complete rules as they were at the time.
+//!
+//! A rule can be made randomizable, for special events: it's then added to its game's catalog as a
+//! technique, so templates can weight it, presets can set it, and it's diffed and shared like any
+//! other, starting from its ruling. Catalogs are loaded once, so that takes effect at the next
+//! restart, and randomizable versions can't be dated to start or end later on.
+
+use crate::games::{default_game, games, read_supplemental, Game, SupplementalRule};
+use crate::rules::IsAllowed;
+use crate::techniques::{catalog, game_slugs, init_catalogs};
+use crate::web::admin::Admin;
+use crate::web::weekly::{current_period_day, game_or_default, game_tabs, RulesetRecord, TrackTab};
+use chrono::{Datelike, NaiveDate, Utc};
+use rocket::form::{Form, FromForm};
+use rocket::response::status::{BadRequest, NotFound};
+use rocket::response::Redirect;
+use rocket::{get, post, Build, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// The `effective_from` of rules that have always been in force.
+const SINCE_THE_START: i32 = 1;
+
+#[derive(sqlx::FromRow, Serialize)]
+struct SupplementalRecord {
+    id: i64,
+    game: String,
+    name: String,
+    description: String,
+    allowed: String,
+    effective_from: i32,
+    effective_until: Option<i32>,
+    randomizable: bool,
+    created_by: String,
+    created_at: String,
+}
+
+const SUPPLEMENTAL_COLUMNS: &str = "id, game, name, description, allowed, effective_from, effective_until, \
+    randomizable, created_by, created_at";
+
+/// Rules are listed in the order they were first added, whatever version is in force.
+const RULE_ORDER: &str = "(SELECT MIN(f.id) FROM supplemental_rules f WHERE f.game = s.game AND f.name = s.name), \
+    s.effective_from";
+
+impl SupplementalRecord {
+    fn to_rule(&self) -> SupplementalRule {
+        SupplementalRule {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            allowed: self.allowed.parse().unwrap_or(IsAllowed::UNSPECIFIED),
+            randomizable: self.randomizable,
+        }
+    }
+
+    fn in_force(&self, day: i32) -> bool {
+        self.effective_from <= day && self.effective_until.is_none_or(|until| day < until)
+    }
+
+    /// The fields an organizer sets, as they'd read them, for the audit log.
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("name", self.name.clone()),
+            ("description", self.description.clone()),
+            ("allowed", self.allowed.clone()),
+            ("from", if self.effective_from == SINCE_THE_START { String::new() } else { date_string(self.effective_from) }),
+            ("until", self.effective_until.map(date_string).unwrap_or_default()),
+            ("randomizable", self.randomizable.to_string()),
+        ]
+    }
+}
+
+fn date_string(day: i32) -> String {
+    NaiveDate::from_num_days_from_ce(day).format("%Y-%m-%d").to_string()
+}
+
+/// `game`'s supplemental rules in force for the period starting on `day`.
+pub(crate) async fn rules_in_force(game: &str, day: i32, pool: &SqlitePool) -> Vec<SupplementalRule> {
+    let records: Vec<SupplementalRecord> = sqlx::query_as(&format!(
+        "SELECT {} FROM supplemental_rules s \
+         WHERE s.game = ? AND s.effective_from <= ? AND (s.effective_until IS NULL OR s.effective_until > ?) \
+         ORDER BY {}",
+        SUPPLEMENTAL_COLUMNS, RULE_ORDER
+    ))
+    .bind(game)
+    .bind(day)
+    .bind(day)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_else(|e| {
+        println!("Error fetching supplemental rules: {:?}", e);
+        vec![]
+    });
+    records.iter().map(SupplementalRecord::to_rule).collect()
+}
+
+/// The supplemental rules in force for a saved ruleset: the ones saved with it, or for rulesets
+/// saved before that, the ones in force on its day.
+pub(crate) async fn ruleset_supplemental(record: &RulesetRecord, pool: &SqlitePool) -> Vec<SupplementalRule> {
+    if let Some(saved) = &record.supplemental {
+        match serde_json::from_str(saved) {
+            Ok(rules) => return rules,
+            Err(e) => println!("Error reading supplemental rules saved with ruleset {}: {}", record.id, e),
+        }
+    }
+    rules_in_force(&record.game, record.day, pool).await
+}
+
+/// Give every game whose rules haven't been seeded yet the ones from its `supplemental.yaml`, in
+/// force from the start. Games are only seeded once, so deleting all of a game's rules sticks.
+async fn seed_supplemental_rules(pool: &SqlitePool) -> Result<(), String> {
+    for game in game_slugs().map_err(|e| e.to_string())? {
+        let seeded: Option<(String,)> = sqlx::query_as("SELECT game FROM supplemental_seeds WHERE game = ?")
+            .bind(game)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        if seeded.is_some() {
+            continue;
+        }
+        let rules = read_supplemental(game).map_err(|e| e.to_string())?;
+        let now = Utc::now().to_rfc3339();
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+        for rule in &rules {
+            sqlx::query(
+                "INSERT INTO supplemental_rules (game, name, description, allowed, effective_from, randomizable, \
+                 created_by, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+                .bind(game)
+                .bind(&rule.name)
+                .bind(&rule.description)
+                .bind(rule.allowed.as_str())
+                .bind(SINCE_THE_START)
+                .bind(rule.randomizable)
+                .bind("supplemental.yaml")
+                .bind(&now)
+                .execute(&mut tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        sqlx::query("INSERT INTO supplemental_seeds (game, seeded_at) VALUES (?, ?)")
+            .bind(game)
+            .bind(&now)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+        println!("Added {} supplemental rules for {} from supplemental.yaml", rules.len(), game);
+    }
+    Ok(())
+}
+
+/// Seed the supplemental rules if need be, then load the technique catalogs with the ones that are
+/// randomizable this period. Has to run before anything looks at a game.
+pub(crate) async fn init_supplemental_rules(pool: &SqlitePool) -> Result<(), String> {
+    seed_supplemental_rules(pool).await?;
+    let rows: Vec<(String, String, String)> = sqlx::query_as(&format!(
+        "SELECT s.game, s.name, s.allowed FROM supplemental_rules s \
+         WHERE s.randomizable AND s.effective_from <= ? AND (s.effective_until IS NULL OR s.effective_until > ?) \
+         ORDER BY {}",
+        RULE_ORDER
+    ))
+    .bind(current_period_day())
+    .bind(current_period_day())
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut randomizable: HashMap<String, Vec<(String, IsAllowed)>> = HashMap::new();
+    for (game, name, allowed) in rows {
+        let allowed = allowed.parse().map_err(|e| format!("Supplemental rule {}: {}", name, e))?;
+        randomizable.entry(game).or_default().push((name, allowed));
+    }
+    init_catalogs(&randomizable).map_err(|e| e.to_string())
+}
+
+async fn render_supplemental(game: &Game, pool: &SqlitePool) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        games: Vec<TrackTab>,
+        tracks: Vec<TrackTab>,
+        supplemental: Vec<SupplementalRule>,
+    }
+
+    Template::render(
+        "supplemental",
+        Ctx {
+            active_tab: "supplemental".to_string(),
+            games: game_tabs(game, "/supplemental"),
+            tracks: vec![],
+            supplemental: rules_in_force(game.slug, current_period_day() as i32, pool).await,
+        },
+    )
+}
+
+#[get("/supplemental")]
+async fn supplemental(pool: &State<SqlitePool>) -> Template {
+    render_supplemental(default_game(), pool).await
+}
+
+#[get("/<game>/supplemental", rank = 2)]
+async fn game_supplemental(game: &str, pool: &State<SqlitePool>) -> Result<Template, NotFound<String>> {
+    Ok(render_supplemental(game_or_default(Some(game))?, pool).await)
+}
+
+fn parse_allowed(allowed: &str) -> Result<IsAllowed, String> {
+    match allowed.parse() {
+        Ok(IsAllowed::UNSPECIFIED) | Err(_) => Err(format!("Expected ALLOWED or DISALLOWED, got {}", allowed)),
+        Ok(a) => Ok(a),
+    }
+}
+
+/// A date from a form as a day number. Blank means `blank`.
+fn parse_day(date: &str, blank: Option<i32>) -> Result<Option<i32>, String> {
+    if date.trim().is_empty() {
+        return Ok(blank);
+    }
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map(|d| Some(d.num_days_from_ce()))
+        .map_err(|_| format!("Expected a date like 2022-04-03, got {}", date))
+}
+
+#[derive(FromForm)]
+struct RuleForm {
+    game: String,
+    name: String,
+    description: String,
+    allowed: String,
+    effective_from: String,
+    effective_until: String,
+    randomizable: bool,
+}
+
+impl RuleForm {
+    /// Validate the form for a rule of `game`, returning the ruling and the days it's in force from
+    /// and until.
+    fn validate(&self, game: &str) -> Result<(IsAllowed, i32, Option<i32>), String> {
+        if self.name.trim().is_empty() {
+            return Err("Name is required".to_string());
+        }
+        let allowed = parse_allowed(&self.allowed)?;
+        if self.randomizable {
+            check_randomizable(game, self.name.trim())?;
+        }
+        let from = parse_day(&self.effective_from, Some(SINCE_THE_START))?.unwrap();
+        let until = parse_day(&self.effective_until, None)?;
+        if until.is_some_and(|until| until <= from) {
+            return Err("The rule ends before it starts".to_string());
+        }
+        if self.randomizable {
+            check_takes_effect_now(from, until)?;
+        }
+        Ok((allowed, from, until))
+    }
+}
+
+/// Whether a rule called `name` can be randomized in `game`: it can't share a name with one of the
+/// game's techniques.
+fn check_randomizable(game: &str, name: &str) -> Result<(), String> {
+    let catalog = catalog(game).ok_or_else(|| format!("Unknown game {}", game))?;
+    if name == "name" || name == "game" || (catalog.id(name).is_some() && !catalog.supplemental().contains(&name)) {
+        return Err(format!("{} can't be randomized, since {} has a technique with that name", name, game));
+    }
+    Ok(())
+}
+
+/// Randomizable rules are read into the catalogs once, at startup, so one can't start or stop on
+/// a later date: the catalogs would go on rolling the set from the day the server started.
+fn check_takes_effect_now(from: i32, until: Option<i32>) -> Result<(), String> {
+    let today = current_period_day() as i32;
+    if from > today || until.is_some_and(|until| until > today) {
+        return Err("A randomizable rule can't start or end on a later date, since it only takes effect when the \
+                    server restarts. Save it on the day it should change, then restart the server"
+            .to_string());
+    }
+    Ok(())
+}
+
+async fn record_rule_audit(rule_id: i64, actor: &str, action: &str, details: &str, pool: &SqlitePool) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO supplemental_audit_log (rule_id, actor, action, details, created_at) VALUES (?, ?, ?, ?, ?)")
+        .bind(rule_id)
+        .bind(actor)
+        .bind(action)
+        .bind(details)
+        .bind(Utc::now().to_rfc3339())
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn admin_url(game: &str) -> String {
+    format!("/admin/supplemental?game={}", game)
+}
+
+async fn get_record(id: i64, pool: &SqlitePool) -> Result<SupplementalRecord, String> {
+    sqlx::query_as(&format!("SELECT {} FROM supplemental_rules WHERE id = ?", SUPPLEMENTAL_COLUMNS))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            println!("Error fetching supplemental rule: {:?}", e);
+            "Unknown supplemental rule id".to_string()
+        })
+}
+
+#[get("/admin/supplemental?<game>")]
+async fn admin_supplemental(game: Option<&str>, admin: Admin, pool: &State<SqlitePool>) -> Result<Template, NotFound<String>> {
+    #[derive(Serialize)]
+    struct RuleView {
+        #[serde(flatten)]
+        record: SupplementalRecord,
+        from_date: String,
+        until_date: String,
+        in_force: bool,
+    }
+
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        admin: String,
+        game: &'static str,
+        games: Vec<TrackTab>,
+        tracks: Vec<TrackTab>,
+        rules: Vec<RuleView>,
+    }
+
+    let game = game_or_default(game)?;
+    let records: Vec<SupplementalRecord> = sqlx::query_as(&format!(
+        "SELECT {} FROM supplemental_rules s WHERE s.game = ? ORDER BY {}",
+        SUPPLEMENTAL_COLUMNS, RULE_ORDER
+    ))
+    .bind(game.slug)
+    .fetch_all(&**pool)
+    .await
+    .unwrap_or_else(|e| {
+        println!("Error fetching supplemental rules: {:?}", e);
+        vec![]
+    });
+    let today = current_period_day() as i32;
+    Ok(Template::render(
+        "admin_supplemental",
+        Ctx {
+            active_tab: "admin".to_string(),
+            admin: admin.name,
+            game: game.slug,
+            games: games()
+                .iter()
+                .map(|g| TrackTab {
+                    name: g.name.clone(),
+                    url: admin_url(g.slug),
+                    active: g.slug == game.slug,
+                })
+                .collect(),
+            tracks: vec![],
+            rules: records
+                .into_iter()
+                .map(|record| RuleView {
+                    from_date: if record.effective_from == SINCE_THE_START {
+                        String::new()
+                    } else {
+                        date_string(record.effective_from)
+                    },
+                    until_date: record.effective_until.map(date_string).unwrap_or_default(),
+                    in_force: record.in_force(today),
+                    record,
+                })
+                .collect(),
+        },
+    ))
+}
+
+#[post("/admin/supplemental", data = "<form>")]
+async fn create_rule(form: Form<RuleForm>, admin: Admin, pool: &State<SqlitePool>) -> Result<Redirect, BadRequest<String>> {
+    let game = game_or_default(Some(&form.game)).map_err(|e| BadRequest(Some(e.0)))?;
+    let (allowed, from, until) = form.validate(game.slug).map_err(|e| BadRequest(Some(e)))?;
+    sqlx::query(
+        "INSERT INTO supplemental_rules (game, name, description, allowed, effective_from, effective_until, \
+         randomizable, created_by, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
+        .bind(game.slug)
+        .bind(form.name.trim())
+        .bind(form.description.trim())
+        .bind(allowed.as_str())
+        .bind(from)
+        .bind(until)
+        .bind(form.randomizable)
+        .bind(&admin.name)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to(admin_url(game.slug)))
+}
+
+/// Correct a version of a rule in place. Rulesets already saved keep the version they were saved
+/// with.
+#[post("/admin/supplemental/<id>", data = "<form>")]
+async fn update_rule(
+    id: i64,
+    form: Form<RuleForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    let record = get_record(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    // the rule stays with its game, whatever the form says
+    let (allowed, from, until) = form.validate(&record.game).map_err(|e| BadRequest(Some(e)))?;
+    sqlx::query(
+        "UPDATE supplemental_rules SET name = ?, description = ?, allowed = ?, effective_from = ?, \
+         effective_until = ?, randomizable = ? WHERE id = ?")
+        .bind(form.name.trim())
+        .bind(form.description.trim())
+        .bind(allowed.as_str())
+        .bind(from)
+        .bind(until)
+        .bind(form.randomizable)
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    let old_fields = record.fields();
+    let corrected = SupplementalRecord {
+        name: form.name.trim().to_string(),
+        description: form.description.trim().to_string(),
+        allowed: allowed.as_str().to_string(),
+        effective_from: from,
+        effective_until: until,
+        randomizable: form.randomizable,
+        ..record
+    };
+    let details = old_fields
+        .into_iter()
+        .zip(corrected.fields())
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| format!("{}: {} -> {}", field, old, new))
+        .collect::<Vec<_>>()
+        .join("\n");
+    record_rule_audit(id, &admin.name, "edit", &details, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    Ok(Redirect::to(admin_url(&corrected.game)))
+}
+
+#[derive(FromForm)]
+struct RevisionForm {
+    description: String,
+    allowed: String,
+    effective_from: String,
+    randomizable: bool,
+}
+
+/// Change a ruling from a date on: the current version ends there and a new one starts.
+#[post("/admin/supplemental/<id>/revise", data = "<form>")]
+async fn revise_rule(
+    id: i64,
+    form: Form<RevisionForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    let record = get_record(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    let allowed = parse_allowed(&form.allowed).map_err(|e| BadRequest(Some(e)))?;
+    if form.randomizable {
+        check_randomizable(&record.game, &record.name).map_err(|e| BadRequest(Some(e)))?;
+    }
+    let from = parse_day(&form.effective_from, None)
+        .map_err(|e| BadRequest(Some(e)))?
+        .ok_or_else(|| BadRequest(Some("A revision needs the date it starts".to_string())))?;
+    if !record.in_force(from) {
+        return Err(BadRequest(Some(format!("{} isn't in force on {}", record.name, date_string(from)))));
+    }
+    if from == record.effective_from {
+        return Err(BadRequest(Some("To change a version from its first day, edit it instead".to_string())));
+    }
+    if record.randomizable || form.randomizable {
+        check_takes_effect_now(from, None).map_err(|e| BadRequest(Some(e)))?;
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| BadRequest(Some(e.to_string())))?;
+    sqlx::query("UPDATE supplemental_rules SET effective_until = ? WHERE id = ?")
+        .bind(from)
+        .bind(id)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    sqlx::query(
+        "INSERT INTO supplemental_rules (game, name, description, allowed, effective_from, effective_until, \
+         randomizable, created_by, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
+        .bind(&record.game)
+        .bind(&record.name)
+        .bind(form.description.trim())
+        .bind(allowed.as_str())
+        .bind(from)
+        .bind(record.effective_until)
+        .bind(form.randomizable)
+        .bind(&admin.name)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut tx)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    tx.commit().await.map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to(admin_url(&record.game)))
+}
+
+#[post("/admin/supplemental/<id>/delete")]
+async fn delete_rule(id: i64, admin: Admin, pool: &State<SqlitePool>) -> Result<Redirect, BadRequest<String>> {
+    let record = get_record(id, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    sqlx::query("DELETE FROM supplemental_rules WHERE id = ?")
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    let details = record
+        .fields()
+        .into_iter()
+        .map(|(field, value)| format!("{}: {}", field, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    record_rule_audit(id, &admin.name, "delete", &details, pool).await.map_err(|e| BadRequest(Some(e)))?;
+    Ok(Redirect::to(admin_url(&record.game)))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount(
+        "/",
+        rocket::routes![
+            supplemental,
+            game_supplemental,
+            admin_supplemental,
+            create_rule,
+            update_rule,
+            revise_rule,
+            delete_rule
+        ],
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_allowed, seed_supplemental_rules, RuleForm, SupplementalRecord};
+    use crate::rules::IsAllowed;
+    use sqlx::migrate::Migrator;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::path::Path;
+
+    fn form(from: &str, until: &str) -> RuleForm {
+        RuleForm {
+            game: "alttp".to_string(),
+            name: "Super Bunny".to_string(),
+            description: String::new(),
+            allowed: "DISALLOWED".to_string(),
+            effective_from: from.to_string(),
+            effective_until: until.to_string(),
+            randomizable: false,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(Ok((IsAllowed::DISALLOWED, 1, None)), form("", "").validate("alttp"));
+        let (_, from, until) = form("2026-10-18", "2026-11-01").validate("alttp").unwrap();
+        assert_eq!(Some(from + 14), until);
+        assert!(form("2026-10-18", "2026-10-18").validate("alttp").is_err());
+        assert!(form("October", "").validate("alttp").is_err());
+        assert!(parse_allowed("UNSPECIFIED").is_err());
+
+        let mut randomized = form("", "");
+        randomized.randomizable = true;
+        assert!(randomized.validate("alttp").is_ok());
+        randomized.name = "Hover".to_string();
+        assert!(randomized.validate("alttp").is_err());
+        // checked against the game the rule is for, not the form's
+        assert!(randomized.validate("sm").is_ok());
+        // the catalogs only pick it up at a restart, so it can't be dated to change later
+        randomized.name = "Super Bunny".to_string();
+        randomized.effective_until = "2999-01-01".to_string();
+        assert!(randomized.validate("alttp").is_err());
+        randomized.effective_until = String::new();
+        randomized.effective_from = "2999-01-01".to_string();
+        assert!(randomized.validate("alttp").is_err());
+        randomized.effective_from = "2026-01-01".to_string();
+        assert!(randomized.validate("alttp").is_ok());
+    }
+
+    #[rocket::async_test]
+    async fn test_seed_once() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        Migrator::new(Path::new("migrations")).await.unwrap().run(&pool).await.unwrap();
+        let count = || async {
+            let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM supplemental_rules WHERE game = 'alttp'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            count
+        };
+        seed_supplemental_rules(&pool).await.unwrap();
+        assert!(count().await > 0);
+        // an organizer deleting all of a game's rules isn't undone at the next start
+        sqlx::query("DELETE FROM supplemental_rules").execute(&pool).await.unwrap();
+        seed_supplemental_rules(&pool).await.unwrap();
+        assert_eq!(0, count().await);
+    }
+
+    #[test]
+    fn test_in_force() {
+        let record = SupplementalRecord {
+            id: 1,
+            game: "alttp".to_string(),
+            name: "Water Walk".to_string(),
+            description: String::new(),
+            allowed: "ALLOWED".to_string(),
+            effective_from: 10,
+            effective_until: Some(17),
+            randomizable: false,
+            created_by: "test".to_string(),
+            created_at: String::new(),
+        };
+        assert!(!record.in_force(3));
+        assert!(record.in_force(10));
+        assert!(!record.in_force(17));
+        assert_eq!(IsAllowed::ALLOWED, record.to_rule().allowed);
+    }
+}
@@ -0,0 +1,80 @@
+//! Checking weight files: the upload page and its API.
+
+use crate::validate::{has_errors, validate_weights, Diagnostic};
+use rocket::form::{Form, FromForm};
+use rocket::fs::TempFile;
+use rocket::serde::json::Json;
+use rocket::{get, post, Build, Rocket};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Validation {
+    valid: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validation {
+    fn new(yaml: &str, strict: bool) -> Self {
+        let diagnostics = validate_weights(yaml, strict);
+        Self {
+            valid: !has_errors(&diagnostics),
+            diagnostics,
+        }
+    }
+}
+
+fn upload_page(yaml: String, strict: bool, validation: Option<Validation>) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        yaml: String,
+        strict: bool,
+        validation: Option<Validation>,
+    }
+
+    Template::render(
+        "submit_weights",
+        Ctx {
+            active_tab: "".to_string(),
+            yaml,
+            strict,
+            validation,
+        },
+    )
+}
+
+#[get("/upload")]
+async fn upload_form() -> Template {
+    upload_page(String::new(), false, None)
+}
+
+#[derive(FromForm)]
+struct Upload<'f> {
+    upload: Option<TempFile<'f>>,
+    yaml: String,
+    strict: bool,
+}
+
+#[post("/upload", data = "<form>")]
+async fn upload(form: Form<Upload<'_>>) -> Template {
+    // a chosen file wins over the text box
+    let file = match &form.upload {
+        Some(TempFile::File { path, .. }) => rocket::tokio::fs::read_to_string(path).await.ok(),
+        Some(TempFile::Buffered { content }) if !content.is_empty() => Some(content.to_string()),
+        _ => None,
+    };
+    let yaml = file.unwrap_or_else(|| form.yaml.clone());
+    let validation = Validation::new(&yaml, form.strict);
+    upload_page(yaml, form.strict, Some(validation))
+}
+
+/// Check a weights file sent as the request body. `?strict=true` makes unknown keys errors.
+#[post("/api/validate?<strict>", data = "<yaml>")]
+async fn api_validate(yaml: String, strict: Option<bool>) -> Json<Validation> {
+    Json(Validation::new(&yaml, strict.unwrap_or(false)))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", rocket::routes![upload_form, upload, api_validate])
+}
@@ -0,0 +1,187 @@
+//! Admin pages for configuring outbound webhooks, and the glue that announces a saved ruleset to
+//! them. Delivery itself lives in `crate::webhooks`.
+
+use crate::web::admin::Admin;
+use crate::web::public_url;
+use crate::web::weekly::{get_previous_stored_ruleset, get_stored_ruleset};
+use crate::webhooks::{announce, RulesetPayload, Webhook, WEBHOOK_COLUMNS};
+use chrono::Utc;
+use rocket::form::{Form, FromForm};
+use rocket::response::status::BadRequest;
+use rocket::response::Redirect;
+use rocket::{get, post, Build, Rocket, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// How many recent deliveries the admin page shows.
+const DELIVERY_LOG_LENGTH: u32 = 50;
+
+/// The webhook payload for saved ruleset `id`, diffed against the ruleset published before it.
+pub(crate) async fn ruleset_payload(id: u32, event: &str, pool: &SqlitePool) -> Result<RulesetPayload, String> {
+    let stored = get_stored_ruleset(id, pool).await?;
+    let ruleset = stored.current().to_ruleset();
+    let payload = RulesetPayload::new(
+        event,
+        id,
+        stored.record.name.clone(),
+        format!("{}/history/{}", public_url(), id),
+        stored.record.note.clone(),
+        &ruleset,
+    );
+    Ok(match get_previous_stored_ruleset(id, pool).await {
+        Some(previous) => payload.with_previous(
+            previous.record.id,
+            previous.record.name.clone(),
+            &previous.current().to_ruleset(),
+            &ruleset,
+        ),
+        None => payload,
+    })
+}
+
+/// Tell every configured webhook about saved ruleset `id`. Failures are logged, never returned:
+/// a webhook being down shouldn't break the page that triggered it.
+pub(crate) async fn announce_ruleset(id: u32, event: &str, pool: &SqlitePool) {
+    match ruleset_payload(id, event, pool).await {
+        Ok(payload) => announce(&payload, pool).await,
+        Err(e) => println!("Error building webhook payload for {}: {}", id, e),
+    }
+}
+
+#[derive(sqlx::FromRow, Serialize)]
+struct DeliveryEntry {
+    id: i64,
+    url: String,
+    event: String,
+    ruleset_id: u32,
+    status: String,
+    attempts: u32,
+    last_status_code: Option<u16>,
+    last_error: Option<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+/// A webhook as shown on the admin page; the secret is only ever shown in part.
+#[derive(Serialize)]
+struct WebhookView {
+    #[serde(flatten)]
+    webhook: Webhook,
+    secret_hint: String,
+}
+
+impl From<Webhook> for WebhookView {
+    fn from(mut webhook: Webhook) -> Self {
+        let secret_hint = format!("{}…", webhook.secret.chars().take(4).collect::<String>());
+        webhook.secret = String::new();
+        Self { webhook, secret_hint }
+    }
+}
+
+#[get("/admin/webhooks")]
+async fn admin_webhooks(admin: Admin, pool: &State<SqlitePool>) -> Template {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        admin: String,
+        webhooks: Vec<WebhookView>,
+        deliveries: Vec<DeliveryEntry>,
+    }
+
+    let webhooks: Vec<Webhook> = sqlx::query_as(&format!("SELECT {} FROM webhooks ORDER BY id", WEBHOOK_COLUMNS))
+        .fetch_all(&**pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching webhooks: {:?}", e);
+            vec![]
+        });
+    let deliveries = sqlx::query_as(
+        "SELECT d.id, w.url, d.event, d.ruleset_id, d.status, d.attempts, d.last_status_code, d.last_error, \
+         d.created_at, d.updated_at FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id \
+         ORDER BY d.id DESC LIMIT ?")
+        .bind(DELIVERY_LOG_LENGTH)
+        .fetch_all(&**pool)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Error fetching webhook deliveries: {:?}", e);
+            vec![]
+        });
+    Template::render(
+        "admin_webhooks",
+        Ctx {
+            active_tab: "admin".to_string(),
+            admin: admin.name,
+            webhooks: webhooks.into_iter().map(WebhookView::from).collect(),
+            deliveries,
+        },
+    )
+}
+
+#[derive(FromForm)]
+struct WebhookForm {
+    url: String,
+    secret: String,
+}
+
+#[post("/admin/webhooks", data = "<form>")]
+async fn create_webhook(
+    form: Form<WebhookForm>,
+    admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    let url = form.url.trim();
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(BadRequest(Some(format!("Expected an http(s) url, got {}", url))));
+    }
+    if form.secret.trim().is_empty() {
+        return Err(BadRequest(Some("A signing secret is required".to_string())));
+    }
+    sqlx::query("INSERT INTO webhooks (url, secret, created_by, created_at) VALUES (?, ?, ?, ?)")
+        .bind(url)
+        .bind(form.secret.trim())
+        .bind(&admin.name)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to("/admin/webhooks"))
+}
+
+#[derive(FromForm)]
+struct ActiveForm {
+    active: bool,
+}
+
+#[post("/admin/webhooks/<id>/active", data = "<form>")]
+async fn set_active(
+    id: i64,
+    form: Form<ActiveForm>,
+    _admin: Admin,
+    pool: &State<SqlitePool>,
+) -> Result<Redirect, BadRequest<String>> {
+    sqlx::query("UPDATE webhooks SET active = ? WHERE id = ?")
+        .bind(form.active)
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to("/admin/webhooks"))
+}
+
+#[post("/admin/webhooks/<id>/delete")]
+async fn delete_webhook(id: i64, _admin: Admin, pool: &State<SqlitePool>) -> Result<Redirect, BadRequest<String>> {
+    sqlx::query("DELETE FROM webhooks WHERE id = ?")
+        .bind(id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    Ok(Redirect::to("/admin/webhooks"))
+}
+
+pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount(
+        "/",
+        rocket::routes![admin_webhooks, create_webhook, set_active, delete_webhook],
+    )
+}
@@ -1,13 +1,28 @@
-use crate::rules::{get_weekly_ruleset, most_recent_sunday};
-use crate::techniques::{Ruleset,  TECHNIQUE_NAMES};
+use crate::games::{default_game, find_game, games, Game, SupplementalRule};
+use crate::rules::{most_recent_sunday, IsAllowed};
+use crate::share::encode_ruleset;
+use crate::similarity::Similar;
+use crate::techniques::{default_catalog, Ruleset};
+use crate::tracks::{default_track, find_track, tracks, Track, DEFAULT_TRACK};
+use crate::web::export::{Export, Exported, Negotiated};
+use crate::web::history::{index_ruleset, ruleset_values};
+use crate::web::public_url;
+use crate::web::similar::closest_past_rulesets;
+use crate::web::notes::{get_route_notes, RouteNotesContext};
+use crate::web::submissions::{get_leaderboard, LeaderboardContext};
+use crate::web::supplemental::{rules_in_force, ruleset_supplemental};
+use crate::web::webhooks::announce_ruleset;
+use crate::webhooks::EVENT_PUBLISHED;
 use chrono::{Date, Datelike, Month, Utc};
 use rocket::response::status::NotFound;
+use rocket::serde::json::Json;
 use rocket::{get, Build, Rocket, State};
 use rocket_dyn_templates::Template;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env::var;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use sqlx::{SqlitePool, Row};
 
@@ -31,14 +46,6 @@ fn month_from_u32(w: u32) -> Month {
     }
 }
 
-#[derive(Serialize)]
-struct WeeklyRuleset<'a> {
-    week_of: String,
-    ruleset: &'a Ruleset,
-    technique_names: &'static [&'static str],
-    active_tab: String,
-}
-
 #[derive(Serialize)]
 struct SerializedRuleset {
     day: i32,
@@ -46,54 +53,145 @@ struct SerializedRuleset {
     technique_names: Vec<String>,
 }
 
+impl SerializedRuleset {
+    fn new(day: i32, ruleset: Ruleset) -> Self {
+        let technique_names = ruleset.catalog().techniques.iter().map(|s| s.to_string()).collect();
+        Self {
+            day,
+            ruleset,
+            technique_names,
+        }
+    }
+}
+
 #[derive(Deserialize)]
-struct DeserializedRuleset {
-    day: i32,
-    ruleset: HashMap<String, String>,
-    technique_names: Vec<String>,
+pub(crate) struct DeserializedRuleset {
+    pub(crate) day: i32,
+    pub(crate) ruleset: HashMap<String, String>,
+    pub(crate) technique_names: Vec<String>,
+}
+
+impl DeserializedRuleset {
+    /// Rebuild a `Ruleset` from the saved values. Techniques that didn't exist when this was saved
+    /// come back `UNSPECIFIED`. Rulesets saved before there were several games are for the default
+    /// one.
+    pub(crate) fn to_ruleset(&self) -> Ruleset {
+        let game = self.ruleset.get("game").map_or(default_catalog().game, |g| g.as_str());
+        let mut r = Ruleset::unspecified(
+            game,
+            self.ruleset.get("name").cloned().unwrap_or_default()
+        );
+        for (k, v) in &self.ruleset {
+            if let Ok(allowed) = v.parse::<IsAllowed>() {
+                r.set(k, allowed);
+            }
+        }
+        r
+    }
 }
 
 fn saved_rulesets_root_path() -> PathBuf {
     PathBuf::from(&var("RULESETS_PATH").unwrap_or("rulesets".to_string()))
 }
 
-fn saved_weeklies_dir() -> PathBuf {
+pub(crate) fn saved_weeklies_dir() -> PathBuf {
     let mut p = saved_rulesets_root_path();
     p.push("weeklies");
     p
 }
 
-async fn save_weekly(ruleset: Ruleset, date: &Date<Utc>, pool: &SqlitePool) -> Result<(), String> {
+/// The saved name of `track`'s ruleset for the period starting `date`: the date, plus the game and
+/// track when they aren't the default ones.
+pub(crate) fn weekly_name(track: &Track, date: &Date<Utc>) -> String {
+    let game = track.game();
+    let mut labels = vec![];
+    if !game.is_default() {
+        labels.push(game.name.as_str());
+    }
+    if !track.is_weekly() {
+        labels.push(track.name.as_str());
+    }
+    if labels.is_empty() {
+        day_to_nice_string(date)
+    } else {
+        format!("{} ({})", day_to_nice_string(date), labels.join(", "))
+    }
+}
+
+async fn save_weekly(track: &Track, ruleset: Ruleset, date: &Date<Utc>, pool: &SqlitePool) -> Result<u32, String> {
     // save to disk
     let mut path = saved_weeklies_dir();
     std::fs::create_dir_all(path.clone()).map_err(|e| format!("Error creating paths: {}", e))?;
-    path.push(format!("{}.json", date.num_days_from_ce()));
+    if track.is_default() {
+        path.push(format!("{}.json", date.num_days_from_ce()));
+    } else if track.game().is_default() {
+        path.push(format!("{}-{}.json", track.slug, date.num_days_from_ce()));
+    } else {
+        path.push(format!("{}-{}-{}.json", track.game, track.slug, date.num_days_from_ce()));
+    }
     if path.exists() {
         return Err("Serialized weekly already exists!".to_string());
     }
     let f = File::create(&path).map_err(|e| e.to_string())?;
-    let name = day_to_nice_string(date);
+    let name = weekly_name(track, date);
 
-    let sw = SerializedRuleset {
-        day: date.num_days_from_ce(),
-        ruleset,
-        technique_names: TECHNIQUE_NAMES.iter().map(|s| s.to_string()).collect(),
-    };
+    let values = ruleset_values(&ruleset);
+    let sw = SerializedRuleset::new(date.num_days_from_ce(), ruleset);
     serde_json::to_writer(f, &sw).map_err(|e| e.to_string())?;
+    let supplemental = rules_in_force(track.game, date.num_days_from_ce(), pool).await;
 
     // save to db
-    sqlx::query(
-        "INSERT INTO rulesets (id, name, filename) VALUES (?, ?, ?)")
+    let result = sqlx::query(
+        "INSERT INTO rulesets (game, track, day, name, filename, supplemental) VALUES (?, ?, ?, ?, ?, ?)")
+        .bind(track.game)
+        .bind(&track.slug)
         .bind(date.num_days_from_ce())
         .bind(name)
         .bind(path.file_name().unwrap().to_string_lossy().into_owned())
+        .bind(serde_json::to_string(&supplemental).map_err(|e| e.to_string())?)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let id = result.last_insert_rowid() as u32;
+    if let Err(e) = index_ruleset(id, &values, pool).await {
+        println!("Error indexing ruleset {}: {}", id, e);
+    }
+    Ok(id)
+}
+
+/// Write `ruleset` as the admin override for the saved ruleset `id`, in force from now on. Each
+/// override gets a file of its own, `{id}.override.{n}.json`, so earlier ones (and the original
+/// roll) are kept.
+pub(crate) async fn save_override(id: u32, ruleset: Ruleset, pool: &SqlitePool) -> Result<(), String> {
+    let (day,): (i32,) = sqlx::query_as("SELECT day FROM rulesets WHERE id = ?")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let dir = saved_weeklies_dir();
+    std::fs::create_dir_all(dir.clone()).map_err(|e| format!("Error creating paths: {}", e))?;
+    let (path, f) = (1..)
+        .map(|n| dir.join(format!("{}.override.{}.json", id, n)))
+        .find_map(|path| match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => None,
+            result => Some(result.map(|f| (path, f))),
+        })
+        .unwrap()
+        .map_err(|e| e.to_string())?;
+    let values = ruleset_values(&ruleset);
+    let sw = SerializedRuleset::new(day, ruleset);
+    serde_json::to_writer(f, &sw).map_err(|e| e.to_string())?;
+
+    sqlx::query("UPDATE rulesets SET override_filename = ? WHERE id = ?")
+        .bind(path.file_name().unwrap().to_string_lossy().into_owned())
+        .bind(id)
         .execute(pool)
         .await
-        .map(|_| ())
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    index_ruleset(id, &values, pool).await
 }
 
-fn day_to_nice_string(day: &Date<Utc>) -> String {
+pub(crate) fn day_to_nice_string(day: &Date<Utc>) -> String {
     format!(
         "{} {}, {}",
         month_from_u32(day.month()).name(),
@@ -115,97 +213,463 @@ struct SavedInfo {
 }
 
 #[derive(sqlx::FromRow, Serialize)]
-struct RulesetRecord {
-    id: u32,
-    name: String,
-    filename: String,
+pub(crate) struct RulesetRecord {
+    pub(crate) id: u32,
+    pub(crate) game: String,
+    pub(crate) track: String,
+    pub(crate) day: i32,
+    pub(crate) name: String,
+    pub(crate) filename: String,
+    pub(crate) override_filename: Option<String>,
+    pub(crate) note: Option<String>,
+    pub(crate) superseded: bool,
+    /// The supplemental rules in force when it was saved, as JSON (see `crate::web::supplemental`).
+    #[serde(skip)]
+    pub(crate) supplemental: Option<String>,
 }
 
-#[get("/history/<id>")]
-async fn render_past_ruleset(id: u32, pool: &State<SqlitePool>) -> Result<Template, NotFound<String>> {
-    #[derive(Serialize)]
-    struct Ctx {
-        active_tab: String,
-        ruleset: HashMap<String, String>,
-        technique_names: Vec<String>,
-        name: String,
+pub(crate) const RECORD_COLUMNS: &str =
+    "id, game, track, day, name, filename, override_filename, note, superseded, supplemental";
+
+/// A saved ruleset along with its admin override, if it has one.
+pub(crate) struct StoredRuleset {
+    pub(crate) record: RulesetRecord,
+    pub(crate) original: DeserializedRuleset,
+    pub(crate) overridden: Option<DeserializedRuleset>,
+}
+
+impl StoredRuleset {
+    /// The ruleset that's actually in force: the override if there is one, otherwise the original roll.
+    pub(crate) fn current(&self) -> &DeserializedRuleset {
+        self.overridden.as_ref().unwrap_or(&self.original)
     }
+}
 
-    let rec: RulesetRecord = sqlx::query_as("SELECT id, name, filename FROM rulesets WHERE id = ?")
+pub(crate) async fn get_record(id: u32, pool: &SqlitePool) -> Result<RulesetRecord, sqlx::Error> {
+    sqlx::query_as(&format!("SELECT {} FROM rulesets WHERE id = ?", RECORD_COLUMNS))
         .bind(id)
-        .fetch_one(&**pool)
+        .fetch_one(pool)
         .await
-        .map_err(|e|
-            {
-                println!("Error fetching ruleset: {:?}", e);
-                NotFound("Unknown ruleset id".to_string())
-            }
-        )?;
+}
 
+pub(crate) async fn get_stored_ruleset(id: u32, pool: &SqlitePool) -> Result<StoredRuleset, String> {
+    let record = get_record(id, pool).await.map_err(|e| {
+        println!("Error fetching ruleset: {:?}", e);
+        "Unknown ruleset id".to_string()
+    })?;
+    let mut p = saved_weeklies_dir();
+    p.push(&record.filename);
+    let original = get_saved_ruleset(p)?;
+    let overridden = match &record.override_filename {
+        Some(f) => {
+            let mut p = saved_weeklies_dir();
+            p.push(f);
+            Some(get_saved_ruleset(p)?)
+        }
+        None => None,
+    };
+    Ok(StoredRuleset {
+        record,
+        original,
+        overridden,
+    })
+}
 
+/// The latest non-superseded ruleset on the same game and track published before `id`, if there is
+/// one.
+pub(crate) async fn get_previous_stored_ruleset(id: u32, pool: &SqlitePool) -> Option<StoredRuleset> {
+    let previous: Option<(u32,)> = sqlx::query_as(
+        "SELECT p.id FROM rulesets p JOIN rulesets r ON r.id = ? \
+         WHERE p.game = r.game AND p.track = r.track AND p.day < r.day AND p.superseded = 0 ORDER BY p.day DESC LIMIT 1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .unwrap_or_else(|e| {
+                println!("Error fetching previous ruleset: {:?}", e);
+                None
+            });
+    match previous {
+        Some((previous_id,)) => get_stored_ruleset(previous_id, pool).await.ok(),
+        None => None,
+    }
+}
 
-    let mut p = saved_weeklies_dir();
-    p.push(rec.filename);
-    let r = get_saved_ruleset(p).map_err(|s| NotFound(s))?;
-    Ok(Template::render(
+/// Context shared by every page that shows a saved ruleset, so the override note and original roll
+/// render the same way everywhere.
+#[derive(Serialize)]
+struct StoredRulesetContext {
+    ruleset: HashMap<String, String>,
+    technique_names: Vec<String>,
+    note: Option<String>,
+    superseded: bool,
+    original_ruleset: Option<HashMap<String, String>>,
+    share_code: String,
+    comparisons_url: String,
+}
+
+impl StoredRulesetContext {
+    /// A ruleset that hasn't been saved.
+    fn unsaved(r: &Ruleset) -> Self {
+        Self {
+            ruleset: r.techniques().map(|(t, allowed)| (t.to_string(), allowed.as_str().to_string())).collect(),
+            technique_names: r.catalog().techniques.iter().map(|s| s.to_string()).collect(),
+            note: None,
+            superseded: false,
+            original_ruleset: None,
+            share_code: encode_ruleset(r),
+            comparisons_url: comparisons_url(r),
+        }
+    }
+}
+
+/// Where the rulesets `r` can be compared against are listed.
+fn comparisons_url(r: &Ruleset) -> String {
+    let game = Game::of(r);
+    if game.is_default() {
+        "/comparisons".to_string()
+    } else {
+        format!("/comparisons?game={}", game.slug)
+    }
+}
+
+impl From<StoredRuleset> for StoredRulesetContext {
+    fn from(stored: StoredRuleset) -> Self {
+        let current = stored.current().to_ruleset();
+        let share_code = encode_ruleset(&current);
+        let comparisons_url = comparisons_url(&current);
+        let (current, original_ruleset) = match stored.overridden {
+            Some(o) => (o, Some(stored.original.ruleset)),
+            None => (stored.original, None),
+        };
+        Self {
+            ruleset: current.ruleset,
+            technique_names: current.technique_names,
+            note: stored.record.note,
+            superseded: stored.record.superseded,
+            original_ruleset,
+            share_code,
+            comparisons_url,
+        }
+    }
+}
+
+/// The id of `track`'s saved ruleset for the period starting on `sunday`, if it's been saved.
+async fn find_saved(track: &Track, sunday: &Date<Utc>, pool: &SqlitePool) -> Result<Option<u32>, sqlx::Error> {
+    let saved: Option<(u32,)> = sqlx::query_as("SELECT id FROM rulesets WHERE game = ? AND track = ? AND day = ?")
+        .bind(track.game)
+        .bind(&track.slug)
+        .bind(sunday.num_days_from_ce())
+        .fetch_optional(pool)
+        .await?;
+    Ok(saved.map(|(id,)| id))
+}
+
+/// This week's ruleset on `game`'s main track, including any admin override.
+pub(crate) async fn current_weekly_ruleset(game: &'static Game, pool: &SqlitePool) -> Ruleset {
+    let now = chrono::offset::Utc::now();
+    let last_sunday = most_recent_sunday(now.date());
+    let track = weekly_track(game);
+    let stored = match find_saved(&track, &last_sunday, pool).await {
+        Ok(Some(id)) => get_stored_ruleset(id, pool).await.ok(),
+        _ => None,
+    };
+    let mut r = match stored {
+        Some(stored) => stored.current().to_ruleset(),
+        None => track
+            .roll(track.seed(&last_sunday))
+            .unwrap_or_else(|_| Ruleset::unspecified(game.slug, String::new())),
+    };
+    r.name = "Weekly".to_string();
+    r
+}
+
+/// `game`'s main track.
+fn weekly_track(game: &'static Game) -> Track {
+    find_track(game, DEFAULT_TRACK).unwrap_or_else(default_track)
+}
+
+/// The day number of the first day of this period.
+pub(crate) fn current_period_day() -> u32 {
+    most_recent_sunday(chrono::offset::Utc::now().date()).num_days_from_ce() as u32
+}
+
+/// Make sure this period's ruleset on `track` is saved, announcing it to webhooks the first time.
+/// Returns its id.
+async fn materialize_track(track: &Track, pool: &SqlitePool) -> Result<u32, String> {
+    let now = chrono::offset::Utc::now();
+    let last_sunday = most_recent_sunday(now.date());
+    if let Some(id) = find_saved(track, &last_sunday, pool).await.map_err(|e| e.to_string())? {
+        return Ok(id);
+    }
+    let ruleset = track.roll(track.seed(&last_sunday)).map_err(|e| e.to_string())?;
+    let id = match save_weekly(track, ruleset, &last_sunday, pool).await {
+        Ok(id) => id,
+        // another request saved it first
+        Err(e) => return find_saved(track, &last_sunday, pool).await.map_err(|e| e.to_string())?.ok_or(e),
+    };
+    announce_ruleset(id, EVENT_PUBLISHED, pool).await;
+    Ok(id)
+}
+
+/// Make sure this period's weekly is saved, announcing it to webhooks the first time. Returns its id.
+pub(crate) async fn materialize_weekly(pool: &SqlitePool) -> Result<u32, String> {
+    materialize_track(&default_track(), pool).await
+}
+
+/// `materialize_weekly` for `game`'s track called `slug`.
+pub(crate) async fn materialize_track_weekly(game: &'static Game, slug: &str, pool: &SqlitePool) -> Result<u32, String> {
+    let track = find_track(game, slug).ok_or_else(|| format!("Unknown track {}", slug))?;
+    materialize_track(&track, pool).await
+}
+
+/// The game called `slug`, or the default one if there's no slug.
+pub(crate) fn game_or_default(slug: Option<&str>) -> Result<&'static Game, NotFound<String>> {
+    match slug {
+        Some(slug) => find_game(slug).ok_or_else(|| NotFound(format!("Unknown game {}", slug))),
+        None => Ok(default_game()),
+    }
+}
+
+/// Absolute url of a saved ruleset's rules card, without the extension, for link previews.
+fn card_url(id: u32) -> String {
+    format!("{}/history/{}/card", public_url(), id)
+}
+
+#[get("/history/<id>")]
+async fn render_past_ruleset(
+    id: u32,
+    export: Export,
+    pool: &State<SqlitePool>,
+) -> Result<Negotiated, NotFound<String>> {
+    #[derive(Serialize)]
+    struct Ctx {
+        active_tab: String,
+        name: String,
+        #[serde(flatten)]
+        stored: StoredRulesetContext,
+        leaderboard: LeaderboardContext,
+        route_notes: RouteNotesContext,
+        supplemental: Vec<SupplementalRule>,
+        card_url: String,
+    }
+
+    let stored = get_stored_ruleset(id, pool).await.map_err(NotFound)?;
+    if let Export(Some(format)) = export {
+        return Ok(Negotiated::Export(Exported::new(
+            format,
+            id,
+            &stored.record.name,
+            &stored.current().to_ruleset(),
+            stored.record.note.as_deref(),
+        )));
+    }
+    let leaderboard = get_leaderboard(id, &stored.current().to_ruleset(), pool).await;
+    let route_notes = get_route_notes(id, pool).await;
+    let supplemental = ruleset_supplemental(&stored.record, pool).await;
+    Ok(Negotiated::Page(Template::render(
         "historical_ruleset",
         Ctx {
             active_tab: "history".to_string(),
-            ruleset: r.ruleset,
-            name: rec.name,
-            technique_names: r.technique_names,
+            name: stored.record.name.clone(),
+            stored: stored.into(),
+            leaderboard,
+            route_notes,
+            supplemental,
+            card_url: card_url(id),
         },
-    ))
+    )))
+}
+
+/// A link to one track's (or game's) version of a page, for the tabs above it.
+#[derive(Serialize)]
+pub(crate) struct TrackTab {
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) active: bool,
 }
 
-#[get("/history")]
-async fn history(pool: &State<SqlitePool>) -> Template {
+/// Tabs for every track of `track`'s game, or none if it has only one. `url` makes the link for a
+/// track.
+pub(crate) fn track_tabs(track: &Track, url: impl Fn(&Track) -> String) -> Vec<TrackTab> {
+    let tracks = tracks(track.game());
+    if tracks.len() < 2 {
+        return vec![];
+    }
+    tracks
+        .iter()
+        .map(|t| TrackTab {
+            name: t.name.clone(),
+            url: url(t),
+            active: t.slug == track.slug,
+        })
+        .collect()
+}
+
+/// Tabs for every game, or none if there's only one. `path` is the page under each game's urls.
+pub(crate) fn game_tabs(active: &Game, path: &str) -> Vec<TrackTab> {
+    if games().len() < 2 {
+        return vec![];
+    }
+    games()
+        .iter()
+        .map(|g| TrackTab {
+            name: g.name.clone(),
+            url: g.url(path),
+            active: g.slug == active.slug,
+        })
+        .collect()
+}
+
+/// `game`'s track called `slug`, or its main track if there's no slug.
+pub(crate) fn track_or_weekly(game: &'static Game, slug: Option<&str>) -> Result<Track, NotFound<String>> {
+    find_track(game, slug.unwrap_or(DEFAULT_TRACK))
+        .ok_or_else(|| NotFound(format!("Unknown track {}", slug.unwrap_or(DEFAULT_TRACK))))
+}
+
+async fn render_weekly(track: Track, export: Export, pool: &SqlitePool) -> Result<Negotiated, NotFound<String>> {
     #[derive(Serialize)]
     struct Ctx {
-        rulesets: Vec<RulesetRecord>,
+        week_of: String,
         active_tab: String,
+        track: String,
+        games: Vec<TrackTab>,
+        tracks: Vec<TrackTab>,
+        #[serde(flatten)]
+        stored: StoredRulesetContext,
+        leaderboard: Option<LeaderboardContext>,
+        route_notes: Option<RouteNotesContext>,
+        card_url: Option<String>,
+        closest_past: Option<Similar>,
     }
 
-    let mut rulesets = match sqlx::query_as(
-        "SELECT id, name, filename FROM rulesets")
-        .fetch_all(&**pool)
-        .await {
-        Ok(o) => o,
+    let now = chrono::offset::Utc::now();
+    let last_sunday = most_recent_sunday(now.date());
+    let week_of = day_to_nice_string(&last_sunday);
+    let saved = match materialize_track(&track, pool).await {
+        Ok(id) => get_stored_ruleset(id, pool).await.map(|s| (id, s)),
+        Err(e) => Err(e),
+    };
+    let (stored, leaderboard, route_notes, card, closest_past) = match saved {
+        Ok((id, s)) => {
+            if let Export(Some(format)) = export {
+                let r = s.current().to_ruleset();
+                return Ok(Negotiated::Export(Exported::new(format, id, &week_of, &r, s.record.note.as_deref())));
+            }
+            let current = s.current().to_ruleset();
+            let leaderboard = get_leaderboard(id, &current, pool).await;
+            let route_notes = get_route_notes(id, pool).await;
+            let closest_past = closest_past_rulesets(&current, &track.slug, s.record.day, 1, pool).await.pop();
+            (s.into(), Some(leaderboard), Some(route_notes), Some(card_url(id)), closest_past)
+        }
         Err(e) => {
-            println!("Error fetching rulesets: {:?}", e);
-            vec![]
+            println!("Error loading saved weekly, showing a fresh roll instead: {}", e);
+            let r = track.roll(track.seed(&last_sunday)).map_err(|e| NotFound(e.to_string()))?;
+            if let Export(Some(format)) = export {
+                return Ok(Negotiated::Export(Exported::new(
+                    format,
+                    last_sunday.num_days_from_ce() as u32,
+                    &week_of,
+                    &r,
+                    None,
+                )));
+            }
+            (StoredRulesetContext::unsaved(&r), None, None, None, None)
         }
     };
-    Template::render(
-        "history",
+
+    let game = track.game();
+    Ok(Negotiated::Page(Template::render(
+        "weekly_ruleset",
         Ctx {
-            rulesets,
-            active_tab: "history".to_string(),
+            week_of,
+            active_tab: "weekly".to_string(),
+            games: game_tabs(game, "/weekly"),
+            tracks: track_tabs(&track, |t| t.url()),
+            track: track.name,
+            stored,
+            leaderboard,
+            route_notes,
+            card_url: card,
+            closest_past,
         },
-    )
+    )))
 }
 
 #[get("/weekly")]
-async fn weekly(pool: &State<SqlitePool>) -> Template {
-    let now = chrono::offset::Utc::now();
-    let last_sunday = most_recent_sunday(now.date());
-    let r = get_weekly_ruleset();
-    let rc = WeeklyRuleset {
-        week_of: day_to_nice_string(&last_sunday),
-        ruleset: &r,
-        technique_names: &TECHNIQUE_NAMES,
-        active_tab: "weekly".to_string(),
-    };
-    match save_weekly(r.clone(), &last_sunday, &pool).await {
-        Ok(_) => {}
-        Err(e) => {
-            println!("Error saving weekly: {}", e);
-        }
-    }
+async fn weekly(export: Export, pool: &State<SqlitePool>) -> Result<Negotiated, NotFound<String>> {
+    render_weekly(track_or_weekly(default_game(), None)?, export, pool).await
+}
+
+#[get("/weekly/<track>")]
+async fn track_weekly(
+    track: &str,
+    export: Export,
+    pool: &State<SqlitePool>,
+) -> Result<Negotiated, NotFound<String>> {
+    render_weekly(track_or_weekly(default_game(), Some(track))?, export, pool).await
+}
+
+#[get("/<game>/weekly", rank = 2)]
+async fn game_weekly(
+    game: &str,
+    export: Export,
+    pool: &State<SqlitePool>,
+) -> Result<Negotiated, NotFound<String>> {
+    render_weekly(track_or_weekly(game_or_default(Some(game))?, None)?, export, pool).await
+}
+
+#[get("/<game>/weekly/<track>", rank = 2)]
+async fn game_track_weekly(
+    game: &str,
+    track: &str,
+    export: Export,
+    pool: &State<SqlitePool>,
+) -> Result<Negotiated, NotFound<String>> {
+    render_weekly(track_or_weekly(game_or_default(Some(game))?, Some(track))?, export, pool).await
+}
 
-    Template::render("weekly_ruleset", rc)
+/// A track as listed by the API, with where to find it.
+#[derive(Serialize)]
+struct TrackSummary {
+    game: String,
+    slug: String,
+    name: String,
+    url: String,
+    history_url: String,
+}
+
+#[get("/api/tracks")]
+fn api_tracks() -> Json<Vec<TrackSummary>> {
+    let base = public_url();
+    Json(
+        games()
+            .iter()
+            .flat_map(tracks)
+            .map(|t| TrackSummary {
+                game: t.game.to_string(),
+                url: format!("{}{}", base, t.url()),
+                history_url: if t.game().is_default() {
+                    format!("{}/api/tracks/{}/history", base, t.slug)
+                } else {
+                    format!("{}/api/tracks/{}/history?game={}", base, t.slug, t.game)
+                },
+                slug: t.slug,
+                name: t.name,
+            })
+            .collect(),
+    )
 }
 
 pub(crate) fn add_routes(rocket: Rocket<Build>) -> Rocket<Build> {
-    rocket.mount("/", rocket::routes![weekly, history, render_past_ruleset])
+    rocket.mount(
+        "/",
+        rocket::routes![
+            weekly,
+            track_weekly,
+            game_weekly,
+            game_track_weekly,
+            render_past_ruleset,
+            api_tracks
+        ],
+    )
 }
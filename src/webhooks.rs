@@ -0,0 +1,550 @@
+//! Outbound webhooks. Each announcement is POSTed as JSON to every active webhook, signed with
+//! HMAC-SHA256 over the body using the webhook's secret, and retried with exponential backoff.
+//! Every delivery, successful or not, is recorded in `webhook_deliveries`.
+
+use crate::games::Game;
+use crate::rules::{diff_rulesets, RuleChange};
+use crate::techniques::Ruleset;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub(crate) const EVENT_PUBLISHED: &str = "ruleset.published";
+pub(crate) const EVENT_OVERRIDDEN: &str = "ruleset.overridden";
+
+pub(crate) const SIGNATURE_HEADER: &str = "X-Ruleset-Signature";
+pub(crate) const EVENT_HEADER: &str = "X-Ruleset-Event";
+pub(crate) const DELIVERY_HEADER: &str = "X-Ruleset-Delivery";
+
+pub(crate) const DELIVERY_PENDING: &str = "pending";
+pub(crate) const DELIVERY_DELIVERED: &str = "delivered";
+pub(crate) const DELIVERY_FAILED: &str = "failed";
+
+lazy_static! {
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        // deliveries are rare, and an idle connection isn't worth keeping around between them
+        .pool_max_idle_per_host(0)
+        .build()
+        .unwrap();
+}
+
+#[derive(sqlx::FromRow, Serialize, Clone)]
+pub(crate) struct Webhook {
+    pub(crate) id: i64,
+    pub(crate) url: String,
+    pub(crate) secret: String,
+    pub(crate) active: bool,
+    pub(crate) created_by: String,
+    pub(crate) created_at: String,
+}
+
+pub(crate) const WEBHOOK_COLUMNS: &str = "id, url, secret, active, created_by, created_at";
+
+#[derive(Serialize)]
+pub(crate) struct PreviousDiff {
+    pub(crate) ruleset_id: u32,
+    pub(crate) name: String,
+    pub(crate) changes: Vec<RuleChange>,
+}
+
+/// The JSON body sent for every event.
+#[derive(Serialize)]
+pub(crate) struct RulesetPayload {
+    pub(crate) event: String,
+    pub(crate) game: String,
+    pub(crate) ruleset_id: u32,
+    pub(crate) name: String,
+    pub(crate) permalink: String,
+    pub(crate) note: Option<String>,
+    /// Technique name -> `ALLOWED`, `DISALLOWED` or `UNSPECIFIED`, in a stable order.
+    pub(crate) ruleset: BTreeMap<String, String>,
+    /// Changes since the previous published ruleset, if there is one.
+    pub(crate) diff_vs_previous: Option<PreviousDiff>,
+    /// Changes from the game's baseline preset (NMG for ALttP).
+    pub(crate) diff_vs_nmg: Vec<RuleChange>,
+}
+
+impl RulesetPayload {
+    pub(crate) fn new(
+        event: &str,
+        ruleset_id: u32,
+        name: String,
+        permalink: String,
+        note: Option<String>,
+        ruleset: &Ruleset,
+    ) -> Self {
+        Self {
+            event: event.to_string(),
+            game: ruleset.game.clone(),
+            ruleset_id,
+            name,
+            permalink,
+            note,
+            ruleset: ruleset.techniques().map(|(t, allowed)| (t.to_string(), allowed.as_str().to_string())).collect(),
+            diff_vs_previous: None,
+            diff_vs_nmg: diff_rulesets(Game::of(ruleset).baseline(), ruleset),
+        }
+    }
+
+    /// Include what changed since the ruleset published before this one.
+    pub(crate) fn with_previous(mut self, ruleset_id: u32, name: String, previous: &Ruleset, ruleset: &Ruleset) -> Self {
+        self.diff_vs_previous = Some(PreviousDiff {
+            ruleset_id,
+            name,
+            changes: diff_rulesets(previous, ruleset),
+        });
+        self
+    }
+}
+
+/// `sha256=<hex>` HMAC of `body` keyed with `secret`, as sent in `X-Ruleset-Signature`.
+pub(crate) fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// How many times to try a delivery, and how long to wait before the first retry. Each retry
+/// waits twice as long as the one before.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RetryPolicy {
+    pub(crate) attempts: u32,
+    pub(crate) base_delay: Duration,
+}
+
+pub(crate) const DEFAULT_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    attempts: 5,
+    base_delay: Duration::from_secs(30),
+};
+
+impl RetryPolicy {
+    /// Delay before attempt number `attempt` (1-based; the first attempt is immediate).
+    pub(crate) fn delay_before(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            Duration::ZERO
+        } else {
+            self.base_delay * 2u32.saturating_pow(attempt - 2)
+        }
+    }
+}
+
+async fn update_delivery(
+    delivery_id: i64,
+    status: &str,
+    attempts: u32,
+    status_code: Option<u16>,
+    error: Option<String>,
+    pool: &SqlitePool,
+) {
+    let result = sqlx::query(
+        "UPDATE webhook_deliveries SET status = ?, attempts = ?, last_status_code = ?, last_error = ?, \
+         updated_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(attempts)
+        .bind(status_code)
+        .bind(error)
+        .bind(Utc::now().to_rfc3339())
+        .bind(delivery_id)
+        .execute(pool)
+        .await;
+    if let Err(e) = result {
+        println!("Error updating webhook delivery {}: {:?}", delivery_id, e);
+    }
+}
+
+/// Deliver `body` to one webhook, retrying per `policy` until it gets a 2xx response. Returns the
+/// id of the `webhook_deliveries` row and whether it was eventually delivered.
+pub(crate) async fn deliver(
+    webhook: &Webhook,
+    event: &str,
+    ruleset_id: u32,
+    body: &str,
+    policy: RetryPolicy,
+    pool: &SqlitePool,
+) -> Result<(i64, bool), sqlx::Error> {
+    let now = Utc::now().to_rfc3339();
+    let delivery_id = sqlx::query(
+        "INSERT INTO webhook_deliveries (webhook_id, event, ruleset_id, payload, status, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?)")
+        .bind(webhook.id)
+        .bind(event)
+        .bind(ruleset_id)
+        .bind(body)
+        .bind(DELIVERY_PENDING)
+        .bind(&now)
+        .bind(&now)
+        .execute(pool)
+        .await?
+        .last_insert_rowid();
+    Ok((delivery_id, attempt_delivery(webhook, delivery_id, event, body, 0, policy, pool).await))
+}
+
+/// Whether webhook `id` is still active. An organizer can deactivate (or delete) it while a
+/// delivery is waiting to retry.
+async fn still_active(id: i64, pool: &SqlitePool) -> bool {
+    match sqlx::query_as::<_, (bool,)>("SELECT active FROM webhooks WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(active) => active.is_some_and(|(a,)| a),
+        Err(e) => {
+            // not worth abandoning a delivery over
+            println!("Error checking webhook {}: {:?}", id, e);
+            true
+        }
+    }
+}
+
+/// Try delivery `delivery_id` from the attempt after `attempts_made` up to `policy.attempts`,
+/// recording each one, and give up if the webhook is deactivated in between. Returns whether it was
+/// delivered.
+async fn attempt_delivery(
+    webhook: &Webhook,
+    delivery_id: i64,
+    event: &str,
+    body: &str,
+    attempts_made: u32,
+    policy: RetryPolicy,
+    pool: &SqlitePool,
+) -> bool {
+    let signature = sign(&webhook.secret, body.as_bytes());
+    for attempt in attempts_made + 1..=policy.attempts {
+        rocket::tokio::time::sleep(policy.delay_before(attempt)).await;
+        if !still_active(webhook.id, pool).await {
+            let error = "Webhook was deactivated before the delivery went through".to_string();
+            update_delivery(delivery_id, DELIVERY_FAILED, attempt - 1, None, Some(error), pool).await;
+            return false;
+        }
+        let result = HTTP_CLIENT
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .header(EVENT_HEADER, event)
+            .header(DELIVERY_HEADER, delivery_id.to_string())
+            .body(body.to_string())
+            .send()
+            .await;
+        let (status_code, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                update_delivery(delivery_id, DELIVERY_DELIVERED, attempt, Some(response.status().as_u16()), None, pool)
+                    .await;
+                return true;
+            }
+            Ok(response) => (Some(response.status().as_u16()), Some(format!("HTTP {}", response.status()))),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let status = if attempt == policy.attempts {
+            DELIVERY_FAILED
+        } else {
+            DELIVERY_PENDING
+        };
+        update_delivery(delivery_id, status, attempt, status_code, error, pool).await;
+    }
+    false
+}
+
+/// Send `payload` to every active webhook. Deliveries (and their retries) run in the background.
+pub(crate) async fn announce(payload: &RulesetPayload, pool: &SqlitePool) {
+    let webhooks: Vec<Webhook> =
+        match sqlx::query_as(&format!("SELECT {} FROM webhooks WHERE active = 1", WEBHOOK_COLUMNS))
+            .fetch_all(pool)
+            .await
+        {
+            Ok(w) => w,
+            Err(e) => {
+                println!("Error fetching webhooks: {:?}", e);
+                return;
+            }
+        };
+    let body = match serde_json::to_string(payload) {
+        Ok(b) => b,
+        Err(e) => {
+            println!("Error serializing webhook payload: {:?}", e);
+            return;
+        }
+    };
+    for webhook in webhooks {
+        let (body, event, ruleset_id, pool) = (body.clone(), payload.event.clone(), payload.ruleset_id, pool.clone());
+        rocket::tokio::spawn(async move {
+            match deliver(&webhook, &event, ruleset_id, &body, DEFAULT_RETRY_POLICY, &pool).await {
+                Ok((_, true)) => {}
+                Ok((id, false)) => println!("Webhook delivery {} to {} failed", id, webhook.url),
+                Err(e) => println!("Error recording webhook delivery to {}: {:?}", webhook.url, e),
+            }
+        });
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingDelivery {
+    id: i64,
+    webhook_id: i64,
+    event: String,
+    payload: String,
+    attempts: u32,
+}
+
+/// Pick up deliveries left `pending` when the server last stopped: retry those to active webhooks
+/// in the background for the attempts they have left, and mark the rest failed. Returns how many
+/// were resumed.
+pub(crate) async fn resume_deliveries(policy: RetryPolicy, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    let pending: Vec<PendingDelivery> = sqlx::query_as(
+        "SELECT id, webhook_id, event, payload, attempts FROM webhook_deliveries WHERE status = ? ORDER BY id")
+        .bind(DELIVERY_PENDING)
+        .fetch_all(pool)
+        .await?;
+    let mut resumed = 0;
+    for delivery in pending {
+        let webhook: Option<Webhook> =
+            sqlx::query_as(&format!("SELECT {} FROM webhooks WHERE id = ? AND active = 1", WEBHOOK_COLUMNS))
+                .bind(delivery.webhook_id)
+                .fetch_optional(pool)
+                .await?;
+        let webhook = match webhook {
+            Some(w) if delivery.attempts < policy.attempts => w,
+            Some(_) => {
+                let error = "Interrupted by a server restart".to_string();
+                update_delivery(delivery.id, DELIVERY_FAILED, delivery.attempts, None, Some(error), pool).await;
+                continue;
+            }
+            None => {
+                let error = "Webhook was deactivated before the server restarted".to_string();
+                update_delivery(delivery.id, DELIVERY_FAILED, delivery.attempts, None, Some(error), pool).await;
+                continue;
+            }
+        };
+        resumed += 1;
+        let pool = pool.clone();
+        rocket::tokio::spawn(async move {
+            let delivered = attempt_delivery(
+                &webhook, delivery.id, &delivery.event, &delivery.payload, delivery.attempts, policy, &pool,
+            )
+            .await;
+            if !delivered {
+                println!("Webhook delivery {} to {} failed", delivery.id, webhook.url);
+            }
+        });
+    }
+    Ok(resumed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{deliver, resume_deliveries, sign, RetryPolicy, Webhook, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER};
+    use rocket::tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use rocket::tokio::net::TcpListener;
+    use sqlx::migrate::Migrator;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use sqlx::SqlitePool;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type Received = Arc<Mutex<Vec<(HashMap<String, String>, String)>>>;
+
+    /// A throwaway HTTP server that answers each request with the next status in `statuses` and
+    /// records what it was sent.
+    async fn stand_in(statuses: Vec<u16>) -> (String, Received) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received: Received = Default::default();
+        let log = received.clone();
+        rocket::tokio::spawn(async move {
+            for status in statuses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![];
+                let mut chunk = [0u8; 4096];
+                let (head, body) = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let head = text[..end].to_string();
+                        let length: usize = head
+                            .lines()
+                            .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                            .unwrap_or(0);
+                        if buf.len() >= end + 4 + length {
+                            break (head, text[end + 4..end + 4 + length].to_string());
+                        }
+                    }
+                };
+                let headers = head
+                    .lines()
+                    .skip(1)
+                    .filter_map(|l| l.split_once(':'))
+                    .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+                    .collect();
+                log.lock().unwrap().push((headers, body));
+                let response = format!("HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, received)
+    }
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        Migrator::new(Path::new("migrations")).await.unwrap().run(&pool).await.unwrap();
+        pool
+    }
+
+    async fn webhook(url: String, pool: &SqlitePool) -> Webhook {
+        let id = sqlx::query("INSERT INTO webhooks (url, secret, created_by, created_at) VALUES (?, 'hunter2', 'fox', 'now')")
+            .bind(&url)
+            .execute(pool)
+            .await
+            .unwrap()
+            .last_insert_rowid();
+        Webhook {
+            id,
+            url,
+            secret: "hunter2".to_string(),
+            active: true,
+            created_by: "fox".to_string(),
+            created_at: "now".to_string(),
+        }
+    }
+
+    const FAST: RetryPolicy = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(5),
+    };
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            sign("Jefe", b"what do ya want for nothing?")
+        );
+    }
+
+    #[test]
+    fn test_retry_delays() {
+        let policy = RetryPolicy {
+            attempts: 5,
+            base_delay: Duration::from_secs(30),
+        };
+        let delays: Vec<u64> = (1..=5).map(|a| policy.delay_before(a).as_secs()).collect();
+        assert_eq!(vec![0, 30, 60, 120, 240], delays);
+    }
+
+    #[rocket::async_test]
+    async fn test_deliver_retries_until_success() {
+        let pool = test_pool().await;
+        let (url, received) = stand_in(vec![500, 204]).await;
+        let hook = webhook(url, &pool).await;
+        let body = r#"{"event":"ruleset.published"}"#;
+
+        let (delivery_id, delivered) = deliver(&hook, "ruleset.published", 7, body, FAST, &pool).await.unwrap();
+        assert!(delivered);
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(2, received.len());
+        for (headers, sent) in &received {
+            assert_eq!(body, sent);
+            assert_eq!(&sign("hunter2", body.as_bytes()), &headers[&SIGNATURE_HEADER.to_lowercase()]);
+            assert_eq!("ruleset.published", headers[&EVENT_HEADER.to_lowercase()]);
+            assert_eq!(delivery_id.to_string(), headers[&DELIVERY_HEADER.to_lowercase()]);
+        }
+
+        let (status, attempts, code): (String, u32, Option<u16>) =
+            sqlx::query_as("SELECT status, attempts, last_status_code FROM webhook_deliveries WHERE id = ?")
+                .bind(delivery_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(("delivered".to_string(), 2, Some(204)), (status, attempts, code));
+    }
+
+    #[rocket::async_test]
+    async fn test_deliver_gives_up() {
+        let pool = test_pool().await;
+        let (url, received) = stand_in(vec![500, 502, 503]).await;
+        let hook = webhook(url, &pool).await;
+
+        let (delivery_id, delivered) = deliver(&hook, "ruleset.published", 7, "{}", FAST, &pool).await.unwrap();
+        assert!(!delivered);
+        assert_eq!(3, received.lock().unwrap().len());
+
+        let (status, attempts, code): (String, u32, Option<u16>) =
+            sqlx::query_as("SELECT status, attempts, last_status_code FROM webhook_deliveries WHERE id = ?")
+                .bind(delivery_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(("failed".to_string(), 3, Some(503)), (status, attempts, code));
+    }
+
+    #[rocket::async_test]
+    async fn test_deliver_stops_when_deactivated() {
+        let pool = test_pool().await;
+        let (url, received) = stand_in(vec![204]).await;
+        let hook = webhook(url, &pool).await;
+        // deactivated after the delivery was queued, say between retries
+        sqlx::query("UPDATE webhooks SET active = 0 WHERE id = ?").bind(hook.id).execute(&pool).await.unwrap();
+
+        let (delivery_id, delivered) = deliver(&hook, "ruleset.published", 7, "{}", FAST, &pool).await.unwrap();
+        assert!(!delivered);
+        assert!(received.lock().unwrap().is_empty());
+        assert_eq!(("failed".to_string(), 0, None), delivery_status(delivery_id, &pool).await);
+    }
+
+    async fn delivery_status(delivery_id: i64, pool: &SqlitePool) -> (String, u32, Option<u16>) {
+        sqlx::query_as("SELECT status, attempts, last_status_code FROM webhook_deliveries WHERE id = ?")
+            .bind(delivery_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn test_resume_deliveries() {
+        let pool = test_pool().await;
+        let (url, received) = stand_in(vec![204]).await;
+        let hook = webhook(url, &pool).await;
+        let gone = webhook("http://127.0.0.1:9/gone".to_string(), &pool).await;
+        sqlx::query("UPDATE webhooks SET active = 0 WHERE id = ?").bind(gone.id).execute(&pool).await.unwrap();
+        // as left by a server that stopped between attempts
+        let mut ids = vec![];
+        for (webhook_id, attempts) in [(hook.id, 1), (gone.id, 1), (hook.id, 3)] {
+            let id = sqlx::query(
+                "INSERT INTO webhook_deliveries (webhook_id, event, ruleset_id, payload, status, attempts, created_at, \
+                 updated_at) VALUES (?, 'ruleset.published', 7, '{}', 'pending', ?, 'now', 'now')")
+                .bind(webhook_id)
+                .bind(attempts)
+                .execute(&pool)
+                .await
+                .unwrap()
+                .last_insert_rowid();
+            ids.push(id);
+        }
+
+        assert_eq!(1, resume_deliveries(FAST, &pool).await.unwrap());
+        for _ in 0..200 {
+            if delivery_status(ids[0], &pool).await.0 != "pending" {
+                break;
+            }
+            rocket::tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(("delivered".to_string(), 2, Some(204)), delivery_status(ids[0], &pool).await);
+        assert_eq!(ids[0].to_string(), received.lock().unwrap()[0].0[&DELIVERY_HEADER.to_lowercase()]);
+        assert_eq!(("failed".to_string(), 1, None), delivery_status(ids[1], &pool).await);
+        assert_eq!(("failed".to_string(), 3, None), delivery_status(ids[2], &pool).await);
+    }
+}
@@ -0,0 +1,231 @@
+//! The chance a technique (or group) is allowed, as written in weights files. All of these mean the
+//! same thing:
+//!
+//! ```yaml
+//! 40%      # a percentage, decimals allowed
+//! 0.4      # a probability from 0 to 1
+//! 400‰     # per mille
+//! 2/5      # a fraction
+//! 2 in 5   # odds
+//! ```
+//!
+//! as do the named levels in `games/levels` (`rare`, `common`...). A bare whole number other
+//! than 0 or 1 is rejected, since it isn't clear whether `40` means 40% or 40‰. Chances are kept
+//! as exact fractions and print back canonically: as a percentage when that's exact to four
+//! decimal places, or a fraction otherwise.
+
+use crate::games::games_path;
+use crate::rules::UserInputError;
+use crate::techniques::read_static;
+use lazy_static::lazy_static;
+use rand::Rng;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// `numerator / denominator`, always in lowest terms.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) struct Chance {
+    numerator: u32,
+    denominator: u32,
+}
+
+const fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+impl Chance {
+    /// `numerator / denominator` reduced, or `None` if that isn't between 0 and 1 or doesn't fit.
+    pub(crate) fn new(numerator: u64, denominator: u64) -> Option<Self> {
+        if denominator == 0 || numerator > denominator {
+            return None;
+        }
+        let g = gcd(numerator, denominator).max(1);
+        Some(Self {
+            numerator: u32::try_from(numerator / g).ok()?,
+            denominator: u32::try_from(denominator / g).ok()?,
+        })
+    }
+
+    /// For chances written in code; `per_thousand` must be at most 1000.
+    pub(crate) const fn per_thousand(per_thousand: u32) -> Self {
+        let g = gcd(per_thousand as u64, 1000) as u32;
+        Self {
+            numerator: per_thousand / g,
+            denominator: 1000 / g,
+        }
+    }
+
+    pub(crate) fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> bool {
+        rng.gen_ratio(self.numerator, self.denominator)
+    }
+
+    pub(crate) fn probability(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl fmt::Display for Chance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (n, d) = (self.numerator as u64, self.denominator as u64);
+        for decimals in 0..=4 {
+            let scale = 10u64.pow(decimals);
+            if (n * 100 * scale) % d == 0 {
+                let scaled = n * 100 * scale / d;
+                return if decimals == 0 {
+                    write!(f, "{}%", scaled)
+                } else {
+                    write!(f, "{}.{:0width$}%", scaled / scale, scaled % scale, width = decimals as usize)
+                };
+            }
+        }
+        write!(f, "{}/{}", n, d)
+    }
+}
+
+/// A non-negative decimal as a fraction, e.g. `12.5` as 125/10.
+fn parse_decimal(s: &str) -> Option<(u64, u64)> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let digits = |d: &str| d.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) || fraction.len() > 9 {
+        return None;
+    }
+    let denominator = 10u64.pow(fraction.len() as u32);
+    let whole: u64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let fraction: u64 = if fraction.is_empty() { 0 } else { fraction.parse().ok()? };
+    Some((whole.checked_mul(denominator)?.checked_add(fraction)?, denominator))
+}
+
+fn parse_whole(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Why a weight didn't parse. `NotAChance` is for input that doesn't look like a chance at all.
+#[derive(PartialEq, Debug)]
+pub(crate) enum ChanceError {
+    NotAChance,
+    Invalid(String),
+}
+
+/// The numeric forms, without named levels.
+fn parse_number(input: &str) -> Result<Chance, ChanceError> {
+    let s: String = input.trim().to_lowercase();
+    let too_big = || ChanceError::Invalid(format!("{} is more than 100%", input.trim()));
+    let (numerator, denominator) = if let Some(p) = s.strip_suffix('%') {
+        let (n, d) = parse_decimal(p.trim()).ok_or(ChanceError::NotAChance)?;
+        (n, d * 100)
+    } else if let Some(p) = s.strip_suffix('‰') {
+        let (n, d) = parse_decimal(p.trim()).ok_or(ChanceError::NotAChance)?;
+        (n, d * 1000)
+    } else if let Some((n, d)) = s.split_once('/').or_else(|| s.split_once(" in ")) {
+        let n = parse_whole(n.trim()).ok_or(ChanceError::NotAChance)?;
+        let d = parse_whole(d.trim()).ok_or(ChanceError::NotAChance)?;
+        if d == 0 {
+            return Err(ChanceError::Invalid(format!("{} divides by zero", input.trim())));
+        }
+        (n, d)
+    } else {
+        let (n, d) = parse_decimal(&s).ok_or(ChanceError::NotAChance)?;
+        if d == 1 && n > 1 {
+            return Err(ChanceError::Invalid(format!(
+                "{} is ambiguous: write {}% or {}‰, or a probability from 0 to 1",
+                s, s, s
+            )));
+        }
+        (n, d)
+    };
+    if numerator > denominator {
+        return Err(too_big());
+    }
+    Chance::new(numerator, denominator).ok_or_else(|| ChanceError::Invalid(format!("{} is too precise", input.trim())))
+}
+
+/// Read `games/levels`: a named chance per line, as `name: chance`.
+fn load_levels() -> Result<Vec<(&'static str, &'static str)>, UserInputError> {
+    let text = read_static(&games_path().join("levels"))?;
+    text.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| match l.split_once(':') {
+            Some((name, chance)) => Ok((name.trim(), chance.trim())),
+            None => Err(UserInputError {
+                err: format!("Expected `name: chance` in games/levels, got {}", l),
+            }),
+        })
+        .collect()
+}
+
+lazy_static! {
+    /// Named chances from `games/levels`, as (name, chance).
+    pub(crate) static ref WEIGHT_LEVELS: Vec<(&'static str, &'static str)> =
+        load_levels().unwrap_or_else(|e| panic!("Error loading weight levels: {}", e));
+}
+
+impl std::str::FromStr for Chance {
+    type Err = ChanceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match WEIGHT_LEVELS.iter().find(|(name, _)| name.eq_ignore_ascii_case(s.trim())) {
+            Some((name, level)) => parse_number(level)
+                .map_err(|_| ChanceError::Invalid(format!("The {} level in games/levels isn't a chance", name))),
+            None => parse_number(s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_number, Chance, ChanceError};
+    use super::WEIGHT_LEVELS;
+
+    fn chance(s: &str) -> Chance {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_forms() {
+        let two_fifths = Chance::new(2, 5).unwrap();
+        for s in ["40%", "40 %", "0.4", ".40", "400‰", "2/5", "4 / 10", "2 in 5"] {
+            assert_eq!(two_fifths, chance(s), "{}", s);
+        }
+        assert_eq!(Chance::new(1, 3).unwrap(), chance("1 in 3"));
+        assert_eq!(Chance::new(0, 1).unwrap(), chance("0"));
+        assert_eq!(Chance::new(1, 1).unwrap(), chance("1"));
+        assert_eq!(Chance::per_thousand(125), chance("12.5%"));
+        assert_eq!(Chance::per_thousand(50), chance("rare"));
+        assert_eq!(chance("RARE"), chance("rare"));
+
+        assert_eq!(Err(ChanceError::NotAChance), "sometimes".parse::<Chance>());
+        assert_eq!(Err(ChanceError::NotAChance), "-5%".parse::<Chance>());
+        assert_eq!(Err(ChanceError::NotAChance), "x123x".parse::<Chance>());
+        for s in ["40", "101%", "1.5", "3/2", "1/0"] {
+            assert!(matches!(s.parse::<Chance>(), Err(ChanceError::Invalid(_))), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_canonical_round_trip() {
+        for (s, canonical) in [
+            ("0.4", "40%"),
+            ("1 in 8", "12.5%"),
+            ("1/3", "1/3"),
+            ("0.12345", "12.345%"),
+            ("1/1000000", "0.0001%"),
+            ("1", "100%"),
+            ("0", "0%"),
+        ] {
+            assert_eq!(canonical, chance(s).to_string());
+            assert_eq!(chance(s), chance(canonical));
+        }
+        for (name, level) in WEIGHT_LEVELS.iter() {
+            assert!(parse_number(level).is_ok(), "level {}", name);
+        }
+    }
+}
@@ -72,7 +72,7 @@ document.addEventListener('DOMContentLoaded', function () {
         indicate_active_comparison_button('None');
     }
 
-    fetch('/comparisons')
+    fetch(comparisons_container.dataset.url)
         .then(response => response.json())
         .then(function (comparisons) {
             for (let comp of comparisons) {